@@ -54,6 +54,7 @@ fn main() {
                 save_file,
                 audio_player,
                 game_config,
+                narration,
             },
         col_encode_choice,
     } = Args::parser().with_help_default().parse_env_or_exit();
@@ -79,6 +80,7 @@ fn main() {
         Some(AutoPlay),
         None,
         Box::new(EnvNull),
+        narration,
     );
     use ColEncodeChoice as C;
     match col_encode_choice {
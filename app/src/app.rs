@@ -2,11 +2,15 @@ use crate::audio::{AppAudioPlayer, Audio};
 use crate::controls::Controls;
 use crate::depth;
 use crate::frontend::Frontend;
+pub use crate::game::{load_config, Config, GameConfig, Omniscient, RngSeed};
 use crate::game::{
-    AbilityChoice, AimEventRoutine, ExamineEventRoutine, GameData, GameEventRoutine,
-    GameOverEventRoutine, GameReturn, GameStatus, InjectedInput, ScreenCoord,
+    AbilityChoice, Achievement, AimEventRoutine, Difficulty, ExamineEventRoutine, GameData,
+    GameEventRoutine, GameMode, GameOverEventRoutine, GameReturn, GameStatus, InjectedInput,
+    Loadout, PickupResolution, Requirement, SafehouseAction, SafehouseOption, ScreenCoord, Unlock,
+    Unlocks,
 };
-pub use crate::game::{GameConfig, Omniscient, RngSeed};
+use crate::graphics::WindowSize;
+use crate::narration::Narration;
 use crate::render::{GameToRender, GameView, Mode};
 use crate::ui;
 use chargrid::input::*;
@@ -20,7 +24,7 @@ use menu::{
     fade_spec, FadeMenuInstanceView, MenuEntryStringFn, MenuEntryToRender, MenuInstanceChoose,
 };
 use render::{ColModifyDefaultForeground, ColModifyMap, Coord, Rgb24, Style};
-use slime99_game::player::Ability;
+use slime99_game::player::{Ability, AbilityTarget, Outcome};
 use std::collections::HashMap;
 
 #[derive(Clone, Copy)]
@@ -41,6 +45,8 @@ enum MainMenuEntry {
     Story,
     Keybindings,
     EndText,
+    Achievements,
+    UnlockList,
 }
 
 impl MainMenuEntry {
@@ -48,12 +54,27 @@ impl MainMenuEntry {
         use MainMenuEntry::*;
         let (items, hotkeys) = match frontend {
             Frontend::Graphical | Frontend::AnsiTerminal => (
-                vec![NewGame, Options, Keybindings, Story, Quit],
-                hashmap!['n' => NewGame, 'o' => Options, 'k' => Keybindings, 'b' => Story, 'q' => Quit],
+                vec![
+                    NewGame,
+                    Options,
+                    Keybindings,
+                    Story,
+                    Achievements,
+                    UnlockList,
+                    Quit,
+                ],
+                hashmap!['n' => NewGame, 'o' => Options, 'k' => Keybindings, 'b' => Story, 'a' => Achievements, 'u' => UnlockList, 'q' => Quit],
             ),
             Frontend::Web => (
-                vec![NewGame, Options, Keybindings, Story],
-                hashmap!['n' => NewGame, 'o' => Options, 'k' => Keybindings, 'b' => Story],
+                vec![
+                    NewGame,
+                    Options,
+                    Keybindings,
+                    Story,
+                    Achievements,
+                    UnlockList,
+                ],
+                hashmap!['n' => NewGame, 'o' => Options, 'k' => Keybindings, 'b' => Story, 'a' => Achievements, 'u' => UnlockList],
             ),
         };
         menu::MenuInstanceBuilder {
@@ -68,12 +89,29 @@ impl MainMenuEntry {
         use MainMenuEntry::*;
         let (items, hotkeys) = match frontend {
             Frontend::Graphical | Frontend::AnsiTerminal => (
-                vec![NewGame, Options, Keybindings, Story, EndText, Quit],
-                hashmap!['n' => NewGame, 'o' => Options, 'k' => Keybindings, 'b' => Story, 'e' => EndText, 'q' => Quit],
+                vec![
+                    NewGame,
+                    Options,
+                    Keybindings,
+                    Story,
+                    EndText,
+                    Achievements,
+                    UnlockList,
+                    Quit,
+                ],
+                hashmap!['n' => NewGame, 'o' => Options, 'k' => Keybindings, 'b' => Story, 'e' => EndText, 'a' => Achievements, 'u' => UnlockList, 'q' => Quit],
             ),
             Frontend::Web => (
-                vec![NewGame, Options, Keybindings, Story, EndText],
-                hashmap!['n' => NewGame, 'o' => Options, 'k' => Keybindings, 'b' => Story, 'e' => EndText],
+                vec![
+                    NewGame,
+                    Options,
+                    Keybindings,
+                    Story,
+                    EndText,
+                    Achievements,
+                    UnlockList,
+                ],
+                hashmap!['n' => NewGame, 'o' => Options, 'k' => Keybindings, 'b' => Story, 'e' => EndText, 'a' => Achievements, 'u' => UnlockList],
             ),
         };
         menu::MenuInstanceBuilder {
@@ -95,13 +133,24 @@ impl MainMenuEntry {
                     Options,
                     Keybindings,
                     Story,
+                    Achievements,
+                    UnlockList,
                     Clear,
                 ],
-                hashmap!['r' => Resume, 'q' => SaveQuit, 'o' => Options, 'k' => Keybindings, 'b'=> Story, 'n' => NewGame, 'c' => Clear],
+                hashmap!['r' => Resume, 'q' => SaveQuit, 'o' => Options, 'k' => Keybindings, 'b'=> Story, 'a' => Achievements, 'u' => UnlockList, 'n' => NewGame, 'c' => Clear],
             ),
             Frontend::Web => (
-                vec![Resume, Save, NewGame, Options, Story, Clear],
-                hashmap!['r' => Resume, 's' => Save, 'o' => Options, 'k' => Keybindings, 'b' => Story, 'n' => NewGame, 'c' => Clear],
+                vec![
+                    Resume,
+                    Save,
+                    NewGame,
+                    Options,
+                    Story,
+                    Achievements,
+                    UnlockList,
+                    Clear,
+                ],
+                hashmap!['r' => Resume, 's' => Save, 'o' => Options, 'k' => Keybindings, 'b' => Story, 'a' => Achievements, 'u' => UnlockList, 'n' => NewGame, 'c' => Clear],
             ),
         };
         menu::MenuInstanceBuilder {
@@ -120,7 +169,13 @@ struct AppData {
     main_menu: menu::MenuInstanceChooseOrEscape<MainMenuEntry>,
     main_menu_type: MainMenuType,
     options_menu: menu::MenuInstanceChooseOrEscape<OrBack<OptionsMenuEntry>>,
+    difficulty_menu: menu::MenuInstanceChooseOrEscape<Difficulty>,
+    mode_menu: menu::MenuInstanceChooseOrEscape<GameMode>,
+    loadout_menu: menu::MenuInstanceChooseOrEscape<Loadout>,
     level_change_menu: Option<menu::MenuInstanceChooseOrEscape<Ability>>,
+    pickup_menu: Option<menu::MenuInstanceChooseOrEscape<PickupResolution>>,
+    bury_menu: Option<menu::MenuInstanceChooseOrEscape<usize>>,
+    safehouse_menu: Option<menu::MenuInstanceChooseOrEscape<SafehouseAction>>,
     last_mouse_coord: Coord,
     env: Box<dyn Env>,
     won: bool,
@@ -130,7 +185,13 @@ struct AppView {
     game: GameView,
     main_menu: FadeMenuInstanceView,
     options_menu: FadeMenuInstanceView,
+    difficulty_menu: FadeMenuInstanceView,
+    mode_menu: FadeMenuInstanceView,
+    loadout_menu: FadeMenuInstanceView,
     level_change_menu: FadeMenuInstanceView,
+    pickup_menu: FadeMenuInstanceView,
+    bury_menu: FadeMenuInstanceView,
+    safehouse_menu: FadeMenuInstanceView,
 }
 
 impl AppData {
@@ -144,6 +205,7 @@ impl AppData {
         rng_seed: RngSeed,
         fullscreen: Option<Fullscreen>,
         env: Box<dyn Env>,
+        narration: Option<Narration>,
     ) -> Self {
         let mut game_data = GameData::new(
             game_config,
@@ -153,6 +215,7 @@ impl AppData {
             audio_player,
             rng_seed,
             frontend,
+            narration,
         );
         if env.fullscreen_supported() {
             let mut config = game_data.config();
@@ -164,7 +227,20 @@ impl AppData {
         }
         Self {
             options_menu: OptionsMenuEntry::instance(&env),
+            difficulty_menu: difficulty_menu_instance(),
+            mode_menu: menu::MenuInstanceBuilder {
+                items: vec![GameMode::Campaign, GameMode::Endless],
+                selected_index: 0,
+                hotkeys: Some(hashmap!['c' => GameMode::Campaign, 'e' => GameMode::Endless]),
+            }
+            .build()
+            .unwrap()
+            .into_choose_or_escape(),
+            loadout_menu: loadout_menu_instance(&game_data.unlocks()),
             level_change_menu: None,
+            pickup_menu: None,
+            bury_menu: None,
+            safehouse_menu: None,
             frontend,
             game: game_data,
             main_menu: MainMenuEntry::init(frontend).into_choose_or_escape(),
@@ -174,6 +250,19 @@ impl AppData {
             won: false,
         }
     }
+
+    /// Saves the size of the window so the next session opens at the same size. A fullscreen
+    /// window covers the monitor, so its size isn't worth remembering.
+    fn remember_window_size(&mut self) {
+        if self.env.fullscreen() {
+            return;
+        }
+        if let Some(window_size) = self.env.window_size() {
+            let mut config = self.game.config();
+            config.graphics.window_size = Some(window_size);
+            self.game.set_config(config);
+        }
+    }
 }
 
 impl AppView {
@@ -214,7 +303,13 @@ impl AppView {
             game: GameView::new(),
             main_menu: FadeMenuInstanceView::new(spec.clone()),
             options_menu: FadeMenuInstanceView::new(spec.clone()),
+            difficulty_menu: FadeMenuInstanceView::new(spec.clone()),
+            mode_menu: FadeMenuInstanceView::new(spec.clone()),
+            loadout_menu: FadeMenuInstanceView::new(spec.clone()),
             level_change_menu: FadeMenuInstanceView::new(spec.clone()),
+            pickup_menu: FadeMenuInstanceView::new(spec.clone()),
+            bury_menu: FadeMenuInstanceView::new(spec.clone()),
+            safehouse_menu: FadeMenuInstanceView::new(spec.clone()),
         }
     }
 }
@@ -300,6 +395,109 @@ where
     }
 }
 
+struct SafehouseMenu<'b, 'e, 'v, E: EventRoutine>(&'b mut EventRoutineView<'e, 'v, E>);
+impl<'b, 'a, 'e, 'v, E> View<&'a AppData> for SafehouseMenu<'b, 'e, 'v, E>
+where
+    E: EventRoutine<View = AppView, Data = AppData>,
+{
+    fn view<F: Frame, C: ColModify>(
+        &mut self,
+        app_data: &'a AppData,
+        context: ViewContext<C>,
+        frame: &mut F,
+    ) {
+        let credits = app_data
+            .game
+            .instance()
+            .map(|instance| instance.game().player().credits)
+            .unwrap_or(0);
+        text::StringView::new(
+            Style::new()
+                .with_foreground(Rgb24::new_grey(255))
+                .with_bold(true),
+            text::wrap::Word::new(),
+        )
+        .view(
+            format!(
+                "You reach a safehouse.\nCredits: {}\nSpend them before heading down:",
+                credits
+            ),
+            context.add_offset(Coord::new(1, 1)),
+            frame,
+        );
+        self.0
+            .view(app_data, context.add_offset(Coord::new(1, 5)), frame);
+    }
+}
+
+struct PickupMenu<'b, 'e, 'v, E: EventRoutine>(&'b mut EventRoutineView<'e, 'v, E>);
+impl<'b, 'a, 'e, 'v, E> View<&'a AppData> for PickupMenu<'b, 'e, 'v, E>
+where
+    E: EventRoutine<View = AppView, Data = AppData>,
+{
+    fn view<F: Frame, C: ColModify>(
+        &mut self,
+        app_data: &'a AppData,
+        context: ViewContext<C>,
+        frame: &mut F,
+    ) {
+        let mut text = String::new();
+        if let Some(card) = app_data
+            .game
+            .instance()
+            .and_then(|instance| instance.game().pending_pickup())
+        {
+            let stack = match card {
+                Outcome::Attack(_) => "Attack",
+                Outcome::Defend(_) => "Defend",
+                Outcome::Tech(_) => "Tech",
+            };
+            text.push_str("You found: ");
+            ui::write_card(card, &mut text);
+            text.push_str(&format!("\nYour {} stack is full.\nChoose now:", stack));
+        }
+        text::StringView::new(
+            Style::new()
+                .with_foreground(Rgb24::new_grey(255))
+                .with_bold(true),
+            text::wrap::Word::new(),
+        )
+        .view(text, context.add_offset(Coord::new(1, 1)), frame);
+        self.0
+            .view(app_data, context.add_offset(Coord::new(1, 5)), frame);
+    }
+}
+
+struct BuryMenu<'b, 'e, 'v, E: EventRoutine> {
+    event_routine_view: &'b mut EventRoutineView<'e, 'v, E>,
+    stack: &'static str,
+}
+impl<'b, 'a, 'e, 'v, E> View<&'a AppData> for BuryMenu<'b, 'e, 'v, E>
+where
+    E: EventRoutine<View = AppView, Data = AppData>,
+{
+    fn view<F: Frame, C: ColModify>(
+        &mut self,
+        app_data: &'a AppData,
+        context: ViewContext<C>,
+        frame: &mut F,
+    ) {
+        let text = format!(
+            "Bury the top card of your {} stack.\nChoose where it goes:",
+            self.stack
+        );
+        text::StringView::new(
+            Style::new()
+                .with_foreground(Rgb24::new_grey(255))
+                .with_bold(true),
+            text::wrap::Word::new(),
+        )
+        .view(text, context.add_offset(Coord::new(1, 1)), frame);
+        self.event_routine_view
+            .view(app_data, context.add_offset(Coord::new(1, 4)), frame);
+    }
+}
+
 struct InitMenu<'e, 'v, E: EventRoutine>(EventRoutineView<'e, 'v, E>);
 impl<'a, 'e, 'v, E> View<&'a AppData> for InitMenu<'e, 'v, E>
 where
@@ -391,6 +589,8 @@ impl EventRoutine for TextOverlay {
                     mouse_coord: None,
                     mode: Mode::Normal,
                     action_error: None,
+                    palette: data.game.config().theme.palette(),
+                    tileset: data.game.tileset(),
                 },
                 context.compose_col_modify(
                     ColModifyDefaultForeground(Rgb24::new_grey(255)).compose(ColModifyMap(
@@ -452,6 +652,8 @@ impl Decorate for DecorateMainMenu {
                     mouse_coord: None,
                     mode: Mode::Normal,
                     action_error: None,
+                    palette: data.game.config().theme.palette(),
+                    tileset: data.game.tileset(),
                 },
                 context.compose_col_modify(
                     ColModifyDefaultForeground(Rgb24::new_grey(255)).compose(ColModifyMap(
@@ -607,6 +809,8 @@ impl Decorate for DecorateLevelChangeMenu {
                     mouse_coord: None,
                     mode: Mode::Normal,
                     action_error: None,
+                    palette: data.game.config().theme.palette(),
+                    tileset: data.game.tileset(),
                 },
                 context.compose_col_modify(
                     ColModifyDefaultForeground(Rgb24::new_grey(255)).compose(ColModifyMap(
@@ -667,72 +871,31 @@ fn level_change_menu(
     })
 }
 
-#[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq)]
-enum OrBack<T> {
-    Selection(T),
-    Back,
-}
-
-#[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq)]
-enum OptionsMenuEntry {
-    ToggleMusic,
-    ToggleSfx,
-    ToggleFullscreen,
-}
-
-impl OptionsMenuEntry {
-    fn instance(env: &Box<dyn Env>) -> menu::MenuInstanceChooseOrEscape<OrBack<OptionsMenuEntry>> {
-        use OptionsMenuEntry::*;
-        use OrBack::*;
-        menu::MenuInstanceBuilder {
-            items: if env.fullscreen_supported() {
-                vec![
-                    Selection(ToggleMusic),
-                    Selection(ToggleSfx),
-                    Selection(ToggleFullscreen),
-                    Back,
-                ]
-            } else {
-                vec![Selection(ToggleMusic), Selection(ToggleSfx), Back]
-            },
-            selected_index: 0,
-            hotkeys: Some(hashmap![
-                'm' => Selection(ToggleMusic),
-                's' => Selection(ToggleSfx),
-                'f' => Selection(ToggleFullscreen),
-            ]),
-        }
-        .build()
-        .unwrap()
-        .into_choose_or_escape()
-    }
-}
-
-struct SelectOptionsMenu;
-impl ViewSelector for SelectOptionsMenu {
+struct SelectSafehouseMenu;
+impl ViewSelector for SelectSafehouseMenu {
     type ViewInput = AppView;
     type ViewOutput = FadeMenuInstanceView;
     fn view<'a>(&self, input: &'a Self::ViewInput) -> &'a Self::ViewOutput {
-        &input.options_menu
+        &input.safehouse_menu
     }
     fn view_mut<'a>(&self, input: &'a mut Self::ViewInput) -> &'a mut Self::ViewOutput {
-        &mut input.options_menu
+        &mut input.safehouse_menu
     }
 }
-impl DataSelector for SelectOptionsMenu {
+impl DataSelector for SelectSafehouseMenu {
     type DataInput = AppData;
-    type DataOutput = menu::MenuInstanceChooseOrEscape<OrBack<OptionsMenuEntry>>;
+    type DataOutput = menu::MenuInstanceChooseOrEscape<SafehouseAction>;
     fn data<'a>(&self, input: &'a Self::DataInput) -> &'a Self::DataOutput {
-        &input.options_menu
+        input.safehouse_menu.as_ref().unwrap()
     }
     fn data_mut<'a>(&self, input: &'a mut Self::DataInput) -> &'a mut Self::DataOutput {
-        &mut input.options_menu
+        input.safehouse_menu.as_mut().unwrap()
     }
 }
-impl Selector for SelectOptionsMenu {}
+impl Selector for SelectSafehouseMenu {}
 
-struct DecorateOptionsMenu;
-impl Decorate for DecorateOptionsMenu {
+struct DecorateSafehouseMenu;
+impl Decorate for DecorateSafehouseMenu {
     type View = AppView;
     type Data = AppData;
     fn view<E, F, C>(
@@ -753,7 +916,10 @@ impl Decorate for DecorateOptionsMenu {
                     rgb24: Rgb24::new_grey(0),
                     view: BorderView {
                         style: &BorderStyle::new(),
-                        view: &mut event_routine_view,
+                        view: PadView {
+                            size: Size::new(0, 1),
+                            view: SafehouseMenu(&mut event_routine_view),
+                        },
                     },
                 },
             }
@@ -765,6 +931,8 @@ impl Decorate for DecorateOptionsMenu {
                     mouse_coord: None,
                     mode: Mode::Normal,
                     action_error: None,
+                    palette: data.game.config().theme.palette(),
+                    tileset: data.game.tileset(),
                 },
                 context.compose_col_modify(
                     ColModifyDefaultForeground(Rgb24::new_grey(255)).compose(ColModifyMap(
@@ -783,96 +951,844 @@ impl Decorate for DecorateOptionsMenu {
     }
 }
 
-fn options_menu() -> impl EventRoutine<
-    Return = Result<OrBack<OptionsMenuEntry>, menu::Escape>,
+fn safehouse_menu() -> impl EventRoutine<
+    Return = Result<SafehouseAction, menu::Escape>,
     Data = AppData,
     View = AppView,
     Event = CommonEvent,
 > {
     SideEffectThen::new_with_view(|data: &mut AppData, _: &_| {
-        let config = data.game.config();
-        let fullscreen = data.env.fullscreen();
-        let fullscreen_requires_restart = data.env.fullscreen_requires_restart();
+        let options: Vec<SafehouseOption> =
+            data.game.instance().unwrap().game().safehouse_options();
+        let mut hotkeys = hashmap!['l' => SafehouseAction::Leave];
+        for (i, option) in options
+            .iter()
+            .filter(|option| option.action != SafehouseAction::Leave)
+            .take(9)
+            .enumerate()
+        {
+            hotkeys.insert(
+                std::char::from_digit(i as u32 + 1, 10).unwrap(),
+                option.action,
+            );
+        }
+        data.safehouse_menu = Some(
+            menu::MenuInstanceBuilder {
+                items: options.iter().map(|option| option.action).collect(),
+                selected_index: 0,
+                hotkeys: Some(hotkeys),
+            }
+            .build()
+            .unwrap()
+            .into_choose_or_escape(),
+        );
         let menu_entry_string = MenuEntryStringFn::new(
-            move |entry: MenuEntryToRender<OrBack<OptionsMenuEntry>>, buf: &mut String| {
+            move |entry: MenuEntryToRender<SafehouseAction>, buf: &mut String| {
                 use std::fmt::Write;
-                use OptionsMenuEntry::*;
-                use OrBack::*;
-                match entry.entry {
-                    Back => write!(buf, "back").unwrap(),
-                    Selection(entry) => match entry {
-                        ToggleMusic => write!(
-                            buf,
-                            "(m) Music enabled [{}]",
-                            if config.music { '*' } else { ' ' }
-                        )
-                        .unwrap(),
-                        ToggleSfx => write!(
-                            buf,
-                            "(s) Sfx enabled [{}]",
-                            if config.sfx { '*' } else { ' ' }
-                        )
-                        .unwrap(),
-                        ToggleFullscreen => {
-                            if fullscreen_requires_restart {
-                                write!(
-                                    buf,
-                                    "(f) Fullscreen (requires restart) [{}]",
-                                    if fullscreen { '*' } else { ' ' }
-                                )
-                                .unwrap()
-                            } else {
-                                write!(
-                                    buf,
-                                    "(f) Fullscreen [{}]",
-                                    if fullscreen { '*' } else { ' ' }
-                                )
-                                .unwrap()
-                            }
-                        }
-                    },
+                let option = &options[entry.index];
+                if option.action == SafehouseAction::Leave {
+                    write!(buf, "(l) Leave").unwrap();
+                    return;
+                }
+                if entry.index < 9 {
+                    write!(buf, "({}) ", entry.index + 1).unwrap();
+                } else {
+                    write!(buf, "    ").unwrap();
+                }
+                match option.action {
+                    SafehouseAction::RemoveCurse(_) => write!(buf, "Remove ").unwrap(),
+                    SafehouseAction::Upgrade(_) => write!(buf, "Upgrade to ").unwrap(),
+                    SafehouseAction::Buy(_) => write!(buf, "Buy ").unwrap(),
+                    SafehouseAction::Sacrifice(_) => write!(buf, "Sacrifice ").unwrap(),
+                    SafehouseAction::Leave => (),
+                }
+                if let Some(card) = option.card {
+                    ui::write_card(card, buf);
+                }
+                if option.cost > 0 {
+                    write!(buf, " (-{})", option.cost).unwrap();
+                }
+                if option.gain > 0 {
+                    write!(buf, " (+{})", option.gain).unwrap();
                 }
             },
         );
         menu::FadeMenuInstanceRoutine::new(menu_entry_string)
-            .select(SelectOptionsMenu)
-            .decorated(DecorateOptionsMenu)
+            .select(SelectSafehouseMenu)
+            .decorated(DecorateSafehouseMenu)
     })
 }
 
-fn options_menu_cycle(
-) -> impl EventRoutine<Return = (), Data = AppData, View = AppView, Event = CommonEvent> {
-    make_either!(Ei = A | B);
-    use OptionsMenuEntry::*;
-    use OrBack::*;
-    Ei::A(options_menu()).repeat(|choice| match choice {
-        Ok(Back) | Err(menu::Escape) => Handled::Return(()),
-        Ok(Selection(selection)) => Handled::Continue(Ei::B(SideEffectThen::new_with_view(
-            move |data: &mut AppData, _: &_| {
-                let mut config = data.game.config();
-                match selection {
-                    ToggleMusic => config.music = !config.music,
-                    ToggleSfx => config.sfx = !config.sfx,
-                    ToggleFullscreen => {
-                        data.env.set_fullscreen(!data.env.fullscreen());
-                        config.fullscreen = data.env.fullscreen();
-                    }
-                }
-                data.game.set_config(config);
-                options_menu()
-            },
-        ))),
-    })
+struct SelectPickupMenu;
+impl ViewSelector for SelectPickupMenu {
+    type ViewInput = AppView;
+    type ViewOutput = FadeMenuInstanceView;
+    fn view<'a>(&self, input: &'a Self::ViewInput) -> &'a Self::ViewOutput {
+        &input.pickup_menu
+    }
+    fn view_mut<'a>(&self, input: &'a mut Self::ViewInput) -> &'a mut Self::ViewOutput {
+        &mut input.pickup_menu
+    }
 }
+impl DataSelector for SelectPickupMenu {
+    type DataInput = AppData;
+    type DataOutput = menu::MenuInstanceChooseOrEscape<PickupResolution>;
+    fn data<'a>(&self, input: &'a Self::DataInput) -> &'a Self::DataOutput {
+        input.pickup_menu.as_ref().unwrap()
+    }
+    fn data_mut<'a>(&self, input: &'a mut Self::DataInput) -> &'a mut Self::DataOutput {
+        input.pickup_menu.as_mut().unwrap()
+    }
+}
+impl Selector for SelectPickupMenu {}
 
-#[derive(Clone, Copy)]
-pub struct AutoPlay;
-
-#[derive(Clone, Copy)]
-pub struct FirstRun;
-
-fn main_menu(
-    auto_play: Option<AutoPlay>,
+struct DecoratePickupMenu;
+impl Decorate for DecoratePickupMenu {
+    type View = AppView;
+    type Data = AppData;
+    fn view<E, F, C>(
+        &self,
+        data: &Self::Data,
+        mut event_routine_view: EventRoutineView<E>,
+        context: ViewContext<C>,
+        frame: &mut F,
+    ) where
+        E: EventRoutine<Data = Self::Data, View = Self::View>,
+        F: Frame,
+        C: ColModify,
+    {
+        if let Some(instance) = data.game.instance() {
+            AlignView {
+                alignment: Alignment::centre(),
+                view: FillBackgroundView {
+                    rgb24: Rgb24::new_grey(0),
+                    view: BorderView {
+                        style: &BorderStyle::new(),
+                        view: PadView {
+                            size: Size::new(0, 1),
+                            view: PickupMenu(&mut event_routine_view),
+                        },
+                    },
+                },
+            }
+            .view(data, context.add_depth(depth::GAME_MAX + 1), frame);
+            event_routine_view.view.game.view(
+                GameToRender {
+                    game: instance.game(),
+                    status: GameStatus::Playing,
+                    mouse_coord: None,
+                    mode: Mode::Normal,
+                    action_error: None,
+                    palette: data.game.config().theme.palette(),
+                    tileset: data.game.tileset(),
+                },
+                context.compose_col_modify(
+                    ColModifyDefaultForeground(Rgb24::new_grey(255)).compose(ColModifyMap(
+                        |col: Rgb24| col.saturating_scalar_mul_div(1, 3),
+                    )),
+                ),
+                frame,
+            );
+        } else {
+            AlignView {
+                view: InitMenu(event_routine_view),
+                alignment: Alignment::centre(),
+            }
+            .view(&data, context, frame);
+        }
+    }
+}
+
+fn pickup_menu(
+    card: Outcome,
+) -> impl EventRoutine<
+    Return = Result<PickupResolution, menu::Escape>,
+    Data = AppData,
+    View = AppView,
+    Event = CommonEvent,
+> {
+    SideEffectThen::new_with_view(move |data: &mut AppData, _: &_| {
+        let player = data.game.instance().unwrap().game().player();
+        let (target, deck): (_, Vec<Outcome>) = match card {
+            Outcome::Attack(_) => (
+                AbilityTarget::Attack,
+                player.attack.iter().cloned().map(Outcome::Attack).collect(),
+            ),
+            Outcome::Defend(_) => (
+                AbilityTarget::Defend,
+                player.defend.iter().cloned().map(Outcome::Defend).collect(),
+            ),
+            Outcome::Tech(_) => (
+                AbilityTarget::Tech,
+                player.tech.iter().cloned().map(Outcome::Tech).collect(),
+            ),
+        };
+        let peeking = player.peeking == Some(target);
+        let mut items = vec![PickupResolution::SwapTop];
+        items.extend((0..deck.len()).map(PickupResolution::Discard));
+        items.push(PickupResolution::Leave);
+        let mut hotkeys =
+            hashmap!['s' => PickupResolution::SwapTop, 'l' => PickupResolution::Leave];
+        for i in 0..deck.len().min(9) {
+            hotkeys.insert(
+                std::char::from_digit(i as u32 + 1, 10).unwrap(),
+                PickupResolution::Discard(i),
+            );
+        }
+        data.pickup_menu = Some(
+            menu::MenuInstanceBuilder {
+                items,
+                selected_index: 0,
+                hotkeys: Some(hotkeys),
+            }
+            .build()
+            .unwrap()
+            .into_choose_or_escape(),
+        );
+        let menu_entry_string = MenuEntryStringFn::new(
+            move |entry: MenuEntryToRender<PickupResolution>, buf: &mut String| {
+                use std::fmt::Write;
+                match *entry.entry {
+                    PickupResolution::SwapTop => {
+                        write!(buf, "(s) Swap with ").unwrap();
+                        ui::write_card(deck[0], buf);
+                    }
+                    PickupResolution::Discard(i) => {
+                        if i < 9 {
+                            write!(buf, "({}) Discard ", i + 1).unwrap();
+                        } else {
+                            write!(buf, "    Discard ").unwrap();
+                        }
+                        ui::write_deck_card(deck[i], i, peeking, buf);
+                    }
+                    PickupResolution::Leave => write!(buf, "(l) Leave it").unwrap(),
+                }
+            },
+        );
+        menu::FadeMenuInstanceRoutine::new(menu_entry_string)
+            .select(SelectPickupMenu)
+            .decorated(DecoratePickupMenu)
+    })
+}
+
+struct SelectBuryMenu;
+impl ViewSelector for SelectBuryMenu {
+    type ViewInput = AppView;
+    type ViewOutput = FadeMenuInstanceView;
+    fn view<'a>(&self, input: &'a Self::ViewInput) -> &'a Self::ViewOutput {
+        &input.bury_menu
+    }
+    fn view_mut<'a>(&self, input: &'a mut Self::ViewInput) -> &'a mut Self::ViewOutput {
+        &mut input.bury_menu
+    }
+}
+impl DataSelector for SelectBuryMenu {
+    type DataInput = AppData;
+    type DataOutput = menu::MenuInstanceChooseOrEscape<usize>;
+    fn data<'a>(&self, input: &'a Self::DataInput) -> &'a Self::DataOutput {
+        input.bury_menu.as_ref().unwrap()
+    }
+    fn data_mut<'a>(&self, input: &'a mut Self::DataInput) -> &'a mut Self::DataOutput {
+        input.bury_menu.as_mut().unwrap()
+    }
+}
+impl Selector for SelectBuryMenu {}
+
+struct DecorateBuryMenu {
+    stack: &'static str,
+}
+impl Decorate for DecorateBuryMenu {
+    type View = AppView;
+    type Data = AppData;
+    fn view<E, F, C>(
+        &self,
+        data: &Self::Data,
+        mut event_routine_view: EventRoutineView<E>,
+        context: ViewContext<C>,
+        frame: &mut F,
+    ) where
+        E: EventRoutine<Data = Self::Data, View = Self::View>,
+        F: Frame,
+        C: ColModify,
+    {
+        if let Some(instance) = data.game.instance() {
+            AlignView {
+                alignment: Alignment::centre(),
+                view: FillBackgroundView {
+                    rgb24: Rgb24::new_grey(0),
+                    view: BorderView {
+                        style: &BorderStyle::new(),
+                        view: PadView {
+                            size: Size::new(0, 1),
+                            view: BuryMenu {
+                                event_routine_view: &mut event_routine_view,
+                                stack: self.stack,
+                            },
+                        },
+                    },
+                },
+            }
+            .view(data, context.add_depth(depth::GAME_MAX + 1), frame);
+            event_routine_view.view.game.view(
+                GameToRender {
+                    game: instance.game(),
+                    status: GameStatus::Playing,
+                    mouse_coord: None,
+                    mode: Mode::Normal,
+                    action_error: None,
+                    palette: data.game.config().theme.palette(),
+                    tileset: data.game.tileset(),
+                },
+                context.compose_col_modify(
+                    ColModifyDefaultForeground(Rgb24::new_grey(255)).compose(ColModifyMap(
+                        |col: Rgb24| col.saturating_scalar_mul_div(1, 3),
+                    )),
+                ),
+                frame,
+            );
+        } else {
+            AlignView {
+                view: InitMenu(event_routine_view),
+                alignment: Alignment::centre(),
+            }
+            .view(&data, context, frame);
+        }
+    }
+}
+
+fn bury_menu(
+    target: AbilityTarget,
+) -> impl EventRoutine<
+    Return = Result<usize, menu::Escape>,
+    Data = AppData,
+    View = AppView,
+    Event = CommonEvent,
+> {
+    SideEffectThen::new_with_view(move |data: &mut AppData, _: &_| {
+        let player = data.game.instance().unwrap().game().player();
+        let (stack, deck): (_, Vec<Outcome>) = match target {
+            AbilityTarget::Attack => (
+                "Attack",
+                player.attack.iter().cloned().map(Outcome::Attack).collect(),
+            ),
+            AbilityTarget::Defend => (
+                "Defend",
+                player.defend.iter().cloned().map(Outcome::Defend).collect(),
+            ),
+            AbilityTarget::Tech => (
+                "Tech",
+                player.tech.iter().cloned().map(Outcome::Tech).collect(),
+            ),
+        };
+        let peeking = player.peeking == Some(target);
+        let items = (1..deck.len()).collect::<Vec<_>>();
+        let hotkeys = (1..deck.len().min(10))
+            .map(|depth| (std::char::from_digit(depth as u32, 10).unwrap(), depth))
+            .collect::<HashMap<_, _>>();
+        data.bury_menu = Some(
+            menu::MenuInstanceBuilder {
+                items,
+                selected_index: 0,
+                hotkeys: Some(hotkeys),
+            }
+            .build()
+            .unwrap()
+            .into_choose_or_escape(),
+        );
+        let menu_entry_string =
+            MenuEntryStringFn::new(move |entry: MenuEntryToRender<usize>, buf: &mut String| {
+                use std::fmt::Write;
+                let depth = *entry.entry;
+                if depth < 10 {
+                    write!(buf, "({}) Below ", depth).unwrap();
+                } else {
+                    write!(buf, "    Below ").unwrap();
+                }
+                ui::write_deck_card(deck[depth], depth, peeking, buf);
+            });
+        menu::FadeMenuInstanceRoutine::new(menu_entry_string)
+            .select(SelectBuryMenu)
+            .decorated(DecorateBuryMenu { stack })
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq)]
+enum OrBack<T> {
+    Selection(T),
+    Back,
+}
+
+#[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq)]
+enum OptionsMenuEntry {
+    ToggleMusic,
+    ToggleSfx,
+    ToggleFullscreen,
+    ToggleUnlockEverything,
+    CycleTheme,
+    ToggleAsciiGlyphs,
+    CycleScale,
+    CycleFont,
+}
+
+impl OptionsMenuEntry {
+    fn instance(env: &Box<dyn Env>) -> menu::MenuInstanceChooseOrEscape<OrBack<OptionsMenuEntry>> {
+        use OptionsMenuEntry::*;
+        use OrBack::*;
+        let mut items = vec![Selection(ToggleMusic), Selection(ToggleSfx)];
+        if env.fullscreen_supported() {
+            items.push(Selection(ToggleFullscreen));
+            items.push(Selection(CycleScale));
+            items.push(Selection(CycleFont));
+        }
+        items.extend(vec![
+            Selection(CycleTheme),
+            Selection(ToggleAsciiGlyphs),
+            Selection(ToggleUnlockEverything),
+            Back,
+        ]);
+        menu::MenuInstanceBuilder {
+            items,
+            selected_index: 0,
+            hotkeys: Some(hashmap![
+                'm' => Selection(ToggleMusic),
+                's' => Selection(ToggleSfx),
+                'f' => Selection(ToggleFullscreen),
+                'z' => Selection(CycleScale),
+                't' => Selection(CycleFont),
+                'c' => Selection(CycleTheme),
+                'a' => Selection(ToggleAsciiGlyphs),
+                'u' => Selection(ToggleUnlockEverything),
+            ]),
+        }
+        .build()
+        .unwrap()
+        .into_choose_or_escape()
+    }
+}
+
+struct SelectOptionsMenu;
+impl ViewSelector for SelectOptionsMenu {
+    type ViewInput = AppView;
+    type ViewOutput = FadeMenuInstanceView;
+    fn view<'a>(&self, input: &'a Self::ViewInput) -> &'a Self::ViewOutput {
+        &input.options_menu
+    }
+    fn view_mut<'a>(&self, input: &'a mut Self::ViewInput) -> &'a mut Self::ViewOutput {
+        &mut input.options_menu
+    }
+}
+impl DataSelector for SelectOptionsMenu {
+    type DataInput = AppData;
+    type DataOutput = menu::MenuInstanceChooseOrEscape<OrBack<OptionsMenuEntry>>;
+    fn data<'a>(&self, input: &'a Self::DataInput) -> &'a Self::DataOutput {
+        &input.options_menu
+    }
+    fn data_mut<'a>(&self, input: &'a mut Self::DataInput) -> &'a mut Self::DataOutput {
+        &mut input.options_menu
+    }
+}
+impl Selector for SelectOptionsMenu {}
+
+struct DecorateOptionsMenu;
+impl Decorate for DecorateOptionsMenu {
+    type View = AppView;
+    type Data = AppData;
+    fn view<E, F, C>(
+        &self,
+        data: &Self::Data,
+        mut event_routine_view: EventRoutineView<E>,
+        context: ViewContext<C>,
+        frame: &mut F,
+    ) where
+        E: EventRoutine<Data = Self::Data, View = Self::View>,
+        F: Frame,
+        C: ColModify,
+    {
+        if let Some(instance) = data.game.instance() {
+            AlignView {
+                alignment: Alignment::centre(),
+                view: FillBackgroundView {
+                    rgb24: Rgb24::new_grey(0),
+                    view: BorderView {
+                        style: &BorderStyle::new(),
+                        view: &mut event_routine_view,
+                    },
+                },
+            }
+            .view(data, context.add_depth(depth::GAME_MAX + 1), frame);
+            event_routine_view.view.game.view(
+                GameToRender {
+                    game: instance.game(),
+                    status: GameStatus::Playing,
+                    mouse_coord: None,
+                    mode: Mode::Normal,
+                    action_error: None,
+                    palette: data.game.config().theme.palette(),
+                    tileset: data.game.tileset(),
+                },
+                context.compose_col_modify(
+                    ColModifyDefaultForeground(Rgb24::new_grey(255)).compose(ColModifyMap(
+                        |col: Rgb24| col.saturating_scalar_mul_div(1, 3),
+                    )),
+                ),
+                frame,
+            );
+        } else {
+            AlignView {
+                view: InitMenu(event_routine_view),
+                alignment: Alignment::centre(),
+            }
+            .view(&data, context, frame);
+        }
+    }
+}
+
+fn options_menu() -> impl EventRoutine<
+    Return = Result<OrBack<OptionsMenuEntry>, menu::Escape>,
+    Data = AppData,
+    View = AppView,
+    Event = CommonEvent,
+> {
+    SideEffectThen::new_with_view(|data: &mut AppData, _: &_| {
+        let config = data.game.config();
+        let fullscreen = data.env.fullscreen();
+        let fullscreen_requires_restart = data.env.fullscreen_requires_restart();
+        let menu_entry_string = MenuEntryStringFn::new(
+            move |entry: MenuEntryToRender<OrBack<OptionsMenuEntry>>, buf: &mut String| {
+                use std::fmt::Write;
+                use OptionsMenuEntry::*;
+                use OrBack::*;
+                match entry.entry {
+                    Back => write!(buf, "back").unwrap(),
+                    Selection(entry) => match entry {
+                        ToggleMusic => write!(
+                            buf,
+                            "(m) Music enabled [{}]",
+                            if config.music { '*' } else { ' ' }
+                        )
+                        .unwrap(),
+                        ToggleSfx => write!(
+                            buf,
+                            "(s) Sfx enabled [{}]",
+                            if config.sfx { '*' } else { ' ' }
+                        )
+                        .unwrap(),
+                        CycleTheme => write!(buf, "(c) Colours: {}", config.theme.name()).unwrap(),
+                        CycleScale => write!(
+                            buf,
+                            "(z) Zoom: {}x (requires restart)",
+                            config.graphics.scale
+                        )
+                        .unwrap(),
+                        CycleFont => write!(
+                            buf,
+                            "(t) Typeface: {} (requires restart)",
+                            config.graphics.font.name()
+                        )
+                        .unwrap(),
+                        ToggleAsciiGlyphs => write!(
+                            buf,
+                            "(a) ASCII glyphs [{}]",
+                            if config.ascii_glyphs { '*' } else { ' ' }
+                        )
+                        .unwrap(),
+                        ToggleUnlockEverything => write!(
+                            buf,
+                            "(u) Unlock everything [{}]",
+                            if config.unlock_everything { '*' } else { ' ' }
+                        )
+                        .unwrap(),
+                        ToggleFullscreen => {
+                            if fullscreen_requires_restart {
+                                write!(
+                                    buf,
+                                    "(f) Fullscreen (requires restart) [{}]",
+                                    if fullscreen { '*' } else { ' ' }
+                                )
+                                .unwrap()
+                            } else {
+                                write!(
+                                    buf,
+                                    "(f) Fullscreen [{}]",
+                                    if fullscreen { '*' } else { ' ' }
+                                )
+                                .unwrap()
+                            }
+                        }
+                    },
+                }
+            },
+        );
+        menu::FadeMenuInstanceRoutine::new(menu_entry_string)
+            .select(SelectOptionsMenu)
+            .decorated(DecorateOptionsMenu)
+    })
+}
+
+fn difficulty_menu_instance() -> menu::MenuInstanceChooseOrEscape<Difficulty> {
+    let items = Difficulty::all().to_vec();
+    let selected_index = items
+        .iter()
+        .position(|&difficulty| difficulty == Difficulty::default())
+        .unwrap();
+    menu::MenuInstanceBuilder {
+        items,
+        selected_index,
+        hotkeys: Some(hashmap![
+            'e' => Difficulty::Easy,
+            'n' => Difficulty::Normal,
+            'h' => Difficulty::Hard,
+            'm' => Difficulty::Nightmare,
+        ]),
+    }
+    .build()
+    .unwrap()
+    .into_choose_or_escape()
+}
+
+struct SelectDifficultyMenu;
+impl ViewSelector for SelectDifficultyMenu {
+    type ViewInput = AppView;
+    type ViewOutput = FadeMenuInstanceView;
+    fn view<'a>(&self, input: &'a Self::ViewInput) -> &'a Self::ViewOutput {
+        &input.difficulty_menu
+    }
+    fn view_mut<'a>(&self, input: &'a mut Self::ViewInput) -> &'a mut Self::ViewOutput {
+        &mut input.difficulty_menu
+    }
+}
+impl DataSelector for SelectDifficultyMenu {
+    type DataInput = AppData;
+    type DataOutput = menu::MenuInstanceChooseOrEscape<Difficulty>;
+    fn data<'a>(&self, input: &'a Self::DataInput) -> &'a Self::DataOutput {
+        &input.difficulty_menu
+    }
+    fn data_mut<'a>(&self, input: &'a mut Self::DataInput) -> &'a mut Self::DataOutput {
+        &mut input.difficulty_menu
+    }
+}
+impl Selector for SelectDifficultyMenu {}
+
+fn difficulty_menu() -> impl EventRoutine<
+    Return = Result<Difficulty, menu::Escape>,
+    Data = AppData,
+    View = AppView,
+    Event = CommonEvent,
+> {
+    let menu_entry_string =
+        MenuEntryStringFn::new(|entry: MenuEntryToRender<Difficulty>, buf: &mut String| {
+            use std::fmt::Write;
+            let name = entry.entry.name();
+            write!(
+                buf,
+                "({}) {}",
+                name.chars().next().unwrap().to_ascii_lowercase(),
+                name
+            )
+            .unwrap();
+        });
+    menu::FadeMenuInstanceRoutine::new(menu_entry_string)
+        .select(SelectDifficultyMenu)
+        .decorated(DecorateOptionsMenu)
+}
+
+struct SelectModeMenu;
+impl ViewSelector for SelectModeMenu {
+    type ViewInput = AppView;
+    type ViewOutput = FadeMenuInstanceView;
+    fn view<'a>(&self, input: &'a Self::ViewInput) -> &'a Self::ViewOutput {
+        &input.mode_menu
+    }
+    fn view_mut<'a>(&self, input: &'a mut Self::ViewInput) -> &'a mut Self::ViewOutput {
+        &mut input.mode_menu
+    }
+}
+impl DataSelector for SelectModeMenu {
+    type DataInput = AppData;
+    type DataOutput = menu::MenuInstanceChooseOrEscape<GameMode>;
+    fn data<'a>(&self, input: &'a Self::DataInput) -> &'a Self::DataOutput {
+        &input.mode_menu
+    }
+    fn data_mut<'a>(&self, input: &'a mut Self::DataInput) -> &'a mut Self::DataOutput {
+        &mut input.mode_menu
+    }
+}
+impl Selector for SelectModeMenu {}
+
+fn mode_menu() -> impl EventRoutine<
+    Return = Result<GameMode, menu::Escape>,
+    Data = AppData,
+    View = AppView,
+    Event = CommonEvent,
+> {
+    SideEffectThen::new_with_view(|data: &mut AppData, _: &_| {
+        let deepest_endless_floor = data.game.config().deepest_endless_floor;
+        let menu_entry_string = MenuEntryStringFn::new(
+            move |entry: MenuEntryToRender<GameMode>, buf: &mut String| {
+                use std::fmt::Write;
+                match entry.entry {
+                    GameMode::Campaign => write!(buf, "(c) Campaign").unwrap(),
+                    GameMode::Endless => {
+                        write!(buf, "(e) Endless Descent").unwrap();
+                        if deepest_endless_floor > 0 {
+                            write!(buf, " (best: floor {})", deepest_endless_floor).unwrap();
+                        }
+                    }
+                }
+            },
+        );
+        menu::FadeMenuInstanceRoutine::new(menu_entry_string)
+            .select(SelectModeMenu)
+            .decorated(DecorateOptionsMenu)
+    })
+}
+
+fn loadout_menu_instance(unlocks: &Unlocks) -> menu::MenuInstanceChooseOrEscape<Loadout> {
+    let items = Loadout::all()
+        .iter()
+        .cloned()
+        .filter(|&loadout| unlocks.allows_loadout(loadout))
+        .collect::<Vec<_>>();
+    let hotkeys = items
+        .iter()
+        .map(|&loadout| {
+            let hotkey = loadout.name().chars().next().unwrap().to_ascii_lowercase();
+            (hotkey, loadout)
+        })
+        .collect::<HashMap<_, _>>();
+    menu::MenuInstanceBuilder {
+        items,
+        selected_index: 0,
+        hotkeys: Some(hotkeys),
+    }
+    .build()
+    .unwrap()
+    .into_choose_or_escape()
+}
+
+struct SelectLoadoutMenu;
+impl ViewSelector for SelectLoadoutMenu {
+    type ViewInput = AppView;
+    type ViewOutput = FadeMenuInstanceView;
+    fn view<'a>(&self, input: &'a Self::ViewInput) -> &'a Self::ViewOutput {
+        &input.loadout_menu
+    }
+    fn view_mut<'a>(&self, input: &'a mut Self::ViewInput) -> &'a mut Self::ViewOutput {
+        &mut input.loadout_menu
+    }
+}
+impl DataSelector for SelectLoadoutMenu {
+    type DataInput = AppData;
+    type DataOutput = menu::MenuInstanceChooseOrEscape<Loadout>;
+    fn data<'a>(&self, input: &'a Self::DataInput) -> &'a Self::DataOutput {
+        &input.loadout_menu
+    }
+    fn data_mut<'a>(&self, input: &'a mut Self::DataInput) -> &'a mut Self::DataOutput {
+        &mut input.loadout_menu
+    }
+}
+impl Selector for SelectLoadoutMenu {}
+
+fn loadout_menu() -> impl EventRoutine<
+    Return = Result<Loadout, menu::Escape>,
+    Data = AppData,
+    View = AppView,
+    Event = CommonEvent,
+> {
+    SideEffectThen::new_with_view(|data: &mut AppData, _: &_| {
+        // loadouts may have been unlocked since the menu was last shown
+        data.loadout_menu = loadout_menu_instance(&data.game.unlocks());
+        let menu_entry_string =
+            MenuEntryStringFn::new(|entry: MenuEntryToRender<Loadout>, buf: &mut String| {
+                use std::fmt::Write;
+                let name = entry.entry.name();
+                write!(
+                    buf,
+                    "({}) {}: {}",
+                    name.chars().next().unwrap().to_ascii_lowercase(),
+                    name,
+                    entry.entry.description(),
+                )
+                .unwrap();
+            });
+        menu::FadeMenuInstanceRoutine::new(menu_entry_string)
+            .select(SelectLoadoutMenu)
+            .decorated(DecorateOptionsMenu)
+    })
+}
+
+/// The menus shown in sequence when starting a new game. Escaping any of them abandons the new
+/// game.
+fn new_game_menus() -> impl EventRoutine<
+    Return = Result<(Difficulty, GameMode, Loadout), menu::Escape>,
+    Data = AppData,
+    View = AppView,
+    Event = CommonEvent,
+> {
+    difficulty_menu()
+        .and_then(|choice| {
+            make_either!(Ei = A | B);
+            match choice {
+                Err(menu::Escape) => Ei::A(Value::new(Err(menu::Escape))),
+                Ok(difficulty) => {
+                    Ei::B(mode_menu().map(move |choice| choice.map(|mode| (difficulty, mode))))
+                }
+            }
+        })
+        .and_then(|choice| {
+            make_either!(Ei = A | B);
+            match choice {
+                Err(menu::Escape) => Ei::A(Value::new(Err(menu::Escape))),
+                Ok((difficulty, mode)) => Ei::B(
+                    loadout_menu()
+                        .map(move |choice| choice.map(|loadout| (difficulty, mode, loadout))),
+                ),
+            }
+        })
+}
+
+fn options_menu_cycle(
+) -> impl EventRoutine<Return = (), Data = AppData, View = AppView, Event = CommonEvent> {
+    make_either!(Ei = A | B);
+    use OptionsMenuEntry::*;
+    use OrBack::*;
+    Ei::A(options_menu()).repeat(|choice| match choice {
+        Ok(Back) | Err(menu::Escape) => Handled::Return(()),
+        Ok(Selection(selection)) => Handled::Continue(Ei::B(SideEffectThen::new_with_view(
+            move |data: &mut AppData, _: &_| {
+                let mut config = data.game.config();
+                match selection {
+                    ToggleMusic => config.music = !config.music,
+                    ToggleSfx => config.sfx = !config.sfx,
+                    ToggleUnlockEverything => config.unlock_everything = !config.unlock_everything,
+                    CycleTheme => config.theme = config.theme.next(),
+                    ToggleAsciiGlyphs => config.ascii_glyphs = !config.ascii_glyphs,
+                    CycleScale => {
+                        config.graphics.scale = config.graphics.next_scale();
+                        // the remembered window size was chosen to fit the previous scale
+                        config.graphics.window_size = None;
+                    }
+                    CycleFont => config.graphics.font = config.graphics.font.next(),
+                    ToggleFullscreen => {
+                        data.env.set_fullscreen(!data.env.fullscreen());
+                        config.fullscreen = data.env.fullscreen();
+                    }
+                }
+                data.game.set_config(config);
+                options_menu()
+            },
+        ))),
+    })
+}
+
+#[derive(Clone, Copy)]
+pub struct AutoPlay;
+
+#[derive(Clone, Copy)]
+pub struct FirstRun;
+
+fn main_menu(
+    auto_play: Option<AutoPlay>,
     first_run: Option<FirstRun>,
 ) -> impl EventRoutine<
     Return = Result<MainMenuEntry, menu::Escape>,
@@ -939,6 +1855,8 @@ fn main_menu(
                             MainMenuEntry::Story => "(b) Back Story",
                             MainMenuEntry::Keybindings => "(k) Keybindings",
                             MainMenuEntry::EndText => "(e) End Text",
+                            MainMenuEntry::Achievements => "(a) Achievements",
+                            MainMenuEntry::UnlockList => "(u) Unlocks",
                         };
                         write!(buf, "{}", s).unwrap();
                     },
@@ -972,7 +1890,7 @@ fn game_over() -> impl EventRoutine<Return = (), Data = AppData, View = AppView,
         .decorated(DecorateGame)
 }
 
-fn win_text() -> TextOverlay {
+fn win_text(difficulty: Option<Difficulty>) -> TextOverlay {
     let bold = Style::new()
         .with_foreground(Rgb24::new(255, 0, 0))
         .with_bold(true);
@@ -1010,15 +1928,108 @@ fn win_text() -> TextOverlay {
         ),
         text::RichTextPartOwned::new("RADIOACTIVE MUTANT SLIMES".to_string(), bold),
         text::RichTextPartOwned::new(" appear in the sewers...".to_string(), normal),
+        text::RichTextPartOwned::new(
+            difficulty
+                .map(|difficulty| format!("\n\nDifficulty: {}", difficulty.name()))
+                .unwrap_or_else(String::new),
+            faint,
+        ),
         text::RichTextPartOwned::new("\n\n\n\n\n\nPress any key...".to_string(), faint),
     ])
 }
 
-fn win() -> impl EventRoutine<Return = (), Data = AppData, View = AppView, Event = CommonEvent> {
-    SideEffectThen::new_with_view(|data: &mut AppData, _: &_| {
+fn win(
+    difficulty: Difficulty,
+) -> impl EventRoutine<Return = (), Data = AppData, View = AppView, Event = CommonEvent> {
+    SideEffectThen::new_with_view(move |data: &mut AppData, _: &_| {
         data.game.loop_music(Audio::EndText, 0.2);
         data.won = true;
-        win_text()
+        win_text(Some(difficulty))
+    })
+}
+
+fn achievements(
+) -> impl EventRoutine<Return = (), Data = AppData, View = AppView, Event = CommonEvent> {
+    SideEffectThen::new_with_view(|data: &mut AppData, _: &_| {
+        let bold = Style::new()
+            .with_foreground(Rgb24::new(255, 255, 0))
+            .with_bold(true);
+        let normal = Style::new().with_foreground(Rgb24::new_grey(255));
+        let faint = Style::new().with_foreground(Rgb24::new_grey(127));
+        let unlocked = data.game.achievements();
+        let mut parts = vec![text::RichTextPartOwned::new(
+            "ACHIEVEMENTS\n\n".to_string(),
+            bold,
+        )];
+        for &achievement in Achievement::all() {
+            let (name_style, description_style) = if unlocked.is_unlocked(achievement) {
+                (bold, normal)
+            } else {
+                (faint, faint)
+            };
+            parts.push(text::RichTextPartOwned::new(
+                format!("{}\n", achievement.name()),
+                name_style,
+            ));
+            parts.push(text::RichTextPartOwned::new(
+                format!("  {}\n\n", achievement.description()),
+                description_style,
+            ));
+        }
+        parts.push(text::RichTextPartOwned::new(
+            "\n\nPress any key...".to_string(),
+            faint,
+        ));
+        TextOverlay::new(parts)
+    })
+}
+
+fn unlocks() -> impl EventRoutine<Return = (), Data = AppData, View = AppView, Event = CommonEvent>
+{
+    SideEffectThen::new_with_view(|data: &mut AppData, _: &_| {
+        let bold = Style::new()
+            .with_foreground(Rgb24::new(0, 255, 255))
+            .with_bold(true);
+        let normal = Style::new().with_foreground(Rgb24::new_grey(255));
+        let faint = Style::new().with_foreground(Rgb24::new_grey(127));
+        let earned = data.game.earned_unlocks();
+        let mut parts = vec![text::RichTextPartOwned::new(
+            "UNLOCKS\n\n".to_string(),
+            bold,
+        )];
+        for &unlock in Unlock::all() {
+            let requirement = match unlock.requirement() {
+                Requirement::RunsFinished(1) => "Finish a run".to_string(),
+                Requirement::RunsFinished(n) => format!("Finish {} runs", n),
+                Requirement::Achievement(achievement) => {
+                    format!("Achievement: {}", achievement.name())
+                }
+            };
+            let (name_style, requirement_style) = if earned.contains(unlock) {
+                (bold, normal)
+            } else {
+                (faint, faint)
+            };
+            parts.push(text::RichTextPartOwned::new(
+                format!("{}\n", unlock.name()),
+                name_style,
+            ));
+            parts.push(text::RichTextPartOwned::new(
+                format!("  {}\n\n", requirement),
+                requirement_style,
+            ));
+        }
+        if data.game.config().unlock_everything {
+            parts.push(text::RichTextPartOwned::new(
+                "Everything is unlocked from the options menu.".to_string(),
+                normal,
+            ));
+        }
+        parts.push(text::RichTextPartOwned::new(
+            "\n\nPress any key...".to_string(),
+            faint,
+        ));
+        TextOverlay::new(parts)
     })
 }
 
@@ -1063,9 +2074,11 @@ fn keybindings() -> TextOverlay {
         text::RichTextPartOwned::new("Movement/Aim: arrows/VI keys/WASD\n\n".to_string(), normal),
         text::RichTextPartOwned::new("Cancel Aim: escape\n\n".to_string(), normal),
         text::RichTextPartOwned::new("Wait: space\n\n".to_string(), normal),
+        text::RichTextPartOwned::new("Close Doors: c\n\n".to_string(), normal),
         text::RichTextPartOwned::new("Use Tech: t\n\n".to_string(), normal),
         text::RichTextPartOwned::new("Examine: x\n\n".to_string(), normal),
-        text::RichTextPartOwned::new("\n\n\n\n\nPress any key...".to_string(), faint),
+        text::RichTextPartOwned::new("Describe Surroundings: v\n\n".to_string(), normal),
+        text::RichTextPartOwned::new("\n\n\nPress any key...".to_string(), faint),
     ])
 }
 
@@ -1112,7 +2125,7 @@ enum GameLoopBreak {
 
 fn game_loop() -> impl EventRoutine<Return = (), Data = AppData, View = AppView, Event = CommonEvent>
 {
-    make_either!(Ei = A | B | C | D);
+    make_either!(Ei = A | B | C | D | E | F | G);
     SideEffect::new_with_view(|data: &mut AppData, _: &_| data.game.pre_game_loop())
         .then(|| {
             Ei::A(game())
@@ -1130,6 +2143,38 @@ fn game_loop() -> impl EventRoutine<Return = (), Data = AppData, View = AppView,
                             },
                         )))
                     }
+                    GameReturn::Pickup(card) => {
+                        Handled::Continue(Ei::E(pickup_menu(card).and_then(|choice| {
+                            let resolution = choice.unwrap_or(PickupResolution::Leave);
+                            game_injecting_inputs(vec![InjectedInput::Pickup(resolution)])
+                        })))
+                    }
+                    GameReturn::Safehouse => {
+                        Handled::Continue(Ei::G(safehouse_menu().and_then(|choice| {
+                            make_either!(Ei = A | B);
+                            match choice {
+                                Err(menu::Escape) => Ei::A(Value::new(GameReturn::Pause)),
+                                Ok(action) => {
+                                    Ei::B(game_injecting_inputs(vec![InjectedInput::Safehouse(
+                                        action,
+                                    )]))
+                                }
+                            }
+                        })))
+                    }
+                    GameReturn::Bury(slot, target) => {
+                        Handled::Continue(Ei::F(bury_menu(target).and_then(move |choice| {
+                            make_either!(Ei = A | B);
+                            match choice {
+                                Err(menu::Escape) => Ei::A(game()),
+                                Ok(depth) => {
+                                    Ei::B(game_injecting_inputs(vec![InjectedInput::Bury(
+                                        slot, depth,
+                                    )]))
+                                }
+                            }
+                        })))
+                    }
                     GameReturn::Examine => {
                         Handled::Continue(Ei::D(examine().and_then(|()| game())))
                     }
@@ -1150,13 +2195,20 @@ fn game_loop() -> impl EventRoutine<Return = (), Data = AppData, View = AppView,
                     match game_loop_break {
                         GameLoopBreak::Win => Ei::C(SideEffectThen::new_with_view(
                             |data: &mut AppData, _: &_| {
+                                let difficulty = data
+                                    .game
+                                    .instance()
+                                    .map(|instance| instance.difficulty())
+                                    .unwrap_or_default();
+                                data.game.record_run_end(true);
                                 data.game.clear_instance();
-                                win()
+                                win(difficulty)
                             },
                         )),
                         GameLoopBreak::Pause => Ei::A(Value::new(())),
                         GameLoopBreak::GameOver => Ei::B(game_over().and_then(|()| {
                             SideEffect::new_with_view(|data: &mut AppData, _: &_| {
+                                data.game.record_run_end(false);
                                 data.game.clear_instance();
                             })
                         })),
@@ -1170,12 +2222,16 @@ fn main_menu_cycle(
     auto_play: Option<AutoPlay>,
     first_run: Option<FirstRun>,
 ) -> impl EventRoutine<Return = Option<Quit>, Data = AppData, View = AppView, Event = CommonEvent> {
-    make_either!(Ei = A | B | C | D | E | F | G | H | I | J);
-    main_menu(auto_play, first_run).and_then(|entry| match entry {
-        Ok(MainMenuEntry::Quit) => Ei::A(Value::new(Some(Quit))),
+    make_either!(Ei = A | B | C | D | E | F | G | H | I | J | K | L);
+    main_menu(auto_play, first_run).and_then(move |entry| match entry {
+        Ok(MainMenuEntry::Quit) => Ei::A(SideEffect::new_with_view(|data: &mut AppData, _: &_| {
+            data.remember_window_size();
+            Some(Quit)
+        })),
         Ok(MainMenuEntry::SaveQuit) => {
             Ei::D(SideEffect::new_with_view(|data: &mut AppData, _: &_| {
                 data.game.save_instance();
+                data.remember_window_size();
                 Some(Quit)
             }))
         }
@@ -1207,16 +2263,41 @@ fn main_menu_cycle(
             },
         )),
         Ok(MainMenuEntry::NewGame) => Ei::C(SideEffectThen::new_with_view(
-            |data: &mut AppData, _: &_| {
-                data.game.instantiate();
-                data.main_menu.menu_instance_mut().set_index(0);
-                game_loop().map(|()| None)
+            move |data: &mut AppData, _: &_| {
+                make_either!(Ei = A | B);
+                let start = |data: &mut AppData, difficulty, mode, loadout| {
+                    data.game.instantiate(difficulty, mode, loadout);
+                    data.main_menu.menu_instance_mut().set_index(0);
+                    game_loop().map(|()| None)
+                };
+                if auto_play.is_some() {
+                    Ei::A(start(
+                        data,
+                        Difficulty::default(),
+                        GameMode::default(),
+                        Loadout::default(),
+                    ))
+                } else {
+                    Ei::B(new_game_menus().and_then(move |choice| {
+                        make_either!(Ei = A | B);
+                        match choice {
+                            Err(menu::Escape) => Ei::A(Value::new(None)),
+                            Ok((difficulty, mode, loadout)) => Ei::B(
+                                SideEffectThen::new_with_view(move |data: &mut AppData, _: &_| {
+                                    start(data, difficulty, mode, loadout)
+                                }),
+                            ),
+                        }
+                    }))
+                }
             },
         )),
         Ok(MainMenuEntry::Options) => Ei::G(options_menu_cycle().map(|_| None)),
         Ok(MainMenuEntry::Story) => Ei::H(story().map(|()| None)),
         Ok(MainMenuEntry::Keybindings) => Ei::I(keybindings().map(|()| None)),
-        Ok(MainMenuEntry::EndText) => Ei::J(win_text().map(|()| None)),
+        Ok(MainMenuEntry::EndText) => Ei::J(win_text(None).map(|()| None)),
+        Ok(MainMenuEntry::Achievements) => Ei::K(achievements().map(|()| None)),
+        Ok(MainMenuEntry::UnlockList) => Ei::L(unlocks().map(|()| None)),
     })
 }
 
@@ -1240,6 +2321,7 @@ fn event_routine(
                 })
                 .return_on_exit(|data| {
                     data.game.save_instance();
+                    data.remember_window_size();
                     ()
                 })
         },
@@ -1253,6 +2335,9 @@ pub trait Env {
     // hack to get around fact that changing fullscreen mid-game on windows crashes
     fn set_fullscreen_init(&self, fullscreen: bool);
     fn set_fullscreen(&self, fullscreen: bool);
+    /// The current size of the window in pixels, which is saved in the config on exit so the
+    /// next session can open a window of the same size. `None` if there is no size to remember.
+    fn window_size(&self) -> Option<WindowSize>;
 }
 pub struct EnvNull;
 impl Env for EnvNull {
@@ -1267,6 +2352,9 @@ impl Env for EnvNull {
     }
     fn set_fullscreen(&self, _fullscreen: bool) {}
     fn set_fullscreen_init(&self, _fullscreen: bool) {}
+    fn window_size(&self) -> Option<WindowSize> {
+        None
+    }
 }
 
 pub struct Fullscreen;
@@ -1282,6 +2370,7 @@ pub fn app(
     auto_play: Option<AutoPlay>,
     fullscreen: Option<Fullscreen>,
     env: Box<dyn Env>,
+    narration: Option<Narration>,
 ) -> impl app::App {
     let app_data = AppData::new(
         game_config,
@@ -1293,6 +2382,7 @@ pub fn app(
         rng_seed,
         fullscreen,
         env,
+        narration,
     );
     let app_view = AppView::new();
     event_routine(auto_play).app_one_shot_ignore_return(app_data, app_view)
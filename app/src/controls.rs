@@ -9,8 +9,11 @@ pub enum AppInput {
     Move(CardinalDirection),
     Tech,
     Wait,
+    CloseDoors,
     Ability(u8),
     Examine,
+    /// Narrate everything in view, when narration is enabled
+    DescribeSurroundings,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -36,7 +39,9 @@ impl Controls {
             KeyboardInput::Char('j') => AppInput::Move(CardinalDirection::South),
             KeyboardInput::Char('t') => AppInput::Tech,
             KeyboardInput::Char('x') => AppInput::Examine,
+            KeyboardInput::Char('v') => AppInput::DescribeSurroundings,
             KeyboardInput::Char(' ') => AppInput::Wait,
+            KeyboardInput::Char('c') => AppInput::CloseDoors,
             KeyboardInput::Char('1') => AppInput::Ability(0),
             KeyboardInput::Char('2') => AppInput::Ability(1),
             KeyboardInput::Char('3') => AppInput::Ability(2),
@@ -1,7 +1,11 @@
 use crate::audio::{AppAudioPlayer, AppHandle, Audio, AudioTable};
 use crate::controls::{AppInput, Controls};
 use crate::frontend::Frontend;
+use crate::graphics::GraphicsConfig;
+use crate::narration::Narration;
+use crate::palette::Theme;
 use crate::render::{GameToRender, GameView, Mode};
+use crate::tileset::Tileset;
 use chargrid::event_routine::common_event::*;
 use chargrid::event_routine::*;
 use chargrid::input::*;
@@ -12,12 +16,20 @@ use rand::{Rng, SeedableRng};
 use rand_isaac::Isaac64Rng;
 use serde::{Deserialize, Serialize};
 use slime99_game::{
-    player::Ability, ActionError, CharacterInfo, ExternalEvent, Game, GameControlFlow, Music,
+    player::{Ability, AbilityTarget, Outcome},
+    ActionError, CharacterInfo, ExternalEvent, Game, GameControlFlow, Music,
+};
+pub use slime99_game::{
+    AbilityChoice, Achievement, Config as GameConfig, Difficulty, GameMode, Input as GameInput,
+    Loadout, Omniscient, PickupResolution, Requirement, SafehouseAction, SafehouseOption, Unlock,
+    Unlocks,
 };
-pub use slime99_game::{AbilityChoice, Config as GameConfig, Input as GameInput, Omniscient};
 use std::time::Duration;
 
 const CONFIG_KEY: &str = "config.json";
+const ACHIEVEMENTS_KEY: &str = "achievements.json";
+const UNLOCKS_KEY: &str = "unlocks.json";
+const TILESET_KEY: &str = "tileset.json";
 
 const GAME_MUSIC_VOLUME: f32 = 0.05;
 const MENU_MUSIC_VOLUME: f32 = 0.02;
@@ -30,6 +42,19 @@ pub struct Config {
     pub sfx: bool,
     pub fullscreen: bool,
     pub first_run: bool,
+    /// The deepest floor reached in endless mode, which serves as its score
+    #[serde(default)]
+    pub deepest_endless_floor: u32,
+    /// Start runs with all unlocks regardless of progress
+    #[serde(default)]
+    pub unlock_everything: bool,
+    #[serde(default)]
+    pub theme: Theme,
+    /// Draw tiles with the built-in ascii tileset rather than the one loaded at startup
+    #[serde(default)]
+    pub ascii_glyphs: bool,
+    #[serde(default)]
+    pub graphics: GraphicsConfig,
 }
 
 impl Default for Config {
@@ -39,10 +64,69 @@ impl Default for Config {
             sfx: true,
             fullscreen: false,
             first_run: true,
+            deepest_endless_floor: 0,
+            unlock_everything: false,
+            theme: Theme::default(),
+            ascii_glyphs: false,
+            graphics: GraphicsConfig::default(),
         }
     }
 }
 
+/// Achievements unlocked across all runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Achievements {
+    unlocked: Vec<Achievement>,
+    runs_finished: u32,
+}
+
+impl Achievements {
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+    fn meets(&self, requirement: Requirement) -> bool {
+        match requirement {
+            Requirement::RunsFinished(n) => self.runs_finished >= n,
+            Requirement::Achievement(achievement) => self.is_unlocked(achievement),
+        }
+    }
+    /// Returns true if the achievement wasn't already unlocked
+    fn unlock(&mut self, achievement: Achievement) -> bool {
+        if self.is_unlocked(achievement) {
+            false
+        } else {
+            self.unlocked.push(achievement);
+            true
+        }
+    }
+}
+
+/// Number of frames for which a newly unlocked achievement is shown
+const ACHIEVEMENT_NOTIFICATION_FRAMES: u32 = 180;
+
+#[derive(Clone, Copy)]
+pub struct AchievementNotification {
+    pub achievement: Achievement,
+    remaining_frames: u32,
+}
+
+impl AchievementNotification {
+    fn new(achievement: Achievement) -> Self {
+        Self {
+            achievement,
+            remaining_frames: ACHIEVEMENT_NOTIFICATION_FRAMES,
+        }
+    }
+    fn next(self) -> Option<Self> {
+        self.remaining_frames
+            .checked_sub(1)
+            .map(|remaining_frames| Self {
+                remaining_frames,
+                achievement: self.achievement,
+            })
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy)]
 struct ScreenShake {
     remaining_frames: u8,
@@ -71,7 +155,10 @@ struct EffectContext<'a> {
     audio_player: &'a AppAudioPlayer,
     audio_table: &'a AudioTable,
     player_coord: GameCoord,
-    config: &'a Config,
+    config: &'a mut Config,
+    achievements: &'a mut Achievements,
+    achievement_notification: &'a mut Option<AchievementNotification>,
+    storage_wrapper: &'a mut StorageWrapper,
 }
 
 impl<'a> EffectContext<'a> {
@@ -79,6 +166,9 @@ impl<'a> EffectContext<'a> {
         *self.screen_shake = self
             .screen_shake
             .and_then(|screen_shake| screen_shake.next());
+        *self.achievement_notification = self
+            .achievement_notification
+            .and_then(|achievement_notification| achievement_notification.next());
     }
     fn play_audio(&self, audio: Audio, volume: f32) {
         log::info!("Playing audio {:?} at volume {:?}", audio, volume);
@@ -107,6 +197,19 @@ impl<'a> EffectContext<'a> {
                 let handle = loop_music(self.audio_player, self.audio_table, self.config, music);
                 *self.current_music_handle = Some(handle);
             }
+            ExternalEvent::Achievement(achievement) => {
+                if self.achievements.unlock(achievement) {
+                    self.storage_wrapper.save_achievements(self.achievements);
+                    *self.achievement_notification =
+                        Some(AchievementNotification::new(achievement));
+                }
+            }
+            ExternalEvent::EndlessFloorReached(floor) => {
+                if floor > self.config.deepest_endless_floor {
+                    self.config.deepest_endless_floor = floor;
+                    self.storage_wrapper.save_config(self.config);
+                }
+            }
         }
     }
 }
@@ -137,6 +240,9 @@ fn loop_music(
 pub enum InjectedInput {
     Tech(Coord),
     LevelChange(Ability),
+    Pickup(PickupResolution),
+    Bury(u8, usize),
+    Safehouse(SafehouseAction),
 }
 
 #[derive(Clone, Copy)]
@@ -161,6 +267,8 @@ pub struct GameInstance {
     screen_shake: Option<ScreenShake>,
     current_music: Option<Music>,
     level_change: Option<AbilityChoice>,
+    #[serde(skip)]
+    achievement_notification: Option<AchievementNotification>,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -176,18 +284,29 @@ pub enum RngSeed {
 }
 
 impl GameInstance {
-    fn new(game_config: &GameConfig, mut rng: Isaac64Rng) -> Self {
+    fn new(
+        game_config: &GameConfig,
+        difficulty: Difficulty,
+        mode: GameMode,
+        loadout: Loadout,
+        unlocks: Unlocks,
+        mut rng: Isaac64Rng,
+    ) -> Self {
         Self {
-            game: Game::new(game_config, &mut rng),
+            game: Game::new(game_config, difficulty, mode, loadout, unlocks, &mut rng),
             rng,
             screen_shake: None,
             current_music: None,
             level_change: None,
+            achievement_notification: None,
         }
     }
     pub fn game(&self) -> &Game {
         &self.game
     }
+    pub fn difficulty(&self) -> Difficulty {
+        self.game.difficulty()
+    }
 }
 
 pub struct GameData {
@@ -202,6 +321,11 @@ pub struct GameData {
     frontend: Frontend,
     music_handle: Option<AppHandle>,
     config: Config,
+    achievements: Achievements,
+    unlocks: Unlocks,
+    tileset: Tileset,
+    ascii_tileset: Tileset,
+    narration: Option<Narration>,
 }
 
 struct StorageWrapper {
@@ -218,6 +342,17 @@ impl StorageWrapper {
     pub fn clear_instance(&mut self) {
         let _ = self.storage.remove(&self.save_key);
     }
+    pub fn save_achievements(&mut self, achievements: &Achievements) {
+        let _ = self
+            .storage
+            .store(ACHIEVEMENTS_KEY, achievements, format::Json);
+    }
+    pub fn save_unlocks(&mut self, unlocks: &Unlocks) {
+        let _ = self.storage.store(UNLOCKS_KEY, unlocks, format::Json);
+    }
+    pub fn save_config(&mut self, config: &Config) {
+        let _ = self.storage.store(CONFIG_KEY, config, format::Json);
+    }
 }
 
 struct RngSeedSource {
@@ -241,6 +376,10 @@ impl RngSeedSource {
     }
 }
 
+pub fn load_config(storage: &StaticStorage) -> Config {
+    storage.load(CONFIG_KEY, format::Json).unwrap_or_default()
+}
+
 impl GameData {
     pub fn new(
         game_config: GameConfig,
@@ -250,8 +389,20 @@ impl GameData {
         audio_player: AppAudioPlayer,
         rng_seed: RngSeed,
         frontend: Frontend,
+        narration: Option<Narration>,
     ) -> Self {
-        let config = storage.load(CONFIG_KEY, format::Json).unwrap_or_default();
+        let config = load_config(&storage);
+        let achievements = storage
+            .load(ACHIEVEMENTS_KEY, format::Json)
+            .unwrap_or_default();
+        let unlocks = storage.load(UNLOCKS_KEY, format::Json).unwrap_or_default();
+        let tileset = match storage.load(TILESET_KEY, format::Json) {
+            Ok(tileset) => tileset,
+            Err(e) => {
+                log::info!("no tileset found, using default: {:?}", e);
+                Tileset::default()
+            }
+        };
         let mut instance: Option<GameInstance> = match storage.load(&save_key, STORAGE_FORMAT) {
             Ok(instance) => Some(instance),
             Err(e) => {
@@ -287,6 +438,11 @@ impl GameData {
             frontend,
             music_handle,
             config,
+            achievements,
+            unlocks,
+            tileset,
+            ascii_tileset: Tileset::ascii(),
+            narration,
         }
     }
     pub fn is_music_playing(&self) -> bool {
@@ -305,6 +461,13 @@ impl GameData {
     pub fn config(&self) -> Config {
         self.config
     }
+    pub fn tileset(&self) -> &Tileset {
+        if self.config.ascii_glyphs {
+            &self.ascii_tileset
+        } else {
+            &self.tileset
+        }
+    }
     pub fn set_config(&mut self, config: Config) {
         self.config = config;
         if let Some(music_handle) = self.music_handle.as_ref() {
@@ -314,10 +477,7 @@ impl GameData {
                 music_handle.pause();
             }
         }
-        let _ = self
-            .storage_wrapper
-            .storage
-            .store(CONFIG_KEY, &config, format::Json);
+        self.storage_wrapper.save_config(&config);
     }
     pub fn pre_game_loop(&mut self) {
         if let Some(music_handle) = self.music_handle.as_ref() {
@@ -337,11 +497,18 @@ impl GameData {
     pub fn has_instance(&self) -> bool {
         self.instance.is_some()
     }
-    pub fn instantiate(&mut self) {
+    pub fn instantiate(&mut self, difficulty: Difficulty, mode: GameMode, loadout: Loadout) {
         let seed = self.rng_seed_source.next_seed();
         self.frontend.log_rng_seed(seed);
         let rng = Isaac64Rng::seed_from_u64(seed);
-        self.instance = Some(GameInstance::new(&self.game_config, rng));
+        self.instance = Some(GameInstance::new(
+            &self.game_config,
+            difficulty,
+            mode,
+            loadout,
+            self.unlocks(),
+            rng,
+        ));
     }
     pub fn save_instance(&mut self) {
         log::info!("saving game...");
@@ -351,6 +518,43 @@ impl GameData {
             self.storage_wrapper.clear_instance();
         }
     }
+    /// Records the end of a run, unlocking any achievements earned by winning it
+    pub fn record_run_end(&mut self, won: bool) {
+        if won {
+            if let Some(instance) = self.instance.as_ref() {
+                let mut achievements = instance.game.win_achievements();
+                if self.achievements.runs_finished == 0 {
+                    achievements.push(Achievement::WinFirstRun);
+                }
+                for achievement in achievements {
+                    self.achievements.unlock(achievement);
+                }
+            }
+        }
+        self.achievements.runs_finished += 1;
+        self.storage_wrapper.save_achievements(&self.achievements);
+        for &unlock in Unlock::all() {
+            if self.achievements.meets(unlock.requirement()) {
+                self.unlocks.insert(unlock);
+            }
+        }
+        self.storage_wrapper.save_unlocks(&self.unlocks);
+    }
+    /// Unlocks earned by meeting their requirements
+    pub fn earned_unlocks(&self) -> &Unlocks {
+        &self.unlocks
+    }
+    /// Unlocks which apply to new runs
+    pub fn unlocks(&self) -> Unlocks {
+        if self.config.unlock_everything {
+            Unlocks::everything()
+        } else {
+            self.unlocks.clone()
+        }
+    }
+    pub fn achievements(&self) -> &Achievements {
+        &self.achievements
+    }
     pub fn clear_instance(&mut self) {
         self.instance = None;
         self.storage_wrapper.clear_instance();
@@ -416,7 +620,9 @@ impl EventRoutine for ExamineEventRoutine {
         let audio_table = &data.audio_table;
         let game_config = &data.game_config;
         let current_music_handle = &mut data.music_handle;
-        let config = &data.config;
+        let config = &mut data.config;
+        let achievements = &mut data.achievements;
+        let storage_wrapper = &mut data.storage_wrapper;
         if let Some(instance) = data.instance.as_mut() {
             event_or_peek_with_handled(event_or_peek, self, |mut s, event| {
                 let examine = match event {
@@ -429,9 +635,11 @@ impl EventRoutine for ExamineEventRoutine {
                                         Examine::KeyboardDirection(direction)
                                     }
                                     AppInput::Examine => Examine::Cancel,
-                                    AppInput::Wait | AppInput::Tech | AppInput::Ability(_) => {
-                                        Examine::Ignore
-                                    }
+                                    AppInput::Wait
+                                    | AppInput::CloseDoors
+                                    | AppInput::Tech
+                                    | AppInput::Ability(_)
+                                    | AppInput::DescribeSurroundings => Examine::Ignore,
                                 }
                             } else {
                                 match keyboard_input {
@@ -487,6 +695,9 @@ impl EventRoutine for ExamineEventRoutine {
                             audio_table,
                             player_coord: GameCoord::of_player(instance.game.player_info()),
                             config,
+                            achievements,
+                            achievement_notification: &mut instance.achievement_notification,
+                            storage_wrapper,
                         };
                         event_context.next_frame();
                         for event in instance.game.events() {
@@ -521,6 +732,8 @@ impl EventRoutine for ExamineEventRoutine {
                         target: self.screen_coord,
                     },
                     action_error: None,
+                    palette: data.config.theme.palette(),
+                    tileset: data.tileset(),
                 },
                 context,
                 frame,
@@ -572,7 +785,9 @@ impl EventRoutine for AimEventRoutine {
         let audio_table = &data.audio_table;
         let game_config = &data.game_config;
         let current_music_handle = &mut data.music_handle;
-        let config = &data.config;
+        let config = &mut data.config;
+        let achievements = &mut data.achievements;
+        let storage_wrapper = &mut data.storage_wrapper;
         if let Some(instance) = data.instance.as_mut() {
             event_or_peek_with_handled(event_or_peek, self, |mut s, event| {
                 let aim = match event {
@@ -583,9 +798,11 @@ impl EventRoutine for AimEventRoutine {
                                 match app_input {
                                     AppInput::Move(direction) => Aim::KeyboardDirection(direction),
                                     AppInput::Wait
+                                    | AppInput::CloseDoors
                                     | AppInput::Tech
                                     | AppInput::Ability(_)
-                                    | AppInput::Examine => Aim::Ignore,
+                                    | AppInput::Examine
+                                    | AppInput::DescribeSurroundings => Aim::Ignore,
                                 }
                             } else {
                                 match keyboard_input {
@@ -647,6 +864,9 @@ impl EventRoutine for AimEventRoutine {
                             audio_table,
                             player_coord: GameCoord::of_player(instance.game.player_info()),
                             config,
+                            achievements,
+                            achievement_notification: &mut instance.achievement_notification,
+                            storage_wrapper,
                         };
                         event_context.next_frame();
                         for event in instance.game.events() {
@@ -683,6 +903,8 @@ impl EventRoutine for AimEventRoutine {
                         target: self.screen_coord.0,
                     },
                     action_error: None,
+                    palette: data.config.theme.palette(),
+                    tileset: data.tileset(),
                 },
                 context,
                 frame,
@@ -716,6 +938,9 @@ pub enum GameReturn {
     GameOver,
     Win,
     LevelChange(AbilityChoice),
+    Pickup(Outcome),
+    Bury(u8, AbilityTarget),
+    Safehouse,
     Examine,
 }
 
@@ -739,7 +964,9 @@ impl EventRoutine for GameEventRoutine {
         let audio_table = &data.audio_table;
         let game_config = &data.game_config;
         let current_music_handle = &mut data.music_handle;
-        let config = &data.config;
+        let config = &mut data.config;
+        let achievements = &mut data.achievements;
+        let narration = &mut data.narration;
         if let Some(instance) = data.instance.as_mut() {
             let player_coord = GameCoord::of_player(instance.game.player_info());
             for injected_input in self.injected_inputs.drain(..) {
@@ -787,11 +1014,44 @@ impl EventRoutine for GameEventRoutine {
                             },
                         }
                     }
+                    InjectedInput::Pickup(resolution) => {
+                        instance.game.resolve_pickup(resolution);
+                    }
+                    InjectedInput::Safehouse(action) => {
+                        instance.game.safehouse_action(action);
+                    }
+                    InjectedInput::Bury(slot, depth) => {
+                        let game_control_flow = instance
+                            .game
+                            .handle_input(GameInput::AbilityWithDepth(slot, depth), game_config);
+                        match game_control_flow {
+                            Err(error) => self.action_error = Some(error),
+                            Ok(None) => self.action_error = None,
+                            Ok(Some(game_control_flow)) => match game_control_flow {
+                                GameControlFlow::Win => return Handled::Return(GameReturn::Win),
+                                GameControlFlow::GameOver => {
+                                    return Handled::Return(GameReturn::GameOver)
+                                }
+                                GameControlFlow::LevelChange(ability_choice) => {
+                                    instance.level_change = Some(ability_choice.clone());
+                                    return Handled::Return(GameReturn::LevelChange(
+                                        ability_choice,
+                                    ));
+                                }
+                            },
+                        }
+                    }
                 }
             }
             if let Some(ability_choice) = instance.level_change.as_ref() {
                 return Handled::Return(GameReturn::LevelChange(ability_choice.clone()));
             }
+            if instance.game.in_safehouse() {
+                return Handled::Return(GameReturn::Safehouse);
+            }
+            if let Some(card) = instance.game.pending_pickup() {
+                return Handled::Return(GameReturn::Pickup(card));
+            }
             let controls = &data.controls;
             event_or_peek_with_handled(event_or_peek, self, |mut s, event| match event {
                 CommonEvent::Input(input) => {
@@ -868,12 +1128,34 @@ impl EventRoutine for GameEventRoutine {
                                         AppInput::Wait => {
                                             instance.game.handle_input(GameInput::Wait, game_config)
                                         }
-                                        AppInput::Ability(n) => instance
+                                        AppInput::CloseDoors => instance
                                             .game
-                                            .handle_input(GameInput::Ability(n), game_config),
+                                            .handle_input(GameInput::CloseDoors, game_config),
+                                        AppInput::Ability(n) => {
+                                            match instance.game.player().ability.get(n as usize) {
+                                                Some(Ability::Bury(target))
+                                                    if instance.game.player().deck_len(target)
+                                                        > 1 =>
+                                                {
+                                                    return Handled::Return(GameReturn::Bury(
+                                                        n, target,
+                                                    ));
+                                                }
+                                                _ => instance.game.handle_input(
+                                                    GameInput::Ability(n),
+                                                    game_config,
+                                                ),
+                                            }
+                                        }
                                         AppInput::Examine => {
                                             return Handled::Return(GameReturn::Examine)
                                         }
+                                        AppInput::DescribeSurroundings => {
+                                            if let Some(narration) = narration.as_mut() {
+                                                narration.describe_surroundings(&instance.game);
+                                            }
+                                            return Handled::Continue(s);
+                                        }
                                     };
                                     match game_control_flow {
                                         Err(error) => s.action_error = Some(error),
@@ -917,11 +1199,19 @@ impl EventRoutine for GameEventRoutine {
                         audio_table,
                         player_coord,
                         config,
+                        achievements,
+                        achievement_notification: &mut instance.achievement_notification,
+                        storage_wrapper,
                     };
                     event_context.next_frame();
                     for event in instance.game.events() {
                         event_context.handle_event(event);
                     }
+                    if let Some(narration) = narration.as_mut() {
+                        if !instance.game.is_gameplay_blocked() {
+                            narration.turn(&instance.game);
+                        }
+                    }
                     if let Some(game_control_flow) = maybe_control_flow {
                         match game_control_flow {
                             GameControlFlow::Win => return Handled::Return(GameReturn::Win),
@@ -961,10 +1251,20 @@ impl EventRoutine for GameEventRoutine {
                     mouse_coord: self.mouse_coord,
                     mode: Mode::Normal,
                     action_error: self.action_error,
+                    palette: data.config.theme.palette(),
+                    tileset: data.tileset(),
                 },
                 context,
                 frame,
             );
+            if let Some(notification) = instance.achievement_notification {
+                view.view_achievement_notification(
+                    notification.achievement,
+                    data.config.theme.palette(),
+                    context,
+                    frame,
+                );
+            }
         }
     }
 }
@@ -1000,7 +1300,9 @@ impl EventRoutine for GameOverEventRoutine {
         let audio_player = &data.audio_player;
         let audio_table = &data.audio_table;
         let current_music_handle = &mut data.music_handle;
-        let config = &data.config;
+        let config = &mut data.config;
+        let achievements = &mut data.achievements;
+        let storage_wrapper = &mut data.storage_wrapper;
         if let Some(instance) = data.instance.as_mut() {
             event_or_peek_with_handled(event_or_peek, self, |mut s, event| match event {
                 CommonEvent::Input(input) => match input {
@@ -1024,6 +1326,9 @@ impl EventRoutine for GameOverEventRoutine {
                         audio_table,
                         player_coord: GameCoord::of_player(instance.game.player_info()),
                         config,
+                        achievements,
+                        achievement_notification: &mut instance.achievement_notification,
+                        storage_wrapper,
                     };
                     event_context.next_frame();
                     for event in instance.game.events() {
@@ -1054,6 +1359,8 @@ impl EventRoutine for GameOverEventRoutine {
                     mouse_coord: None,
                     mode: Mode::Normal,
                     action_error: None,
+                    palette: data.config.theme.palette(),
+                    tileset: data.tileset(),
                 },
                 context,
                 frame,
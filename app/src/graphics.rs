@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+/// Cell scales which can be chosen from the options menu
+const SCALES: &[f64] = &[1., 1.5, 2., 2.5, 3.];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Font {
+    Thin,
+    Thick,
+}
+
+impl Font {
+    pub fn all() -> &'static [Font] {
+        &[Font::Thin, Font::Thick]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Font::Thin => "Thin",
+            Font::Thick => "Thick",
+        }
+    }
+
+    /// The font after this one, wrapping around to the first
+    pub fn next(self) -> Self {
+        let all = Self::all();
+        let index = all.iter().position(|&font| font == self).unwrap();
+        all[(index + 1) % all.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Settings for the graphical frontend. These are read before the window is created, so changes
+/// made from the options menu take effect the next time the game starts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GraphicsConfig {
+    /// Multiplier applied to the size of each cell
+    pub scale: f64,
+    pub font: Font,
+    /// The size of the window at the end of the previous windowed session
+    #[serde(default)]
+    pub window_size: Option<WindowSize>,
+}
+
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        Self {
+            scale: 1.,
+            font: Font::Thin,
+            window_size: None,
+        }
+    }
+}
+
+impl GraphicsConfig {
+    /// The smallest selectable scale larger than the current one, wrapping around to the first
+    pub fn next_scale(&self) -> f64 {
+        SCALES
+            .iter()
+            .cloned()
+            .find(|&scale| scale > self.scale)
+            .unwrap_or(SCALES[0])
+    }
+
+    /// Scales which are not positive and finite would produce an unusable window
+    pub fn is_valid_scale(scale: f64) -> bool {
+        scale.is_finite() && scale > 0.
+    }
+
+    /// The largest whole-number scale at which a window of `size_in_cells` cells, each
+    /// `cell_size` pixels across, fits on a monitor of the given size. Fractional scales blur the
+    /// font when stretched to fill the screen, so this is used instead of `scale` when fullscreen.
+    pub fn fullscreen_scale(
+        monitor_size: WindowSize,
+        size_in_cells: (f64, f64),
+        cell_size: f64,
+    ) -> f64 {
+        let (width_in_cells, height_in_cells) = size_in_cells;
+        let max_scale_x = monitor_size.width as f64 / (width_in_cells * cell_size);
+        let max_scale_y = monitor_size.height as f64 / (height_in_cells * cell_size);
+        max_scale_x.min(max_scale_y).floor().max(1.)
+    }
+}
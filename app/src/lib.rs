@@ -5,7 +5,11 @@ mod controls;
 mod depth;
 mod frontend;
 mod game;
+mod graphics;
+mod narration;
+mod palette;
 mod render;
+mod tileset;
 mod ui;
 
 pub use app::*;
@@ -13,3 +17,5 @@ pub use audio::AppAudioPlayer;
 pub use controls::Controls;
 
 pub use frontend::Frontend;
+pub use graphics::{Font, GraphicsConfig, WindowSize};
+pub use narration::Narration;
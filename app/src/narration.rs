@@ -0,0 +1,280 @@
+use crate::{render, ui};
+use slime99_game::player::{AbilityTarget, Outcome};
+use slime99_game::{CardinalDirection, Coord, Game, NpcAction, Tile};
+use std::io::Write;
+
+fn direction_str(direction: CardinalDirection) -> &'static str {
+    match direction {
+        CardinalDirection::North => "north",
+        CardinalDirection::East => "east",
+        CardinalDirection::South => "south",
+        CardinalDirection::West => "west",
+    }
+}
+
+/// Describes a position relative to the player, such as "3 north, 2 east"
+fn offset_str(offset: Coord) -> String {
+    let mut parts = Vec::new();
+    if offset.y < 0 {
+        parts.push(format!("{} north", -offset.y));
+    } else if offset.y > 0 {
+        parts.push(format!("{} south", offset.y));
+    }
+    if offset.x < 0 {
+        parts.push(format!("{} west", -offset.x));
+    } else if offset.x > 0 {
+        parts.push(format!("{} east", offset.x));
+    }
+    if parts.is_empty() {
+        "where you stand".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+fn action_str(action: NpcAction, player_coord: Coord) -> String {
+    match action {
+        NpcAction::Wait => "waiting".to_string(),
+        NpcAction::Walk(direction) => format!("moving {}", direction_str(direction)),
+        NpcAction::Summon => "about to summon slimes".to_string(),
+        NpcAction::Flood => "about to flood the area with sludge".to_string(),
+        NpcAction::Charge(direction) => format!("about to charge {}", direction_str(direction)),
+        NpcAction::Shoot(target) => {
+            if target == player_coord {
+                "about to spit at you".to_string()
+            } else {
+                format!("about to spit at {}", offset_str(target - player_coord))
+            }
+        }
+    }
+}
+
+fn is_feature(tile: Tile) -> bool {
+    match tile {
+        Tile::Stairs
+        | Tile::Key
+        | Tile::AttackItem { .. }
+        | Tile::DefendItem { .. }
+        | Tile::TechItem { .. } => true,
+        _ => false,
+    }
+}
+
+fn cards_str(game: &Game) -> String {
+    let player = game.player();
+    let mut s = "Next cards:".to_string();
+    let decks = [
+        (
+            "attack",
+            AbilityTarget::Attack,
+            player
+                .attack
+                .iter()
+                .map(|&a| Outcome::Attack(a))
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "defend",
+            AbilityTarget::Defend,
+            player
+                .defend
+                .iter()
+                .map(|&d| Outcome::Defend(d))
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "tech",
+            AbilityTarget::Tech,
+            player
+                .tech
+                .iter()
+                .map(|&t| Outcome::Tech(t))
+                .collect::<Vec<_>>(),
+        ),
+    ];
+    for (i, (name, target, deck)) in decks.iter().enumerate() {
+        s.push_str(if i == 0 { " " } else { ", " });
+        s.push_str(name);
+        s.push(' ');
+        let peeking = player.peeking == Some(*target);
+        match deck.first() {
+            Some(&card) => {
+                ui::write_card(card, &mut s);
+                for (index, &card) in deck.iter().enumerate().skip(1) {
+                    if !ui::is_card_revealed(index, peeking) {
+                        break;
+                    }
+                    s.push_str(" then ");
+                    ui::write_card(card, &mut s);
+                }
+                s.push_str(&format!(" ({} left)", deck.len()));
+            }
+            None => s.push_str("none"),
+        }
+    }
+    if player.defend.len() == 0 {
+        s.push_str(". You have no defend cards, so the next hit will kill you");
+    }
+    s
+}
+
+struct Feature {
+    coord: Coord,
+    tile: Tile,
+    description: String,
+}
+
+/// The parts of the game state which are narrated, so consecutive turns can be compared
+struct Snapshot {
+    level: u32,
+    characters: Vec<String>,
+    features: Vec<Feature>,
+    cards: String,
+}
+
+impl Snapshot {
+    fn new(game: &Game) -> Self {
+        let player_coord = game.player_coord();
+        let visibility_grid = game.visibility_grid();
+        let mut characters = Vec::new();
+        let mut features = Vec::new();
+        for entity in game.to_render_entities() {
+            if !visibility_grid.is_coord_currently_visible(entity.coord) {
+                continue;
+            }
+            let name = match render::tile_str(entity.tile) {
+                Some(name) => name,
+                None => continue,
+            };
+            let offset = entity.coord - player_coord;
+            if let Some(hit_points) = entity.hit_points {
+                if entity.tile == Tile::Player {
+                    continue;
+                }
+                let mut description = format!(
+                    "{}, {}, {}/{} hit points",
+                    name,
+                    offset_str(offset),
+                    hit_points.current,
+                    hit_points.max
+                );
+                if let Some(next_action) = entity.next_action {
+                    description.push_str(", ");
+                    description.push_str(&action_str(next_action, player_coord));
+                }
+                characters.push((offset.x.abs() + offset.y.abs(), description));
+            } else if is_feature(entity.tile) {
+                let mut description = name.to_string();
+                if let Some(card) = entity.revealed_card {
+                    description.push_str(" (");
+                    ui::write_card(card, &mut description);
+                    description.push(')');
+                }
+                description.push_str(", ");
+                description.push_str(&offset_str(offset));
+                features.push(Feature {
+                    coord: entity.coord,
+                    tile: entity.tile,
+                    description,
+                });
+            }
+        }
+        characters.sort_by_key(|&(distance, _)| distance);
+        Self {
+            level: game.current_level(),
+            characters: characters
+                .into_iter()
+                .map(|(_, description)| description)
+                .collect(),
+            features,
+            cards: cards_str(game),
+        }
+    }
+
+    fn has_feature(&self, feature: &Feature) -> bool {
+        self.features
+            .iter()
+            .any(|f| f.coord == feature.coord && f.tile == feature.tile)
+    }
+}
+
+/// Writes a plain-text description of what changes each turn, for players using a screen reader
+pub struct Narration {
+    output: Box<dyn Write>,
+    last: Option<Snapshot>,
+    /// The level and round narrated by the previous call to `turn`
+    last_turn: Option<(u32, u64)>,
+}
+
+impl Narration {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        Self {
+            output,
+            last: None,
+            last_turn: None,
+        }
+    }
+
+    fn write_lines(&mut self, lines: &[String]) {
+        for line in lines {
+            let _ = writeln!(self.output, "{}", line);
+        }
+        let _ = self.output.flush();
+    }
+
+    fn describe_characters(snapshot: &Snapshot, lines: &mut Vec<String>) {
+        if snapshot.characters.is_empty() {
+            lines.push("Nothing in view".to_string());
+        } else {
+            lines.extend(snapshot.characters.iter().cloned());
+        }
+    }
+
+    /// Describes whatever changed since the previous call. Does nothing unless a round has
+    /// finished or the level has changed since then.
+    pub fn turn(&mut self, game: &Game) {
+        let turn = (game.current_level(), game.rounds());
+        if self.last_turn == Some(turn) {
+            return;
+        }
+        self.last_turn = Some(turn);
+        let snapshot = Snapshot::new(game);
+        let mut lines = Vec::new();
+        match self.last.as_ref() {
+            Some(last) if last.level == snapshot.level => {
+                if last.characters != snapshot.characters {
+                    Self::describe_characters(&snapshot, &mut lines);
+                }
+                for feature in snapshot.features.iter() {
+                    if !last.has_feature(feature) {
+                        lines.push(format!("You see {}", feature.description));
+                    }
+                }
+                if last.cards != snapshot.cards {
+                    lines.push(snapshot.cards.clone());
+                }
+            }
+            _ => Self::describe(&snapshot, &mut lines),
+        }
+        self.write_lines(&lines);
+        self.last = Some(snapshot);
+    }
+
+    /// Describes everything in view regardless of what changed
+    pub fn describe_surroundings(&mut self, game: &Game) {
+        let snapshot = Snapshot::new(game);
+        let mut lines = Vec::new();
+        Self::describe(&snapshot, &mut lines);
+        self.write_lines(&lines);
+        self.last = Some(snapshot);
+    }
+
+    fn describe(snapshot: &Snapshot, lines: &mut Vec<String>) {
+        lines.push(format!("Floor {}", snapshot.level));
+        Self::describe_characters(snapshot, lines);
+        for feature in snapshot.features.iter() {
+            lines.push(format!("You see {}", feature.description));
+        }
+        lines.push(snapshot.cards.clone());
+    }
+}
@@ -0,0 +1,285 @@
+use chargrid::render::Rgb24;
+use serde::{Deserialize, Serialize};
+
+/// Selects the palette used to render the game. Chosen in the options menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    Standard,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+    HighContrast,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::Standard
+    }
+}
+
+impl Theme {
+    pub fn all() -> &'static [Theme] {
+        &[
+            Theme::Standard,
+            Theme::Deuteranopia,
+            Theme::Protanopia,
+            Theme::Tritanopia,
+            Theme::HighContrast,
+        ]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Standard => "Standard",
+            Theme::Deuteranopia => "Deuteranopia",
+            Theme::Protanopia => "Protanopia",
+            Theme::Tritanopia => "Tritanopia",
+            Theme::HighContrast => "High Contrast",
+        }
+    }
+
+    /// The theme after this one, wrapping around to the first
+    pub fn next(self) -> Self {
+        let all = Self::all();
+        let index = all.iter().position(|&theme| theme == self).unwrap();
+        all[(index + 1) % all.len()]
+    }
+
+    pub fn palette(self) -> &'static Palette {
+        match self {
+            Theme::Standard => &STANDARD,
+            Theme::Deuteranopia => &DEUTERANOPIA,
+            Theme::Protanopia => &PROTANOPIA,
+            Theme::Tritanopia => &TRITANOPIA,
+            Theme::HighContrast => &HIGH_CONTRAST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NpcColours {
+    pub foreground: Rgb24,
+    pub background: Rgb24,
+}
+
+const fn npc(foreground: Rgb24, background: Rgb24) -> NpcColours {
+    NpcColours {
+        foreground,
+        background,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    pub text: Rgb24,
+    pub text_faint: Rgb24,
+    pub text_disabled: Rgb24,
+    /// Game over text, empty decks, and the aim line
+    pub danger: Rgb24,
+    /// The examine cursor, mouse hover, and achievement notifications
+    pub highlight: Rgb24,
+    pub player: Rgb24,
+    pub floor_foreground: Rgb24,
+    pub floor_background: Rgb24,
+    pub wall_top: Rgb24,
+    pub wall_front: Rgb24,
+    pub door_foreground: Rgb24,
+    pub door_background: Rgb24,
+    pub locked_door_foreground: Rgb24,
+    pub locked_door_background: Rgb24,
+    pub stairs: Rgb24,
+    /// Sludge flickers between shades which are scaled by the green channel of this colour
+    pub sludge: Rgb24,
+    pub bridge_foreground: Rgb24,
+    pub bridge_background: Rgb24,
+    pub remembered_foreground: Rgb24,
+    pub remembered_background: Rgb24,
+    pub slime_divide: NpcColours,
+    pub slime_swap: NpcColours,
+    pub slime_teleport: NpcColours,
+    pub slime_goo: NpcColours,
+    pub slime_boss: NpcColours,
+    pub slime_upgrade: NpcColours,
+    pub slime_curse: NpcColours,
+    pub slime_spitter: NpcColours,
+    pub human: NpcColours,
+    pub former_human: NpcColours,
+    pub bullet: Rgb24,
+    pub rocket: Rgb24,
+    pub sludge_spit: Rgb24,
+    pub item: Rgb24,
+    pub key: Rgb24,
+    /// Light applied to the whole map when the game is lost
+    pub game_over_light: Rgb24,
+    pub slowed: Rgb24,
+    pub stunned: Rgb24,
+    pub poisoned: Rgb24,
+    pub rooted: Rgb24,
+    pub sludge_coated: Rgb24,
+    pub marked: Rgb24,
+    pub humans_following: Rgb24,
+    pub sludge_label: Rgb24,
+    pub keys: Rgb24,
+    pub credits: Rgb24,
+}
+
+impl Palette {
+    pub fn sludge(&self, colour_hint: Option<Rgb24>) -> Rgb24 {
+        match colour_hint {
+            Some(colour_hint) => self.sludge.normalised_mul(Rgb24::new_grey(colour_hint.g)),
+            None => self.sludge,
+        }
+    }
+}
+
+const STANDARD: Palette = Palette {
+    text: Rgb24::new_grey(255),
+    text_faint: Rgb24::new_grey(127),
+    text_disabled: Rgb24::new_grey(63),
+    danger: Rgb24::new(255, 0, 0),
+    highlight: Rgb24::new(255, 255, 0),
+    player: Rgb24::new(255, 255, 255),
+    floor_foreground: Rgb24::new(0, 187, 187),
+    floor_background: Rgb24::new(0, 127, 127),
+    wall_top: Rgb24::new(255, 0, 255),
+    wall_front: Rgb24::new(127, 0, 127),
+    door_foreground: Rgb24::new(255, 127, 255),
+    door_background: Rgb24::new(127, 0, 127),
+    locked_door_foreground: Rgb24::new(255, 187, 63),
+    locked_door_background: Rgb24::new(127, 63, 0),
+    stairs: Rgb24::new(255, 255, 255),
+    sludge: Rgb24::new(0, 255, 0),
+    bridge_foreground: Rgb24::new(127, 127, 0),
+    bridge_background: Rgb24::new(200, 127, 0),
+    remembered_foreground: Rgb24::new_grey(63),
+    remembered_background: Rgb24::new_grey(15),
+    slime_divide: npc(Rgb24::new(255, 63, 63), Rgb24::new(31, 15, 15)),
+    slime_swap: npc(Rgb24::new(127, 127, 255), Rgb24::new(15, 15, 31)),
+    slime_teleport: npc(Rgb24::new(187, 63, 255), Rgb24::new(15, 0, 31)),
+    slime_goo: npc(Rgb24::new(0, 255, 0), Rgb24::new(0, 63, 0)),
+    slime_boss: npc(Rgb24::new(127, 127, 127), Rgb24::new(0, 0, 0)),
+    slime_upgrade: npc(Rgb24::new(255, 255, 255), Rgb24::new(31, 31, 31)),
+    slime_curse: npc(Rgb24::new(187, 187, 187), Rgb24::new(31, 31, 31)),
+    slime_spitter: npc(Rgb24::new(187, 255, 63), Rgb24::new(15, 31, 0)),
+    human: npc(Rgb24::new(255, 187, 127), Rgb24::new(31, 15, 0)),
+    former_human: npc(Rgb24::new(127, 187, 63), Rgb24::new(15, 31, 0)),
+    bullet: Rgb24::new(255, 255, 187),
+    rocket: Rgb24::new(255, 187, 63),
+    sludge_spit: Rgb24::new(0, 255, 0),
+    item: Rgb24::new_grey(255),
+    key: Rgb24::new(255, 187, 63),
+    game_over_light: Rgb24::new(255, 87, 31),
+    slowed: Rgb24::new(0, 87, 187),
+    stunned: Rgb24::new(187, 187, 0),
+    poisoned: Rgb24::new(127, 0, 187),
+    rooted: Rgb24::new(127, 63, 0),
+    sludge_coated: Rgb24::new(63, 127, 0),
+    marked: Rgb24::new(187, 0, 0),
+    humans_following: Rgb24::new(255, 187, 127),
+    sludge_label: Rgb24::new(0, 255, 0),
+    keys: Rgb24::new(255, 187, 63),
+    credits: Rgb24::new(255, 255, 127),
+};
+
+// The colour-blind palettes are built from hues which remain distinct under each condition.
+// Red-green deficiencies get blues, oranges and yellows in place of reds and greens, and
+// blue-yellow deficiency gets reds, cyans and magentas in place of blues and yellows.
+
+const DEUTERANOPIA: Palette = Palette {
+    danger: Rgb24::new(255, 127, 0),
+    floor_foreground: Rgb24::new(63, 127, 187),
+    floor_background: Rgb24::new(31, 63, 127),
+    wall_top: Rgb24::new(187, 187, 187),
+    wall_front: Rgb24::new(87, 87, 87),
+    door_foreground: Rgb24::new(255, 255, 187),
+    door_background: Rgb24::new(87, 87, 87),
+    sludge: Rgb24::new(255, 187, 0),
+    bridge_foreground: Rgb24::new(31, 31, 31),
+    bridge_background: Rgb24::new(127, 187, 255),
+    slime_divide: npc(Rgb24::new(255, 127, 0), Rgb24::new(31, 15, 0)),
+    slime_swap: npc(Rgb24::new(87, 187, 255), Rgb24::new(0, 15, 31)),
+    slime_teleport: npc(Rgb24::new(255, 127, 255), Rgb24::new(31, 0, 31)),
+    slime_goo: npc(Rgb24::new(255, 255, 0), Rgb24::new(31, 31, 0)),
+    slime_spitter: npc(Rgb24::new(255, 255, 187), Rgb24::new(31, 31, 15)),
+    former_human: npc(Rgb24::new(187, 187, 255), Rgb24::new(15, 15, 31)),
+    sludge_spit: Rgb24::new(255, 187, 0),
+    game_over_light: Rgb24::new(255, 187, 63),
+    poisoned: Rgb24::new(187, 87, 187),
+    rooted: Rgb24::new(87, 87, 87),
+    sludge_coated: Rgb24::new(187, 127, 0),
+    marked: Rgb24::new(255, 255, 255),
+    sludge_label: Rgb24::new(255, 187, 0),
+    ..STANDARD
+};
+
+// Reds appear dim with protanopia, so danger and marks are brightened compared to deuteranopia
+const PROTANOPIA: Palette = Palette {
+    danger: Rgb24::new(255, 187, 0),
+    slime_divide: npc(Rgb24::new(255, 187, 63), Rgb24::new(31, 15, 0)),
+    human: npc(Rgb24::new(255, 255, 255), Rgb24::new(31, 31, 31)),
+    humans_following: Rgb24::new(255, 255, 255),
+    game_over_light: Rgb24::new(255, 255, 127),
+    ..DEUTERANOPIA
+};
+
+const TRITANOPIA: Palette = Palette {
+    highlight: Rgb24::new(255, 0, 255),
+    floor_foreground: Rgb24::new(127, 127, 127),
+    floor_background: Rgb24::new(63, 63, 63),
+    wall_top: Rgb24::new(0, 187, 187),
+    wall_front: Rgb24::new(0, 87, 87),
+    door_foreground: Rgb24::new(127, 255, 255),
+    door_background: Rgb24::new(0, 87, 87),
+    locked_door_foreground: Rgb24::new(255, 127, 127),
+    locked_door_background: Rgb24::new(87, 0, 0),
+    sludge: Rgb24::new(255, 63, 127),
+    bridge_foreground: Rgb24::new(31, 31, 31),
+    bridge_background: Rgb24::new(187, 187, 187),
+    slime_divide: npc(Rgb24::new(255, 63, 63), Rgb24::new(31, 0, 0)),
+    slime_swap: npc(Rgb24::new(0, 255, 255), Rgb24::new(0, 31, 31)),
+    slime_teleport: npc(Rgb24::new(255, 127, 255), Rgb24::new(31, 0, 31)),
+    slime_goo: npc(Rgb24::new(255, 127, 187), Rgb24::new(31, 0, 15)),
+    slime_spitter: npc(Rgb24::new(127, 255, 255), Rgb24::new(0, 31, 31)),
+    former_human: npc(Rgb24::new(187, 127, 127), Rgb24::new(31, 15, 15)),
+    sludge_spit: Rgb24::new(255, 63, 127),
+    key: Rgb24::new(255, 127, 127),
+    stunned: Rgb24::new(0, 187, 187),
+    poisoned: Rgb24::new(187, 0, 187),
+    rooted: Rgb24::new(87, 87, 87),
+    sludge_coated: Rgb24::new(187, 31, 87),
+    sludge_label: Rgb24::new(255, 63, 127),
+    keys: Rgb24::new(255, 127, 127),
+    credits: Rgb24::new(255, 255, 255),
+    ..STANDARD
+};
+
+// Bright foregrounds against black so that every cell stands out from its surroundings
+const HIGH_CONTRAST: Palette = Palette {
+    text_faint: Rgb24::new_grey(187),
+    text_disabled: Rgb24::new_grey(127),
+    floor_foreground: Rgb24::new_grey(87),
+    floor_background: Rgb24::new_grey(0),
+    wall_top: Rgb24::new_grey(255),
+    wall_front: Rgb24::new_grey(187),
+    door_foreground: Rgb24::new(255, 255, 0),
+    door_background: Rgb24::new_grey(0),
+    locked_door_foreground: Rgb24::new(255, 127, 0),
+    locked_door_background: Rgb24::new_grey(0),
+    sludge: Rgb24::new(0, 255, 0),
+    bridge_foreground: Rgb24::new_grey(255),
+    bridge_background: Rgb24::new_grey(0),
+    remembered_foreground: Rgb24::new_grey(127),
+    remembered_background: Rgb24::new_grey(0),
+    slime_divide: npc(Rgb24::new(255, 0, 0), Rgb24::new_grey(0)),
+    slime_swap: npc(Rgb24::new(0, 255, 255), Rgb24::new_grey(0)),
+    slime_teleport: npc(Rgb24::new(255, 0, 255), Rgb24::new_grey(0)),
+    slime_goo: npc(Rgb24::new(0, 255, 0), Rgb24::new_grey(0)),
+    slime_boss: npc(Rgb24::new_grey(255), Rgb24::new_grey(0)),
+    slime_upgrade: npc(Rgb24::new_grey(255), Rgb24::new_grey(0)),
+    slime_curse: npc(Rgb24::new_grey(255), Rgb24::new_grey(0)),
+    slime_spitter: npc(Rgb24::new(255, 255, 0), Rgb24::new_grey(0)),
+    human: npc(Rgb24::new(255, 187, 127), Rgb24::new_grey(0)),
+    former_human: npc(Rgb24::new(127, 255, 127), Rgb24::new_grey(0)),
+    game_over_light: Rgb24::new(255, 127, 127),
+    ..STANDARD
+};
@@ -1,4 +1,11 @@
-use crate::{blink::Blink, depth, game::GameStatus, ui};
+use crate::{
+    blink::Blink,
+    depth,
+    game::GameStatus,
+    palette::{NpcColours, Palette},
+    tileset::{Glyphs, NpcActionGlyphs, NpcGlyph, Tileset},
+    ui,
+};
 use chargrid::render::{
     blend_mode, ColModify, Coord, Frame, Rgb24, Style, View, ViewCell, ViewContext,
 };
@@ -6,7 +13,8 @@ use chargrid::text::{wrap, StringView, StringViewSingleLine};
 use direction::CardinalDirection;
 use line_2d::{Config as LineConfig, LineSegment};
 use slime99_game::{
-    ActionError, CellVisibility, Game, Layer, NpcAction, Tile, ToRenderEntity, MAP_SIZE,
+    Achievement, ActionError, CellVisibility, Game, GameMode, Layer, NpcAction, StatusEffect,
+    StatusEffects, Tile, ToRenderEntity, MAP_SIZE,
 };
 use std::time::Duration;
 
@@ -28,6 +36,8 @@ pub struct GameToRender<'a> {
     pub mouse_coord: Option<Coord>,
     pub mode: Mode,
     pub action_error: Option<ActionError>,
+    pub palette: &'a Palette,
+    pub tileset: &'a Tileset,
 }
 
 pub struct GameView {
@@ -49,6 +59,23 @@ impl GameView {
         }
     }
 
+    pub fn view_achievement_notification<F: Frame, C: ColModify>(
+        &self,
+        achievement: Achievement,
+        palette: &Palette,
+        context: ViewContext<C>,
+        frame: &mut F,
+    ) {
+        let text = format!("Achievement unlocked: {}", achievement.name());
+        StringViewSingleLine::new(
+            Style::new()
+                .with_foreground(palette.highlight)
+                .with_background(Rgb24::new_grey(0))
+                .with_bold(true),
+        )
+        .view(&text, context.add_depth(depth::GAME_MAX), frame);
+    }
+
     pub fn absolute_coord_to_game_relative_screen_coord(&self, coord: Coord) -> Coord {
         coord - self.last_offset
     }
@@ -59,11 +86,20 @@ impl GameView {
         context: ViewContext<C>,
         frame: &mut F,
     ) {
+        let palette = game_to_render.palette;
+        let tileset = game_to_render.tileset;
         match game_to_render.status {
             GameStatus::Playing => {
                 let mut entity_under_cursor = None;
                 for entity in game_to_render.game.to_render_entities() {
-                    render_entity(&entity, game_to_render.game, context, frame);
+                    render_entity(
+                        &entity,
+                        game_to_render.game,
+                        palette,
+                        tileset,
+                        context,
+                        frame,
+                    );
                     if let Some(mouse_coord) = game_to_render.mouse_coord {
                         let game_coord = mouse_coord / 2;
                         if entity.coord == game_coord {
@@ -80,20 +116,33 @@ impl GameView {
                                 | CellVisibility::CurrentlyVisibleWithLightColour(None) => None,
                             };
                             if let Some(verb) = verb {
-                                if let Some((max_depth, _tile, _verb)) = entity_under_cursor {
-                                    let depth = layer_depth(entity.layer);
-                                    if depth > max_depth {
-                                        entity_under_cursor = Some((depth, entity.tile, verb));
+                                let status_effects = match verb {
+                                    MessageVerb::See => entity.status_effects.clone(),
+                                    MessageVerb::Remember => None,
+                                };
+                                let depth = layer_depth(entity.layer);
+                                let replace = match &entity_under_cursor {
+                                    Some((max_depth, _tile, _verb, _status_effects, _card)) => {
+                                        depth > *max_depth
                                     }
-                                } else {
-                                    entity_under_cursor =
-                                        Some((layer_depth(entity.layer), entity.tile, verb));
+                                    None => true,
+                                };
+                                if replace {
+                                    entity_under_cursor = Some((
+                                        depth,
+                                        entity.tile,
+                                        verb,
+                                        status_effects,
+                                        entity.revealed_card,
+                                    ));
                                 }
                             }
                         }
                     }
                 }
-                if let Some((_depth, tile, verb)) = entity_under_cursor {
+                if let Some((_depth, tile, verb, status_effects, revealed_card)) =
+                    entity_under_cursor
+                {
                     if let Some(description) = tile_str(tile) {
                         let verb_str = match verb {
                             MessageVerb::Remember => "remember seeing",
@@ -101,11 +150,17 @@ impl GameView {
                         };
                         let mut buf = String::new();
                         use std::fmt::Write;
-                        write!(&mut buf, "You {} {} here.", verb_str, description).unwrap();
-                        StringViewSingleLine::new(
-                            Style::new().with_foreground(Rgb24::new_grey(255)),
-                        )
-                        .view(
+                        write!(&mut buf, "You {} {}", verb_str, description).unwrap();
+                        if let Some(status_effects) = status_effects {
+                            write_status_effects(&status_effects, &mut buf);
+                        }
+                        if let Some(card) = revealed_card {
+                            write!(&mut buf, " (").unwrap();
+                            ui::write_card(card, &mut buf);
+                            write!(&mut buf, ")").unwrap();
+                        }
+                        write!(&mut buf, " here.").unwrap();
+                        StringViewSingleLine::new(Style::new().with_foreground(palette.text)).view(
                             &buf,
                             context.add_offset(Coord::new(0, MAP_SIZE.height() as i32 * 2)),
                             frame,
@@ -115,9 +170,7 @@ impl GameView {
                     let current_level = game_to_render.game.current_level();
                     if current_level == 0 {
                         StringView::new(
-                            Style::new()
-                                .with_foreground(Rgb24::new_grey(255))
-                                .with_bold(true),
+                            Style::new().with_foreground(palette.text).with_bold(true),
                             wrap::Word::new(),
                         )
                         .view(
@@ -129,37 +182,70 @@ impl GameView {
                             frame,
                         );
                     } else {
-                        if current_level == slime99_game::FINAL_LEVEL {
+                        let endless = game_to_render.game.mode() == GameMode::Endless;
+                        if game_to_render.game.is_boss_level() {
+                            let text = if endless {
+                                format!("BOSS FLOOR {}", current_level)
+                            } else {
+                                "FINAL FLOOR".to_string()
+                            };
                             StringView::new(
-                                Style::new()
-                                    .with_foreground(Rgb24::new_grey(255))
-                                    .with_bold(true),
+                                Style::new().with_foreground(palette.text).with_bold(true),
                                 wrap::Word::new(),
                             )
                             .view(
-                                "FINAL FLOOR".to_string(),
+                                text,
                                 context.add_offset(Coord::new(0, MAP_SIZE.height() as i32 * 2)),
                                 frame,
                             );
                         } else {
-                            StringViewSingleLine::new(
-                                Style::new().with_foreground(Rgb24::new_grey(255)),
-                            )
-                            .view(
-                                format!("Floor {}/{}", current_level, slime99_game::FINAL_LEVEL),
-                                context.add_offset(Coord::new(0, MAP_SIZE.height() as i32 * 2)),
-                                frame,
-                            );
+                            StringViewSingleLine::new(Style::new().with_foreground(palette.text))
+                                .view(
+                                    if endless {
+                                        format!("Floor {}", current_level)
+                                    } else {
+                                        format!(
+                                            "Floor {}/{}",
+                                            current_level,
+                                            slime99_game::FINAL_LEVEL
+                                        )
+                                    },
+                                    context.add_offset(Coord::new(0, MAP_SIZE.height() as i32 * 2)),
+                                    frame,
+                                );
                         }
                     }
                 }
             }
             GameStatus::Over => {
                 for entity in game_to_render.game.to_render_entities() {
-                    render_entity_game_over(&entity, game_to_render.game, context, frame);
+                    render_entity_game_over(
+                        &entity,
+                        game_to_render.game,
+                        palette,
+                        tileset,
+                        context,
+                        frame,
+                    );
                 }
-                StringView::new(Style::new().with_foreground(Rgb24::new(255, 0, 0)), wrap::Word::new()).view(
-                    "You failed. The slimes overrun the city and CONSUME WHAT REMAINS OF HUMANITY. Press a key to continue...",
+                let difficulty = game_to_render.game.difficulty().name();
+                let text = match game_to_render.game.mode() {
+                    GameMode::Campaign => format!(
+                        "You failed ({}). The slimes overrun the city and CONSUME WHAT REMAINS OF HUMANITY. Press a key to continue...",
+                        difficulty
+                    ),
+                    GameMode::Endless => format!(
+                        "You fell on floor {} ({}). The slimes CONSUME WHAT REMAINS OF HUMANITY. Press a key to continue...",
+                        game_to_render.game.current_level(),
+                        difficulty
+                    ),
+                };
+                StringView::new(
+                    Style::new().with_foreground(palette.danger),
+                    wrap::Word::new(),
+                )
+                .view(
+                    text,
                     context.add_offset(Coord::new(0, MAP_SIZE.height() as i32 * 2)),
                     frame,
                 );
@@ -168,7 +254,7 @@ impl GameView {
         if let Some(action_error) = game_to_render.action_error {
             let s = action_error_str(action_error);
             StringView::new(
-                Style::new().with_foreground(Rgb24::new(255, 255, 255)),
+                Style::new().with_foreground(palette.text),
                 wrap::Word::new(),
             )
             .view(
@@ -178,7 +264,10 @@ impl GameView {
             );
         }
         let ui = ui::Ui {
+            palette,
             player: game_to_render.game.player(),
+            escort: game_to_render.game.escort_objective(),
+            sludge_spread: game_to_render.game.sludge_spread(),
         };
         ui::UiView.view(ui, context.add_offset(Coord::new(39, 0)), frame);
         match game_to_render.mode {
@@ -204,7 +293,7 @@ impl GameView {
                             frame.blend_cell_background_relative(
                                 output_coord,
                                 depth::GAME_MAX,
-                                Rgb24::new(255, 0, 0),
+                                palette.danger,
                                 127,
                                 blend_mode::LinearInterpolate,
                                 context,
@@ -219,7 +308,7 @@ impl GameView {
                         frame.blend_cell_background_relative(
                             output_coord,
                             depth::GAME_MAX,
-                            Rgb24::new(255, 0, 0),
+                            palette.danger,
                             alpha,
                             blend_mode::LinearInterpolate,
                             context,
@@ -236,14 +325,14 @@ impl GameView {
                         frame.blend_cell_background_relative(
                             output_coord,
                             depth::GAME_MAX,
-                            Rgb24::new(255, 255, 0),
+                            palette.highlight,
                             alpha,
                             blend_mode::LinearInterpolate,
                             context,
                         );
                     }
                 }
-                StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(127))).view(
+                StringViewSingleLine::new(Style::new().with_foreground(palette.text_faint)).view(
                     "Examining (escape to return to game)",
                     context.add_offset(Coord::new(0, MAP_SIZE.height() as i32 * 2 + 1)),
                     frame,
@@ -259,7 +348,7 @@ impl GameView {
                     frame.blend_cell_background_relative(
                         output_coord,
                         depth::GAME_MAX,
-                        Rgb24::new(255, 255, 0),
+                        palette.highlight,
                         alpha,
                         blend_mode::LinearInterpolate,
                         context,
@@ -292,120 +381,58 @@ fn apply_lighting(cell_colour: Rgb24, light_colour: Rgb24) -> Rgb24 {
 }
 
 impl Quad {
-    fn new_repeating(to_repeat: ViewCell) -> Self {
-        Self {
-            cells: [to_repeat, to_repeat, to_repeat, to_repeat],
-        }
-    }
-    fn enumerate<'a>(&'a self) -> impl 'a + Iterator<Item = (Coord, ViewCell)> {
-        quad::OFFSETS
-            .iter()
-            .cloned()
-            .zip(self.cells.iter().cloned())
-    }
-    fn new_wall_front(front_col: Rgb24, top_col: Rgb24) -> Self {
-        let front = ViewCell::new()
-            .with_character(' ')
-            .with_background(front_col);
-        let top = ViewCell::new()
-            .with_character('█')
-            .with_background(front_col)
-            .with_foreground(top_col);
-        Self {
-            cells: [top, top, front, front],
-        }
-    }
-    fn new_wall_top(top: Rgb24) -> Self {
-        let top = ViewCell::new().with_character(' ').with_background(top);
-        Self::new_repeating(top)
-    }
-    fn new_floor(foreground: Rgb24, background: Rgb24) -> Self {
-        let base = ViewCell::new()
-            .with_foreground(foreground)
-            .with_background(background);
-        Self {
-            cells: [
-                base.with_character('▗'),
-                base.with_character('▖'),
-                base.with_character('▝'),
-                base.with_character('▘'),
-            ],
-        }
-    }
-    fn new_door_closed(foreground: Rgb24, background: Rgb24) -> Self {
-        let base = ViewCell::new()
-            .with_foreground(background)
-            .with_background(foreground);
-        Self {
-            cells: [
-                base.with_character('▘'),
-                base.with_character('▝'),
-                base.with_character('▖'),
-                base.with_character('▗'),
-            ],
-        }
-    }
-    fn new_door_open(foreground: Rgb24, background: Rgb24) -> Self {
-        let base = ViewCell::new()
-            .with_foreground(foreground)
-            .with_background(background);
-        Self {
-            cells: [
-                base.with_character('▄'),
-                base.with_character('▄'),
-                base.with_character('▀'),
-                base.with_character('▀'),
-            ],
-        }
-    }
-    fn new_stairs(foreground: Rgb24, background: Rgb24) -> Self {
-        let base = ViewCell::new().with_bold(true);
-        Self {
-            cells: [
-                base.with_character('▝')
-                    .with_foreground(background)
-                    .with_background(foreground),
-                base.with_character(' ').with_background(background),
-                base.with_character(' ').with_background(foreground),
-                base.with_character('▝')
-                    .with_foreground(background)
-                    .with_background(foreground),
-            ],
-        }
-    }
-    fn new_player(foreground: Rgb24) -> Self {
-        let base = ViewCell::new().with_bold(true).with_foreground(foreground);
-        Self {
-            cells: [
-                base.with_character('╔'),
-                base.with_character('╗'),
-                base.with_character('╚'),
-                base.with_character('╩'),
-            ],
+    fn new_glyphs(glyphs: &Glyphs, foreground: Option<Rgb24>, background: Option<Rgb24>) -> Self {
+        let foreground = glyphs.foreground.or(foreground);
+        let background = glyphs.background.or(background);
+        let mut cells = [ViewCell::new(); 4];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            let (foreground, background) = if glyphs.inverted[i] {
+                (background, foreground)
+            } else {
+                (foreground, background)
+            };
+            *cell = cell.with_character(glyphs.characters[i]);
+            if let Some(foreground) = foreground {
+                *cell = cell.with_foreground(foreground);
+            }
+            if let Some(background) = background {
+                *cell = cell.with_background(background);
+            }
+            if glyphs.bold {
+                *cell = cell.with_bold(true);
+            }
         }
+        Self { cells }
     }
-    fn new_slime(
-        character: char,
-        foreground: Rgb24,
-        background: Rgb24,
+    fn new_npc(
+        glyph: &NpcGlyph,
+        colours: NpcColours,
+        action_glyphs: &NpcActionGlyphs,
         hit_points: u32,
         next_action: NpcAction,
     ) -> Self {
+        let foreground = glyph.foreground.unwrap_or(colours.foreground);
+        let background = glyph.background.unwrap_or(colours.background);
         let base = ViewCell::new()
             .with_background(background)
             .with_foreground(foreground);
+        let direction_index = |direction: CardinalDirection| match direction {
+            CardinalDirection::North => 0,
+            CardinalDirection::East => 1,
+            CardinalDirection::South => 2,
+            CardinalDirection::West => 3,
+        };
         let action_character = match next_action {
-            NpcAction::Wait => ' ',
-            NpcAction::Walk(direction) => match direction {
-                CardinalDirection::North => '↑',
-                CardinalDirection::East => '→',
-                CardinalDirection::South => '↓',
-                CardinalDirection::West => '←',
-            },
+            NpcAction::Wait => action_glyphs.wait,
+            NpcAction::Walk(direction) => action_glyphs.walk[direction_index(direction)],
+            NpcAction::Summon => action_glyphs.summon,
+            NpcAction::Flood => action_glyphs.flood,
+            NpcAction::Charge(direction) => action_glyphs.charge[direction_index(direction)],
+            NpcAction::Shoot(_) => action_glyphs.shoot,
         };
         Self {
             cells: [
-                base.with_character(character)
+                base.with_character(glyph.character)
                     .with_bold(true)
                     .with_foreground(foreground),
                 base.with_character(action_character),
@@ -414,37 +441,12 @@ impl Quad {
             ],
         }
     }
-    fn new_attack(foreground: Rgb24, special: bool) -> Self {
-        let base = ViewCell::new().with_foreground(foreground).with_bold(true);
-        Self {
-            cells: [
-                base.with_character('A'),
-                base.with_character('t'),
-                base.with_character('k'),
-                base.with_character(if special { '*' } else { ' ' }),
-            ],
-        }
-    }
-    fn new_defend(foreground: Rgb24, special: bool) -> Self {
-        let base = ViewCell::new().with_foreground(foreground).with_bold(true);
-        Self {
-            cells: [
-                base.with_character('D'),
-                base.with_character('e'),
-                base.with_character('f'),
-                base.with_character(if special { '*' } else { ' ' }),
-            ],
-        }
-    }
-    fn new_tech(foreground: Rgb24, special: bool) -> Self {
-        let base = ViewCell::new().with_foreground(foreground).with_bold(true);
-        Self {
-            cells: [
-                base.with_character('T'),
-                base.with_character('c'),
-                base.with_character('h'),
-                base.with_character(if special { '*' } else { ' ' }),
-            ],
+    fn apply_status_effects(&mut self, status_effects: &StatusEffects, palette: &Palette) {
+        if let Some((effect, _)) = status_effects.iter().next() {
+            let colour = status_effect_colour(effect, palette);
+            for view_cell in self.cells[2..].iter_mut() {
+                view_cell.style.background = Some(colour);
+            }
         }
     }
     fn apply_lighting(&mut self, light_colour: Rgb24) {
@@ -459,168 +461,158 @@ impl Quad {
     }
 }
 
-fn entity_to_quad_visible(entity: &ToRenderEntity, game: &Game, game_over: bool) -> Quad {
+fn bridge_glyphs<'a>(entity: &ToRenderEntity, game: &Game, tileset: &'a Tileset) -> &'a Glyphs {
+    if game.contains_bridge(entity.coord + Coord::new(0, 1))
+        || game.contains_bridge(entity.coord - Coord::new(0, 1))
+    {
+        &tileset.bridge_vertical
+    } else {
+        &tileset.bridge_horizontal
+    }
+}
+
+fn entity_to_quad_visible(
+    entity: &ToRenderEntity,
+    game: &Game,
+    palette: &Palette,
+    tileset: &Tileset,
+    game_over: bool,
+) -> Quad {
+    let npc = |glyph: &NpcGlyph, colours: NpcColours| {
+        Quad::new_npc(
+            glyph,
+            colours,
+            &tileset.npc_action,
+            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
+            entity.next_action.unwrap_or(NpcAction::Wait),
+        )
+    };
     match entity.tile {
-        Tile::Player => Quad::new_player(Rgb24::new(255, 255, 255)),
-        Tile::Floor => Quad::new_floor(Rgb24::new(0, 187, 187), Rgb24::new(0, 127, 127)),
+        Tile::Player => Quad::new_glyphs(&tileset.player, Some(palette.player), None),
+        Tile::Floor => Quad::new_glyphs(
+            &tileset.floor,
+            Some(palette.floor_foreground),
+            Some(palette.floor_background),
+        ),
         Tile::Wall => {
             let below = entity.coord + Coord::new(0, 1);
             if game.contains_wall(below)
                 && (game_over || !game.visibility_grid().is_coord_never_visible(below))
             {
-                Quad::new_wall_top(Rgb24::new(255, 0, 255))
+                Quad::new_glyphs(&tileset.wall_top, None, Some(palette.wall_top))
             } else {
-                Quad::new_wall_front(Rgb24::new(127, 0, 127), Rgb24::new(255, 0, 255))
+                Quad::new_glyphs(
+                    &tileset.wall_front,
+                    Some(palette.wall_top),
+                    Some(palette.wall_front),
+                )
             }
         }
-        Tile::DoorClosed => {
-            Quad::new_door_closed(Rgb24::new(255, 127, 255), Rgb24::new(127, 0, 127))
-        }
-        Tile::DoorOpen => Quad::new_door_open(Rgb24::new(255, 127, 255), Rgb24::new(0, 127, 127)),
-        Tile::Stairs => Quad::new_stairs(Rgb24::new(255, 255, 255), Rgb24::new(0, 127, 127)),
-        Tile::Sludge0 => {
-            let background = entity.colour_hint.unwrap_or_else(|| Rgb24::new(255, 0, 0));
-            let foreground = background.scalar_div(2);
-            Quad::new_repeating(
-                ViewCell::new()
-                    .with_character('~')
-                    .with_foreground(foreground)
-                    .with_background(background),
-            )
-        }
-        Tile::Sludge1 => {
-            let background = entity.colour_hint.unwrap_or_else(|| Rgb24::new(255, 0, 0));
-            let foreground = background.scalar_div(2);
-            Quad::new_repeating(
-                ViewCell::new()
-                    .with_character('≈')
-                    .with_foreground(foreground)
-                    .with_background(background),
-            )
-        }
-        Tile::Bridge => {
-            let character = if game.contains_bridge(entity.coord + Coord::new(0, 1))
-                || game.contains_bridge(entity.coord - Coord::new(0, 1))
-            {
-                '║'
-            } else {
-                '═'
-            };
-            Quad::new_repeating(
-                ViewCell::new()
-                    .with_character(character)
-                    .with_foreground(Rgb24::new(127, 127, 0))
-                    .with_background(Rgb24::new(200, 127, 0)),
-            )
-        }
-        Tile::SlimeDivide => Quad::new_slime(
-            'd',
-            Rgb24::new(255, 63, 63),
-            Rgb24::new(31, 15, 15),
-            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
-            entity.next_action.unwrap_or(NpcAction::Wait),
-        ),
-        Tile::SlimeSwap => Quad::new_slime(
-            's',
-            Rgb24::new(127, 127, 255),
-            Rgb24::new(15, 15, 31),
-            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
-            entity.next_action.unwrap_or(NpcAction::Wait),
+        Tile::DoorClosed => Quad::new_glyphs(
+            &tileset.door_closed,
+            Some(palette.door_foreground),
+            Some(palette.door_background),
         ),
-        Tile::SlimeTeleport => Quad::new_slime(
-            't',
-            Rgb24::new(187, 63, 255),
-            Rgb24::new(15, 0, 31),
-            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
-            entity.next_action.unwrap_or(NpcAction::Wait),
+        Tile::DoorOpen => Quad::new_glyphs(
+            &tileset.door_open,
+            Some(palette.door_foreground),
+            Some(palette.floor_background),
         ),
-        Tile::SlimeGoo => Quad::new_slime(
-            'g',
-            Rgb24::new(0, 255, 0),
-            Rgb24::new(0, 63, 0),
-            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
-            entity.next_action.unwrap_or(NpcAction::Wait),
+        Tile::DoorLocked => Quad::new_glyphs(
+            &tileset.door_locked,
+            Some(palette.locked_door_foreground),
+            Some(palette.locked_door_background),
         ),
-        Tile::SlimeBoss => Quad::new_slime(
-            '?',
-            Rgb24::new(127, 127, 127),
-            Rgb24::new(0, 0, 0),
-            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
-            entity.next_action.unwrap_or(NpcAction::Wait),
+        Tile::Stairs => Quad::new_glyphs(
+            &tileset.stairs,
+            Some(palette.stairs),
+            Some(palette.floor_background),
         ),
-        Tile::SlimeAttackUpgrade => Quad::new_slime(
-            'A',
-            Rgb24::new(255, 255, 255),
-            Rgb24::new(31, 31, 31),
-            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
-            entity.next_action.unwrap_or(NpcAction::Wait),
+        Tile::Sludge0 | Tile::Sludge1 => {
+            let glyphs = if entity.tile == Tile::Sludge0 {
+                &tileset.sludge0
+            } else {
+                &tileset.sludge1
+            };
+            let background = palette.sludge(entity.colour_hint);
+            let foreground = background.scalar_div(2);
+            Quad::new_glyphs(glyphs, Some(foreground), Some(background))
+        }
+        Tile::Bridge => Quad::new_glyphs(
+            bridge_glyphs(entity, game, tileset),
+            Some(palette.bridge_foreground),
+            Some(palette.bridge_background),
         ),
-        Tile::SlimeDefendUpgrade => Quad::new_slime(
-            'D',
-            Rgb24::new(255, 255, 255),
-            Rgb24::new(31, 31, 31),
-            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
-            entity.next_action.unwrap_or(NpcAction::Wait),
+        Tile::SlimeDivide => npc(&tileset.slime_divide, palette.slime_divide),
+        Tile::SlimeSwap => npc(&tileset.slime_swap, palette.slime_swap),
+        Tile::SlimeTeleport => npc(&tileset.slime_teleport, palette.slime_teleport),
+        Tile::SlimeGoo => npc(&tileset.slime_goo, palette.slime_goo),
+        Tile::SlimeBoss => npc(&tileset.slime_boss, palette.slime_boss),
+        Tile::SlimeAttackUpgrade => npc(&tileset.slime_attack_upgrade, palette.slime_upgrade),
+        Tile::SlimeDefendUpgrade => npc(&tileset.slime_defend_upgrade, palette.slime_upgrade),
+        Tile::SlimeTechUpgrade => npc(&tileset.slime_tech_upgrade, palette.slime_upgrade),
+        Tile::SlimeCurse => npc(&tileset.slime_curse, palette.slime_curse),
+        Tile::SlimeSpitter => npc(&tileset.slime_spitter, palette.slime_spitter),
+        Tile::Human => npc(&tileset.human, palette.human),
+        Tile::FormerHuman => npc(&tileset.former_human, palette.former_human),
+        Tile::Bullet => Quad::new_glyphs(&tileset.bullet, Some(palette.bullet), None),
+        Tile::Rocket => Quad::new_glyphs(&tileset.rocket, Some(palette.rocket), None),
+        Tile::SludgeSpit => Quad::new_glyphs(&tileset.sludge_spit, Some(palette.sludge_spit), None),
+        Tile::AttackItem { special } => Quad::new_glyphs(
+            if special {
+                &tileset.attack_item_special
+            } else {
+                &tileset.attack_item
+            },
+            Some(palette.item),
+            None,
         ),
-        Tile::SlimeTechUpgrade => Quad::new_slime(
-            'T',
-            Rgb24::new(255, 255, 255),
-            Rgb24::new(31, 31, 31),
-            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
-            entity.next_action.unwrap_or(NpcAction::Wait),
+        Tile::DefendItem { special } => Quad::new_glyphs(
+            if special {
+                &tileset.defend_item_special
+            } else {
+                &tileset.defend_item
+            },
+            Some(palette.item),
+            None,
         ),
-        Tile::AttackItem { special } => Quad::new_attack(Rgb24::new_grey(255), special),
-        Tile::DefendItem { special } => Quad::new_defend(Rgb24::new_grey(255), special),
-        Tile::TechItem { special } => Quad::new_tech(Rgb24::new_grey(255), special),
-        Tile::SlimeCurse => Quad::new_slime(
-            'c',
-            Rgb24::new(187, 187, 187),
-            Rgb24::new(31, 31, 31),
-            entity.hit_points.map(|hp| hp.current).unwrap_or(0),
-            entity.next_action.unwrap_or(NpcAction::Wait),
+        Tile::TechItem { special } => Quad::new_glyphs(
+            if special {
+                &tileset.tech_item_special
+            } else {
+                &tileset.tech_item
+            },
+            Some(palette.item),
+            None,
         ),
+        Tile::Key => Quad::new_glyphs(&tileset.key, Some(palette.key), None),
     }
 }
 
-fn entity_to_quad_remembered(entity: &ToRenderEntity, game: &Game) -> Option<Quad> {
-    let foreground = Rgb24::new_grey(63);
-    let background = Rgb24::new_grey(15);
-    let quad = match entity.tile {
-        Tile::Floor => Quad::new_floor(foreground, background),
+fn entity_to_quad_remembered(
+    entity: &ToRenderEntity,
+    game: &Game,
+    palette: &Palette,
+    tileset: &Tileset,
+) -> Option<Quad> {
+    let foreground = Some(palette.remembered_foreground);
+    let background = Some(palette.remembered_background);
+    let glyphs = match entity.tile {
+        Tile::Floor => &tileset.floor,
         Tile::Wall => {
             if game.contains_wall(entity.coord + Coord::new(0, 1)) {
-                Quad::new_wall_top(foreground)
+                return Some(Quad::new_glyphs(&tileset.wall_top, None, foreground));
             } else {
-                Quad::new_wall_front(background, foreground)
+                &tileset.wall_front
             }
         }
-        Tile::DoorClosed => Quad::new_door_closed(foreground, background),
-        Tile::DoorOpen => Quad::new_door_closed(foreground, background),
-        Tile::Stairs => Quad::new_stairs(foreground, background),
-        Tile::Sludge0 | Tile::Sludge1 => Quad::new_repeating(
-            ViewCell::new()
-                .with_character('~')
-                .with_foreground(foreground)
-                .with_background(background),
-        ),
-        Tile::Bridge => {
-            let character = if game.contains_bridge(entity.coord + Coord::new(0, 1))
-                || game.contains_bridge(entity.coord - Coord::new(0, 1))
-            {
-                '║'
-            } else {
-                '═'
-            };
-            Quad::new_repeating(
-                ViewCell::new()
-                    .with_character(character)
-                    .with_foreground(foreground)
-                    .with_background(background),
-            )
-        }
+        Tile::DoorClosed | Tile::DoorOpen | Tile::DoorLocked => &tileset.door_closed,
+        Tile::Stairs => &tileset.stairs,
+        Tile::Sludge0 | Tile::Sludge1 => &tileset.sludge0,
+        Tile::Bridge => bridge_glyphs(entity, game, tileset),
         _ => return None,
     };
-    Some(quad)
+    Some(Quad::new_glyphs(glyphs, foreground, background))
 }
 
 fn layer_depth(layer: Option<Layer>) -> i8 {
@@ -651,18 +643,23 @@ fn render_quad<F: Frame, C: ColModify>(
 fn render_entity<F: Frame, C: ColModify>(
     entity: &ToRenderEntity,
     game: &Game,
+    palette: &Palette,
+    tileset: &Tileset,
     context: ViewContext<C>,
     frame: &mut F,
 ) {
     match game.visibility_grid().cell_visibility(entity.coord) {
         CellVisibility::CurrentlyVisibleWithLightColour(Some(light_colour)) => {
-            let mut quad = entity_to_quad_visible(entity, game, false);
+            let mut quad = entity_to_quad_visible(entity, game, palette, tileset, false);
             let depth = layer_depth(entity.layer);
+            if let Some(status_effects) = entity.status_effects.as_ref() {
+                quad.apply_status_effects(status_effects, palette);
+            }
             quad.apply_lighting(light_colour);
             render_quad(entity.coord, depth, &quad, context, frame);
         }
         CellVisibility::PreviouslyVisible => {
-            if let Some(quad) = entity_to_quad_remembered(entity, game) {
+            if let Some(quad) = entity_to_quad_remembered(entity, game, palette, tileset) {
                 let depth = layer_depth(entity.layer);
                 render_quad(entity.coord, depth, &quad, context, frame);
             }
@@ -674,21 +671,57 @@ fn render_entity<F: Frame, C: ColModify>(
 fn render_entity_game_over<F: Frame, C: ColModify>(
     entity: &ToRenderEntity,
     game: &Game,
+    palette: &Palette,
+    tileset: &Tileset,
     context: ViewContext<C>,
     frame: &mut F,
 ) {
-    let mut quad = entity_to_quad_visible(entity, game, true);
+    let mut quad = entity_to_quad_visible(entity, game, palette, tileset, true);
     let depth = layer_depth(entity.layer);
-    quad.apply_lighting(Rgb24::new(255, 87, 31));
+    quad.apply_lighting(palette.game_over_light);
     render_quad(entity.coord, depth, &quad, context, frame);
 }
 
-fn tile_str(tile: Tile) -> Option<&'static str> {
+fn status_effect_colour(effect: StatusEffect, palette: &Palette) -> Rgb24 {
+    match effect {
+        StatusEffect::Slowed => palette.slowed,
+        StatusEffect::Stunned => palette.stunned,
+        StatusEffect::Poisoned => palette.poisoned,
+        StatusEffect::Rooted => palette.rooted,
+        StatusEffect::SludgeCoated => palette.sludge_coated,
+        StatusEffect::Marked => palette.marked,
+    }
+}
+
+fn status_effect_str(effect: StatusEffect) -> &'static str {
+    match effect {
+        StatusEffect::Slowed => "slowed",
+        StatusEffect::Stunned => "stunned",
+        StatusEffect::Poisoned => "poisoned",
+        StatusEffect::Rooted => "rooted",
+        StatusEffect::SludgeCoated => "sludge-coated",
+        StatusEffect::Marked => "marked",
+    }
+}
+
+fn write_status_effects(status_effects: &StatusEffects, buf: &mut String) {
+    use std::fmt::Write;
+    for (i, (effect, turns)) in status_effects.iter().enumerate() {
+        let separator = if i == 0 { " (" } else { ", " };
+        write!(buf, "{}{} {}", separator, status_effect_str(effect), turns).unwrap();
+    }
+    if !status_effects.is_empty() {
+        buf.push(')');
+    }
+}
+
+pub fn tile_str(tile: Tile) -> Option<&'static str> {
     match tile {
         Tile::Player => Some("yourself"),
         Tile::Sludge0 | Tile::Sludge1 => Some("some toxic sludge"),
         Tile::Bridge => Some("a bridge over toxic sludge"),
         Tile::DoorClosed | Tile::DoorOpen => Some("a door"),
+        Tile::DoorLocked => Some("a locked door"),
         Tile::Wall => Some("a wall"),
         Tile::Floor => Some("the floor"),
         Tile::Stairs => Some("a staircase leading further down"),
@@ -701,12 +734,19 @@ fn tile_str(tile: Tile) -> Option<&'static str> {
         Tile::SlimeDefendUpgrade => Some("a Defend Upgrade Slime"),
         Tile::SlimeTechUpgrade => Some("a Tech Upgrade Slime"),
         Tile::SlimeCurse => Some("a Curse Slime"),
+        Tile::SlimeSpitter => Some("a Spitter Slime"),
+        Tile::Human => Some("a human survivor"),
+        Tile::FormerHuman => Some("a Former Human"),
+        Tile::Bullet => Some("a bullet"),
+        Tile::Rocket => Some("a rocket"),
+        Tile::SludgeSpit => Some("a glob of sludge"),
         Tile::AttackItem { special: false } => Some("an Attack"),
         Tile::AttackItem { special: true } => Some("a Special Attack"),
         Tile::DefendItem { special: false } => Some("a Defend"),
         Tile::DefendItem { special: true } => Some("a Special Defend"),
         Tile::TechItem { special: false } => Some("a Tech"),
         Tile::TechItem { special: true } => Some("a Special Tech"),
+        Tile::Key => Some("a key"),
     }
 }
 
@@ -723,5 +763,12 @@ fn action_error_str(action_error: ActionError) -> &'static str {
         ActionError::NotEnoughAttacks => "Too few attacks",
         ActionError::NotEnoughDefends => "Too few defends",
         ActionError::NotEnoughTechs => "Too few techs",
+        ActionError::FireWithoutTarget => "Can't fire without target",
+        ActionError::FireAtSelf => "Can't fire at yourself",
+        ActionError::Rooted => "You are rooted in place",
+        ActionError::DoorLocked => "The door is locked",
+        ActionError::NoDoorToClose => "There's no open door next to you",
+        ActionError::BuryWithoutDepth => "Choose how deep to bury the card",
+        ActionError::CantMergeAttacks => "The top 2 attack cards must both be Hits",
     }
 }
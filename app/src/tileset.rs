@@ -0,0 +1,214 @@
+use chargrid::render::Rgb24;
+use serde::{Deserialize, Serialize};
+
+/// The glyphs of a tile, which occupies a 2x2 block of cells. Cells are listed top-left,
+/// top-right, bottom-left, bottom-right.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Glyphs {
+    pub characters: [char; 4],
+    /// Cells which swap the foreground and background colours of the tile
+    #[serde(default)]
+    pub inverted: [bool; 4],
+    #[serde(default)]
+    pub bold: bool,
+    /// Overrides the colour taken from the current palette
+    #[serde(default)]
+    pub foreground: Option<Rgb24>,
+    /// Overrides the colour taken from the current palette
+    #[serde(default)]
+    pub background: Option<Rgb24>,
+}
+
+impl Glyphs {
+    fn new(characters: [char; 4]) -> Self {
+        Self {
+            characters,
+            inverted: [false; 4],
+            bold: false,
+            foreground: None,
+            background: None,
+        }
+    }
+    fn repeating(character: char) -> Self {
+        Self::new([character; 4])
+    }
+    fn projectile(character: char) -> Self {
+        Self::new([' ', ' ', character, ' ']).bold()
+    }
+    fn label(characters: [char; 4]) -> Self {
+        Self::new(characters).bold()
+    }
+    fn inverted(self, inverted: [bool; 4]) -> Self {
+        Self { inverted, ..self }
+    }
+    fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+}
+
+/// Npcs show their next action and hit points alongside their glyph, so only the top-left cell
+/// is configurable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NpcGlyph {
+    pub character: char,
+    /// Overrides the colour taken from the current palette
+    #[serde(default)]
+    pub foreground: Option<Rgb24>,
+    /// Overrides the colour taken from the current palette
+    #[serde(default)]
+    pub background: Option<Rgb24>,
+}
+
+impl NpcGlyph {
+    fn new(character: char) -> Self {
+        Self {
+            character,
+            foreground: None,
+            background: None,
+        }
+    }
+}
+
+/// Shown next to an npc to indicate what it will do on its next turn. Directions are listed
+/// north, east, south, west.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NpcActionGlyphs {
+    pub wait: char,
+    pub walk: [char; 4],
+    pub summon: char,
+    pub flood: char,
+    pub charge: [char; 4],
+    pub shoot: char,
+}
+
+/// Describes how each tile is drawn. Loaded from a file at startup, with any tiles missing from
+/// the file taken from the built-in default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tileset {
+    pub player: Glyphs,
+    pub floor: Glyphs,
+    /// A wall with another wall below it
+    pub wall_top: Glyphs,
+    /// A wall with no wall below it, showing its front face
+    pub wall_front: Glyphs,
+    pub door_closed: Glyphs,
+    pub door_open: Glyphs,
+    pub door_locked: Glyphs,
+    pub stairs: Glyphs,
+    pub sludge0: Glyphs,
+    pub sludge1: Glyphs,
+    pub bridge_vertical: Glyphs,
+    pub bridge_horizontal: Glyphs,
+    pub bullet: Glyphs,
+    pub rocket: Glyphs,
+    pub sludge_spit: Glyphs,
+    pub attack_item: Glyphs,
+    pub attack_item_special: Glyphs,
+    pub defend_item: Glyphs,
+    pub defend_item_special: Glyphs,
+    pub tech_item: Glyphs,
+    pub tech_item_special: Glyphs,
+    pub key: Glyphs,
+    pub slime_divide: NpcGlyph,
+    pub slime_swap: NpcGlyph,
+    pub slime_teleport: NpcGlyph,
+    pub slime_goo: NpcGlyph,
+    pub slime_boss: NpcGlyph,
+    pub slime_attack_upgrade: NpcGlyph,
+    pub slime_defend_upgrade: NpcGlyph,
+    pub slime_tech_upgrade: NpcGlyph,
+    pub slime_curse: NpcGlyph,
+    pub slime_spitter: NpcGlyph,
+    pub human: NpcGlyph,
+    pub former_human: NpcGlyph,
+    pub npc_action: NpcActionGlyphs,
+}
+
+impl Default for Tileset {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+impl Tileset {
+    pub fn unicode() -> Self {
+        Self {
+            player: Glyphs::new(['╔', '╗', '╚', '╩']).bold(),
+            floor: Glyphs::new(['▗', '▖', '▝', '▘']),
+            wall_top: Glyphs::repeating(' '),
+            wall_front: Glyphs::new(['█', '█', ' ', ' ']),
+            door_closed: Glyphs::new(['▘', '▝', '▖', '▗']).inverted([true; 4]),
+            door_open: Glyphs::new(['▄', '▄', '▀', '▀']),
+            door_locked: Glyphs::new(['▘', '▝', '▖', '▗']).inverted([true; 4]),
+            stairs: Glyphs::new(['▝', ' ', ' ', '▝'])
+                .inverted([true, false, true, true])
+                .bold(),
+            sludge0: Glyphs::repeating('~'),
+            sludge1: Glyphs::repeating('≈'),
+            bridge_vertical: Glyphs::repeating('║'),
+            bridge_horizontal: Glyphs::repeating('═'),
+            bullet: Glyphs::projectile('•'),
+            rocket: Glyphs::projectile('◆'),
+            sludge_spit: Glyphs::projectile('°'),
+            attack_item: Glyphs::label(['A', 't', 'k', ' ']),
+            attack_item_special: Glyphs::label(['A', 't', 'k', '*']),
+            defend_item: Glyphs::label(['D', 'e', 'f', ' ']),
+            defend_item_special: Glyphs::label(['D', 'e', 'f', '*']),
+            tech_item: Glyphs::label(['T', 'c', 'h', ' ']),
+            tech_item_special: Glyphs::label(['T', 'c', 'h', '*']),
+            key: Glyphs::label(['K', 'e', 'y', ' ']),
+            slime_divide: NpcGlyph::new('d'),
+            slime_swap: NpcGlyph::new('s'),
+            slime_teleport: NpcGlyph::new('t'),
+            slime_goo: NpcGlyph::new('g'),
+            slime_boss: NpcGlyph::new('?'),
+            slime_attack_upgrade: NpcGlyph::new('A'),
+            slime_defend_upgrade: NpcGlyph::new('D'),
+            slime_tech_upgrade: NpcGlyph::new('T'),
+            slime_curse: NpcGlyph::new('c'),
+            slime_spitter: NpcGlyph::new('p'),
+            human: NpcGlyph::new('h'),
+            former_human: NpcGlyph::new('f'),
+            npc_action: NpcActionGlyphs {
+                wait: ' ',
+                walk: ['↑', '→', '↓', '←'],
+                summon: '*',
+                flood: '≈',
+                charge: ['▲', '►', '▼', '◄'],
+                shoot: '¤',
+            },
+        }
+    }
+
+    /// For terminals without box-drawing, block or arrow characters
+    pub fn ascii() -> Self {
+        Self {
+            player: Glyphs::new(['/', '\\', '|', '|']).bold(),
+            floor: Glyphs::new(['.', ' ', ' ', ' ']),
+            wall_front: Glyphs::repeating(' ').inverted([true, true, false, false]),
+            door_closed: Glyphs::new(['+', '-', '-', '+']),
+            door_open: Glyphs::new(['/', ' ', ' ', '/']),
+            door_locked: Glyphs::new(['+', '=', '=', '+']),
+            stairs: Glyphs::new(['>', ' ', ' ', '>'])
+                .inverted([true, false, true, true])
+                .bold(),
+            sludge0: Glyphs::repeating('~'),
+            sludge1: Glyphs::repeating('='),
+            bridge_vertical: Glyphs::repeating('|'),
+            bridge_horizontal: Glyphs::repeating('-'),
+            bullet: Glyphs::projectile('.'),
+            rocket: Glyphs::projectile('*'),
+            sludge_spit: Glyphs::projectile('o'),
+            npc_action: NpcActionGlyphs {
+                wait: ' ',
+                walk: ['^', '>', 'v', '<'],
+                summon: '*',
+                flood: '~',
+                charge: ['A', '}', 'V', '{'],
+                shoot: '%',
+            },
+            ..Self::unicode()
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use chargrid::render::{ColModify, Coord, Frame, Rgb24, Style, View, ViewContext};
+use crate::palette::Palette;
+use chargrid::render::{ColModify, Coord, Frame, Style, View, ViewContext};
 use chargrid::text::StringViewSingleLine;
 use slime99_game::player::{
-    Ability, AbilityTable, AbilityTarget, Attack, Deck, Defend, Player, Tech, EMPTY_ATTACK,
+    Ability, AbilityTable, AbilityTarget, Attack, Deck, Defend, Outcome, Player, Tech,
+    EMPTY_ATTACK, PEEK_COUNT,
 };
+use slime99_game::{EscortObjective, SludgeSpread};
 
 fn write_attack(attack: Attack, s: &mut String) {
     use std::fmt::Write;
@@ -10,6 +13,7 @@ fn write_attack(attack: Attack, s: &mut String) {
         Attack::Hit(n) => write!(s, "Hit {}", n).unwrap(),
         Attack::Cleave(n) => write!(s, "Cleave {}", n).unwrap(),
         Attack::Skewer(n) => write!(s, "Skewer {}", n).unwrap(),
+        Attack::Poison(n) => write!(s, "Poison {}", n).unwrap(),
         Attack::Miss => write!(s, "Miss").unwrap(),
     }
 }
@@ -35,6 +39,33 @@ fn write_tech(tech: Tech, s: &mut String) {
         Tech::MissNext => write!(s, "Miss Next").unwrap(),
         Tech::TeleportNext => write!(s, "Teleport Next").unwrap(),
         Tech::Skip => write!(s, "Skip").unwrap(),
+        Tech::Shotgun => write!(s, "Shotgun").unwrap(),
+        Tech::Rocket => write!(s, "Rocket").unwrap(),
+        Tech::Stun => write!(s, "Stun").unwrap(),
+        Tech::Root => write!(s, "Root").unwrap(),
+        Tech::Mark => write!(s, "Mark").unwrap(),
+    }
+}
+
+pub fn write_card(card: Outcome, s: &mut String) {
+    match card {
+        Outcome::Attack(attack) => write_attack(attack, s),
+        Outcome::Defend(defend) => write_defend(defend, s),
+        Outcome::Tech(tech) => write_tech(tech, s),
+    }
+}
+
+/// Only the top card of a deck is visible, unless a peek has revealed the cards below it
+pub fn is_card_revealed(index: usize, peeking: bool) -> bool {
+    index == 0 || (peeking && index < PEEK_COUNT)
+}
+
+/// Writes the card at `index` in a deck, or a placeholder if the card is hidden
+pub fn write_deck_card(card: Outcome, index: usize, peeking: bool, s: &mut String) {
+    if is_card_revealed(index, peeking) {
+        write_card(card, s);
+    } else {
+        s.push_str("??");
     }
 }
 
@@ -61,19 +92,40 @@ pub fn write_abiilty(abiilty: Ability, s: &mut String) {
             write!(s, "Discard ").unwrap();
             write_ability_target(target, s);
         }
+        Ability::Peek(target) => {
+            write!(s, "Peek ").unwrap();
+            write_ability_target(target, s);
+        }
+        Ability::Bury(target) => {
+            write!(s, "Bury ").unwrap();
+            write_ability_target(target, s);
+        }
+        Ability::Shuffle(target) => {
+            write!(s, "Shuffle ").unwrap();
+            write_ability_target(target, s);
+        }
+        Ability::DuplicateTop(target) => {
+            write!(s, "Dup top ").unwrap();
+            write_ability_target(target, s);
+        }
+        Ability::TransferToDefend => write!(s, "Atk to Def").unwrap(),
+        Ability::TransferToAttack => write!(s, "Def to Atk").unwrap(),
+        Ability::MergeTop2 => write!(s, "Merge 2 hits").unwrap(),
     }
 }
 
 fn view_attack_list<F: Frame, C: ColModify>(
     attack: &Deck<Attack>,
+    peeking: bool,
+    palette: &Palette,
     context: ViewContext<C>,
     frame: &mut F,
 ) {
-    StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(255)))
+    StringViewSingleLine::new(Style::new().with_foreground(palette.text))
         .view("Atk:", context, frame);
     let padding = attack.max_size() - attack.len();
     for i in 0..padding {
-        StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(63))).view(
+        StringViewSingleLine::new(Style::new().with_foreground(palette.text_disabled)).view(
             "--",
             context.add_offset(Coord::new(0, i as i32 + 1)),
             frame,
@@ -81,13 +133,15 @@ fn view_attack_list<F: Frame, C: ColModify>(
     }
     let mut buf = String::new();
     for (i, &attack) in attack.iter().enumerate() {
-        let mut view = if i == 0 {
-            StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(255)))
+        let mut view = if peeking && i < PEEK_COUNT {
+            StringViewSingleLine::new(Style::new().with_foreground(palette.highlight))
+        } else if i == 0 {
+            StringViewSingleLine::new(Style::new().with_foreground(palette.text))
         } else {
-            StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(127)))
+            StringViewSingleLine::new(Style::new().with_foreground(palette.text_faint))
         };
         buf.clear();
-        write_attack(attack, &mut buf);
+        write_deck_card(Outcome::Attack(attack), i, peeking, &mut buf);
         view.view(
             &buf,
             context.add_offset(Coord::new(0, (i + padding) as i32 + 1)),
@@ -95,9 +149,9 @@ fn view_attack_list<F: Frame, C: ColModify>(
         );
     }
     let empty_colour = if attack.len() == 0 {
-        Rgb24::new(255, 0, 0)
+        palette.danger
     } else {
-        Rgb24::new_grey(63)
+        palette.text_disabled
     };
     buf.clear();
     write_attack(EMPTY_ATTACK, &mut buf);
@@ -109,14 +163,16 @@ fn view_attack_list<F: Frame, C: ColModify>(
 }
 fn view_defend_list<F: Frame, C: ColModify>(
     defend: &Deck<Defend>,
+    peeking: bool,
+    palette: &Palette,
     context: ViewContext<C>,
     frame: &mut F,
 ) {
-    StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(255)))
+    StringViewSingleLine::new(Style::new().with_foreground(palette.text))
         .view("Def:", context, frame);
     let padding = defend.max_size() - defend.len();
     for i in 0..padding {
-        StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(63))).view(
+        StringViewSingleLine::new(Style::new().with_foreground(palette.text_disabled)).view(
             "--",
             context.add_offset(Coord::new(0, i as i32 + 1)),
             frame,
@@ -124,13 +180,15 @@ fn view_defend_list<F: Frame, C: ColModify>(
     }
     let mut buf = String::new();
     for (i, &defend) in defend.iter().enumerate() {
-        let mut view = if i == 0 {
-            StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(255)))
+        let mut view = if peeking && i < PEEK_COUNT {
+            StringViewSingleLine::new(Style::new().with_foreground(palette.highlight))
+        } else if i == 0 {
+            StringViewSingleLine::new(Style::new().with_foreground(palette.text))
         } else {
-            StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(127)))
+            StringViewSingleLine::new(Style::new().with_foreground(palette.text_faint))
         };
         buf.clear();
-        write_defend(defend, &mut buf);
+        write_deck_card(Outcome::Defend(defend), i, peeking, &mut buf);
         view.view(
             &buf,
             context.add_offset(Coord::new(0, (i + padding) as i32 + 1)),
@@ -138,9 +196,9 @@ fn view_defend_list<F: Frame, C: ColModify>(
         );
     }
     let die_colour = if defend.len() == 0 {
-        Rgb24::new(255, 0, 0)
+        palette.danger
     } else {
-        Rgb24::new_grey(63)
+        palette.text_disabled
     };
     StringViewSingleLine::new(Style::new().with_foreground(die_colour)).view(
         "Die",
@@ -150,14 +208,16 @@ fn view_defend_list<F: Frame, C: ColModify>(
 }
 fn view_tech_list<F: Frame, C: ColModify>(
     tech: &Deck<Tech>,
+    peeking: bool,
+    palette: &Palette,
     context: ViewContext<C>,
     frame: &mut F,
 ) {
-    StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(255)))
+    StringViewSingleLine::new(Style::new().with_foreground(palette.text))
         .view("(t) Tch:", context, frame);
     let padding = tech.max_size() - tech.len();
     for i in 0..padding {
-        StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(63))).view(
+        StringViewSingleLine::new(Style::new().with_foreground(palette.text_disabled)).view(
             "--",
             context.add_offset(Coord::new(0, i as i32 + 1)),
             frame,
@@ -165,13 +225,15 @@ fn view_tech_list<F: Frame, C: ColModify>(
     }
     let mut buf = String::new();
     for (i, &tech) in tech.iter().enumerate() {
-        let mut view = if i == 0 {
-            StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(255)))
+        let mut view = if peeking && i < PEEK_COUNT {
+            StringViewSingleLine::new(Style::new().with_foreground(palette.highlight))
+        } else if i == 0 {
+            StringViewSingleLine::new(Style::new().with_foreground(palette.text))
         } else {
-            StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(127)))
+            StringViewSingleLine::new(Style::new().with_foreground(palette.text_faint))
         };
         buf.clear();
-        write_tech(tech, &mut buf);
+        write_deck_card(Outcome::Tech(tech), i, peeking, &mut buf);
         view.view(
             &buf,
             context.add_offset(Coord::new(0, (i + padding) as i32 + 1)),
@@ -181,6 +243,7 @@ fn view_tech_list<F: Frame, C: ColModify>(
 }
 fn view_abiilty_list<F: Frame, C: ColModify>(
     ability: &AbilityTable,
+    palette: &Palette,
     context: ViewContext<C>,
     frame: &mut F,
 ) {
@@ -190,7 +253,7 @@ fn view_abiilty_list<F: Frame, C: ColModify>(
         buf.clear();
         write!(&mut buf, "({}) ", i + 1).unwrap();
         write_abiilty(abiilty, &mut buf);
-        StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(255))).view(
+        StringViewSingleLine::new(Style::new().with_foreground(palette.text)).view(
             &buf,
             context.add_offset(Coord::new(0, i as i32)),
             frame,
@@ -199,7 +262,7 @@ fn view_abiilty_list<F: Frame, C: ColModify>(
     for i in 0..(ability.max_size() - ability.len()) {
         buf.clear();
         write!(&mut buf, "({}) --", i + 1 + ability.len()).unwrap();
-        StringViewSingleLine::new(Style::new().with_foreground(Rgb24::new_grey(63))).view(
+        StringViewSingleLine::new(Style::new().with_foreground(palette.text_disabled)).view(
             &buf,
             context.add_offset(Coord::new(0, (ability.len() + i) as i32)),
             frame,
@@ -207,22 +270,125 @@ fn view_abiilty_list<F: Frame, C: ColModify>(
     }
 }
 
+fn view_escort_objective<F: Frame, C: ColModify>(
+    escort: EscortObjective,
+    palette: &Palette,
+    context: ViewContext<C>,
+    frame: &mut F,
+) {
+    if escort.humans_on_floor == 0 && escort.humans_rescued == 0 {
+        return;
+    }
+    let colour = if escort.humans_following > 0 {
+        palette.humans_following
+    } else {
+        palette.text_faint
+    };
+    StringViewSingleLine::new(Style::new().with_foreground(colour)).view(
+        &format!(
+            "Humans: {}/{} following",
+            escort.humans_following, escort.humans_on_floor
+        ),
+        context,
+        frame,
+    );
+    StringViewSingleLine::new(Style::new().with_foreground(palette.text_faint)).view(
+        &format!("Rescued: {}", escort.humans_rescued),
+        context.add_offset(Coord::new(0, 1)),
+        frame,
+    );
+}
+
+fn view_sludge_spread<F: Frame, C: ColModify>(
+    sludge_spread: Option<SludgeSpread>,
+    palette: &Palette,
+    context: ViewContext<C>,
+    frame: &mut F,
+) {
+    let sludge_spread = if let Some(sludge_spread) = sludge_spread {
+        sludge_spread
+    } else {
+        return;
+    };
+    StringViewSingleLine::new(Style::new().with_foreground(palette.sludge_label))
+        .view("Sludge", context, frame);
+    // the tech list leaves little room, so show the time until the next spread out of the
+    // number of turns between spreads
+    let next_colour = if sludge_spread.turns_until_spread <= 1 {
+        palette.danger
+    } else {
+        palette.text_faint
+    };
+    StringViewSingleLine::new(Style::new().with_foreground(next_colour)).view(
+        &format!(
+            "in {}/{}",
+            sludge_spread.turns_until_spread, sludge_spread.turns_per_spread
+        ),
+        context.add_offset(Coord::new(0, 1)),
+        frame,
+    );
+}
+
+fn view_keys<F: Frame, C: ColModify>(
+    keys: u32,
+    palette: &Palette,
+    context: ViewContext<C>,
+    frame: &mut F,
+) {
+    if keys == 0 {
+        return;
+    }
+    StringViewSingleLine::new(Style::new().with_foreground(palette.keys)).view(
+        &format!("Keys: {}", keys),
+        context,
+        frame,
+    );
+}
+
+fn view_credits<F: Frame, C: ColModify>(
+    credits: u32,
+    palette: &Palette,
+    context: ViewContext<C>,
+    frame: &mut F,
+) {
+    // abbreviated to fit in the narrow side panel
+    StringViewSingleLine::new(Style::new().with_foreground(palette.credits)).view(
+        &format!("Cr {}", credits),
+        context,
+        frame,
+    );
+}
+
 pub struct Ui<'a> {
+    pub palette: &'a Palette,
     pub player: &'a Player,
+    pub escort: EscortObjective,
+    pub sludge_spread: Option<SludgeSpread>,
 }
 
 pub struct UiView;
 
 impl UiView {
     pub fn view<F: Frame, C: ColModify>(&mut self, ui: Ui, context: ViewContext<C>, frame: &mut F) {
-        view_attack_list(&ui.player.attack, context, frame);
+        let peeking = |target: AbilityTarget| ui.player.peeking == Some(target);
+        view_attack_list(
+            &ui.player.attack,
+            peeking(AbilityTarget::Attack),
+            ui.palette,
+            context,
+            frame,
+        );
         view_defend_list(
             &ui.player.defend,
+            peeking(AbilityTarget::Defend),
+            ui.palette,
             context.add_offset(Coord::new(11, 0)),
             frame,
         );
         view_tech_list(
             &ui.player.tech,
+            peeking(AbilityTarget::Tech),
+            ui.palette,
             context.add_offset(Coord::new(
                 0,
                 ui.player.attack.max_size().max(ui.player.defend.max_size()) as i32 + 3,
@@ -231,6 +397,7 @@ impl UiView {
         );
         view_abiilty_list(
             &ui.player.ability,
+            ui.palette,
             context.add_offset(Coord::new(
                 0,
                 (ui.player.attack.max_size().max(ui.player.defend.max_size())
@@ -239,5 +406,39 @@ impl UiView {
             )),
             frame,
         );
+        view_escort_objective(
+            ui.escort,
+            ui.palette,
+            context.add_offset(Coord::new(
+                0,
+                (ui.player.attack.max_size().max(ui.player.defend.max_size())
+                    + ui.player.tech.max_size()
+                    + ui.player.ability.max_size()) as i32
+                    + 7,
+            )),
+            frame,
+        );
+        let side_panel_offset = Coord::new(
+            14,
+            ui.player.attack.max_size().max(ui.player.defend.max_size()) as i32 + 3,
+        );
+        view_sludge_spread(
+            ui.sludge_spread,
+            ui.palette,
+            context.add_offset(side_panel_offset),
+            frame,
+        );
+        view_keys(
+            ui.player.keys,
+            ui.palette,
+            context.add_offset(side_panel_offset + Coord::new(0, 3)),
+            frame,
+        );
+        view_credits(
+            ui.player.credits,
+            ui.palette,
+            context.add_offset(side_panel_offset + Coord::new(0, 4)),
+            frame,
+        );
     }
 }
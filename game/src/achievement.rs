@@ -0,0 +1,119 @@
+use crate::{player::Player, Difficulty, ExternalEvent};
+use serde::{Deserialize, Serialize};
+
+/// Number of consecutive rounds the player must survive with an empty defend deck
+const EMPTY_DEFEND_ROUNDS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Achievement {
+    WinWithoutBlink,
+    KillBossWithRevenge,
+    FloorWithoutSludge,
+    SurviveWithEmptyDefend,
+    WinFirstRun,
+    WinOnNightmare,
+}
+
+impl Achievement {
+    pub fn all() -> &'static [Achievement] {
+        &[
+            Achievement::WinWithoutBlink,
+            Achievement::KillBossWithRevenge,
+            Achievement::FloorWithoutSludge,
+            Achievement::SurviveWithEmptyDefend,
+            Achievement::WinFirstRun,
+            Achievement::WinOnNightmare,
+        ]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::WinWithoutBlink => "Grounded",
+            Achievement::KillBossWithRevenge => "Poetic Justice",
+            Achievement::FloorWithoutSludge => "Clean Boots",
+            Achievement::SurviveWithEmptyDefend => "Running On Empty",
+            Achievement::WinFirstRun => "Beginner's Luck",
+            Achievement::WinOnNightmare => "Nightmare's End",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::WinWithoutBlink => "Win without using Blink",
+            Achievement::KillBossWithRevenge => "Kill a boss with Revenge",
+            Achievement::FloorWithoutSludge => "Clear a floor of enemies without touching sludge",
+            Achievement::SurviveWithEmptyDefend => "Survive 10 turns with no defend cards",
+            Achievement::WinFirstRun => "Win on your first run",
+            Achievement::WinOnNightmare => "Win on Nightmare",
+        }
+    }
+}
+
+/// Things which happen in the world that count towards achievements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Milestone {
+    UsedBlink,
+    TouchedSludge,
+    KilledBossWithRevenge,
+}
+
+/// Tracks progress towards achievements over the course of a single run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AchievementTracker {
+    used_blink: bool,
+    touched_sludge_on_floor: bool,
+    empty_defend_rounds: u32,
+    awarded: Vec<Achievement>,
+}
+
+impl AchievementTracker {
+    fn award(&mut self, achievement: Achievement, external_events: &mut Vec<ExternalEvent>) {
+        if !self.awarded.contains(&achievement) {
+            self.awarded.push(achievement);
+            external_events.push(ExternalEvent::Achievement(achievement));
+        }
+    }
+
+    pub fn milestone(&mut self, milestone: Milestone, external_events: &mut Vec<ExternalEvent>) {
+        match milestone {
+            Milestone::UsedBlink => self.used_blink = true,
+            Milestone::TouchedSludge => self.touched_sludge_on_floor = true,
+            Milestone::KilledBossWithRevenge => {
+                self.award(Achievement::KillBossWithRevenge, external_events)
+            }
+        }
+    }
+
+    /// Called at the end of each round while the player is alive
+    pub fn round_survived(&mut self, player: &Player, external_events: &mut Vec<ExternalEvent>) {
+        if player.defend.peek().is_none() {
+            self.empty_defend_rounds += 1;
+            if self.empty_defend_rounds >= EMPTY_DEFEND_ROUNDS {
+                self.award(Achievement::SurviveWithEmptyDefend, external_events);
+            }
+        } else {
+            self.empty_defend_rounds = 0;
+        }
+    }
+
+    /// Called when the player leaves a floor. A floor is cleared if no hostile npcs remain on it.
+    pub fn floor_left(&mut self, cleared: bool, external_events: &mut Vec<ExternalEvent>) {
+        if cleared && !self.touched_sludge_on_floor {
+            self.award(Achievement::FloorWithoutSludge, external_events);
+        }
+        self.touched_sludge_on_floor = false;
+    }
+
+    /// Achievements earned by winning the game. Achievements which depend on previous runs are
+    /// left to the caller.
+    pub fn win_achievements(&self, difficulty: Difficulty) -> Vec<Achievement> {
+        let mut achievements = Vec::new();
+        if !self.used_blink {
+            achievements.push(Achievement::WinWithoutBlink);
+        }
+        if difficulty == Difficulty::Nightmare {
+            achievements.push(Achievement::WinOnNightmare);
+        }
+        achievements
+    }
+}
@@ -1,5 +1,6 @@
 use crate::visibility::Visibility;
 use crate::world::{Disposition, NpcAction, World};
+use direction::CardinalDirection;
 use entity_table::Entity;
 use grid_2d::{Coord, Grid, Size};
 use grid_search_cardinal::{
@@ -17,6 +18,12 @@ use serde::{Deserialize, Serialize};
 use shadowcast::{vision_distance, Context as ShadowcastContext, VisionDistance};
 
 const FLEE_DISTANCE: Distance = 5;
+const BOSS_CHARGE_RANGE: i32 = 8;
+const BOSS_MAX_MINIONS: usize = 8;
+const SPIT_COOLDOWN: u32 = 3;
+const PACK_DISTANCE: u32 = 4;
+const DOORWAY_AMBUSH_DISTANCE: Distance = 2;
+const HUMAN_HUNT_DISTANCE: u32 = 6;
 
 fn has_line_of_sight(
     eye: Coord,
@@ -97,6 +104,14 @@ impl LastSeenGrid {
     }
 }
 
+/// Where a hostile npc was standing when it saw the player this turn, so other members of its
+/// pack who can see it can learn where the player is
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct PackSighting {
+    seen_from: Coord,
+    player_coord: Coord,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct BehaviourContext {
     best_search_context: BestSearchContext,
@@ -106,6 +121,8 @@ pub struct BehaviourContext {
     player_approach: DistanceMap,
     player_flee: DistanceMap,
     wander_path: Path,
+    pack_sightings: Vec<PackSighting>,
+    claimed_approach_cells: Vec<Coord>,
 }
 
 impl BehaviourContext {
@@ -118,9 +135,13 @@ impl BehaviourContext {
             player_approach: DistanceMap::new(size),
             player_flee: DistanceMap::new(size),
             wander_path: Path::default(),
+            pack_sightings: Vec::new(),
+            claimed_approach_cells: Vec::new(),
         }
     }
     pub fn update(&mut self, player: Entity, world: &World) {
+        self.pack_sightings.clear();
+        self.claimed_approach_cells.clear();
         if let Some(player_coord) = world.entity_coord(player) {
             let can_enter = WorldCanEnterIgnoreCharacters { world };
             self.distance_map_populate_context.add(player_coord);
@@ -137,6 +158,152 @@ impl BehaviourContext {
             self.player_flee.clear();
         }
     }
+
+    /// The most recent player coord reported by a pack member that `eye` can see
+    fn shared_player_coord(
+        &self,
+        eye: Coord,
+        world: &World,
+        vision_distance: vision_distance::Circle,
+    ) -> Option<Coord> {
+        self.pack_sightings
+            .iter()
+            .rev()
+            .find(|sighting| has_line_of_sight(eye, sighting.seen_from, world, vision_distance))
+            .map(|sighting| sighting.player_coord)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum BossPhase {
+    Summon,
+    Flood,
+    Charge,
+    Enraged,
+}
+
+impl BossPhase {
+    fn from_hit_points(current: u32, max: u32) -> Self {
+        match (current * 100) / max.max(1) {
+            76..=u32::MAX => Self::Summon,
+            51..=75 => Self::Flood,
+            26..=50 => Self::Charge,
+            _ => Self::Enraged,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Boss {
+    phase: BossPhase,
+    special_cooldown: u32,
+    charge_cooldown: u32,
+    num_specials: u32,
+}
+
+impl Boss {
+    fn new() -> Self {
+        Self {
+            phase: BossPhase::Summon,
+            special_cooldown: 0,
+            charge_cooldown: 0,
+            num_specials: 0,
+        }
+    }
+
+    fn num_minions(world: &World) -> usize {
+        world
+            .components
+            .npc
+            .iter()
+            .filter(|(_, npc)| npc.disposition == Disposition::Hostile)
+            .count()
+            .saturating_sub(1)
+    }
+
+    /// Returns the direction the boss would charge in to hit the player, if they share a row
+    /// or column and nothing blocks the way
+    fn charge_direction(
+        coord: Coord,
+        player_coord: Coord,
+        world: &World,
+        vision_distance: vision_distance::Circle,
+    ) -> Option<CardinalDirection> {
+        let delta = player_coord - coord;
+        let direction = match (delta.x, delta.y) {
+            (0, y) if y < 0 => CardinalDirection::North,
+            (0, y) if y > 0 => CardinalDirection::South,
+            (x, 0) if x < 0 => CardinalDirection::West,
+            (x, 0) if x > 0 => CardinalDirection::East,
+            _ => return None,
+        };
+        if delta.x.abs() + delta.y.abs() > BOSS_CHARGE_RANGE {
+            return None;
+        }
+        if has_line_of_sight(coord, player_coord, world, vision_distance) {
+            Some(direction)
+        } else {
+            None
+        }
+    }
+
+    /// Decide whether to use a special action this turn. Returns `None` if the boss should just
+    /// chase the player like any other slime.
+    fn act(
+        &mut self,
+        entity: Entity,
+        coord: Coord,
+        world: &World,
+        player: Entity,
+        vision_distance: vision_distance::Circle,
+    ) -> Option<NpcAction> {
+        let player_coord = world.entity_coord(player)?;
+        let hit_points = world.components.hit_points.get(entity)?;
+        let phase = BossPhase::from_hit_points(hit_points.current, hit_points.max);
+        if phase != self.phase {
+            // open each phase with its signature move
+            self.phase = phase;
+            self.special_cooldown = 0;
+            self.charge_cooldown = 0;
+        }
+        self.special_cooldown = self.special_cooldown.saturating_sub(1);
+        self.charge_cooldown = self.charge_cooldown.saturating_sub(1);
+        if let BossPhase::Charge | BossPhase::Enraged = phase {
+            if self.charge_cooldown == 0 {
+                if let Some(direction) =
+                    Self::charge_direction(coord, player_coord, world, vision_distance)
+                {
+                    self.charge_cooldown = if phase == BossPhase::Enraged { 2 } else { 4 };
+                    return Some(NpcAction::Charge(direction));
+                }
+            }
+        }
+        if self.special_cooldown > 0 {
+            return None;
+        }
+        let can_summon = Self::num_minions(world) < BOSS_MAX_MINIONS;
+        let action = match phase {
+            BossPhase::Summon if can_summon => {
+                self.special_cooldown = 6;
+                NpcAction::Summon
+            }
+            BossPhase::Summon | BossPhase::Charge => return None,
+            BossPhase::Flood => {
+                self.special_cooldown = 4;
+                NpcAction::Flood
+            }
+            BossPhase::Enraged => {
+                self.special_cooldown = 3;
+                if can_summon && self.num_specials % 2 == 0 {
+                    NpcAction::Summon
+                } else {
+                    NpcAction::Flood
+                }
+            }
+        };
+        self.num_specials += 1;
+        Some(action)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -144,6 +311,8 @@ pub struct Agent {
     last_seen_grid: LastSeenGrid,
     vision_distance: vision_distance::Circle,
     behaviour: Behaviour,
+    boss: Option<Boss>,
+    spit_cooldown: u32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -156,6 +325,8 @@ enum Behaviour {
         accurate: bool,
     },
     Flee,
+    /// Friendly npcs follow the player once they have seen them
+    Follow,
 }
 
 fn can_enter_wrt_sludge(world: &World, step: Step, entity: Entity) -> bool {
@@ -263,14 +434,179 @@ impl<'a> CanEnter for WorldCanEnterAvoidNpcs<'a> {
     }
 }
 
+/// Like `WorldCanEnterAvoidNpcs`, but the hunted human's cell can be entered, which attacks it
+struct WorldCanEnterHuntHuman<'a> {
+    world: &'a World,
+    entity: Entity,
+    human_coord: Coord,
+}
+
+impl<'a> CanEnter for WorldCanEnterHuntHuman<'a> {
+    fn can_enter(&self, coord: Coord) -> bool {
+        self.world.can_npc_traverse_feature_at_coord(coord)
+            && (coord == self.human_coord || !self.world.is_npc_at_coord(coord))
+    }
+    fn can_step(&self, step: Step) -> bool {
+        can_enter_wrt_sludge(&self.world, step, self.entity) && self.can_enter(step.to_coord)
+    }
+}
+
+/// Hostile npcs go after the nearest human they can see, unless the player is closer, so they
+/// can turn it into a former human
+fn hunt_human(
+    entity: Entity,
+    coord: Coord,
+    world: &World,
+    player: Entity,
+    vision_distance: vision_distance::Circle,
+    behaviour_context: &mut BehaviourContext,
+) -> Option<CardinalDirection> {
+    let player_distance = world
+        .entity_coord(player)
+        .map(|player_coord| player_coord.manhattan_distance(coord))
+        .unwrap_or(u32::MAX);
+    let human_coord = world
+        .humans_within(coord, HUMAN_HUNT_DISTANCE)
+        .filter_map(|human| world.entity_coord(human))
+        .filter(|&human_coord| {
+            human_coord.manhattan_distance(coord) < player_distance
+                && has_line_of_sight(coord, human_coord, world, vision_distance)
+        })
+        .min_by_key(|&human_coord| human_coord.manhattan_distance(coord))?;
+    if let Some(direction) =
+        CardinalDirection::all().find(|direction| coord + direction.coord() == human_coord)
+    {
+        return Some(direction);
+    }
+    let result = behaviour_context
+        .point_to_point_search_context
+        .point_to_point_search_first(
+            expand::JumpPoint,
+            &WorldCanEnterHuntHuman {
+                world,
+                entity,
+                human_coord,
+            },
+            coord,
+            human_coord,
+        );
+    if let Ok(Some(cardinal_direction)) = result {
+        Some(cardinal_direction)
+    } else {
+        None
+    }
+}
+
+fn has_pack_nearby(entity: Entity, coord: Coord, world: &World) -> bool {
+    world.components.npc.iter().any(|(other, npc)| {
+        other != entity
+            && npc.disposition == Disposition::Hostile
+            && world
+                .entity_coord(other)
+                .map(|other_coord| other_coord.manhattan_distance(coord) <= PACK_DISTANCE)
+                .unwrap_or(false)
+    })
+}
+
+/// Pick a free cell next to the player that no other pack member is heading for, so a pack
+/// surrounds the player from several sides instead of queueing behind one another
+fn pack_approach(
+    entity: Entity,
+    coord: Coord,
+    player_coord: Coord,
+    world: &World,
+    behaviour_context: &mut BehaviourContext,
+) -> Option<CardinalDirection> {
+    if coord.manhattan_distance(player_coord) <= 1 || !has_pack_nearby(entity, coord, world) {
+        return None;
+    }
+    let target = CardinalDirection::all()
+        .map(|direction| player_coord + direction.coord())
+        .filter(|&candidate| {
+            world.can_npc_traverse_feature_at_coord(candidate)
+                && !world.is_npc_at_coord(candidate)
+                && !behaviour_context
+                    .claimed_approach_cells
+                    .contains(&candidate)
+        })
+        .min_by_key(|&candidate| candidate.manhattan_distance(coord))?;
+    let result = behaviour_context
+        .point_to_point_search_context
+        .point_to_point_search_first(
+            expand::JumpPoint,
+            &WorldCanEnterAvoidNpcs { world, entity },
+            coord,
+            target,
+        );
+    if let Ok(Some(cardinal_direction)) = result {
+        behaviour_context.claimed_approach_cells.push(target);
+        Some(cardinal_direction)
+    } else {
+        None
+    }
+}
+
+/// Rather than filing through a doorway next to the player one at a time, a pack waits outside
+/// for the player to come to them
+fn should_hold_at_doorway(
+    entity: Entity,
+    next_coord: Coord,
+    world: &World,
+    behaviour_context: &BehaviourContext,
+) -> bool {
+    let is_door = world
+        .spatial_table
+        .layers_at(next_coord)
+        .and_then(|cell| cell.feature)
+        .map(|feature| world.components.door_state.contains(feature))
+        .unwrap_or(false);
+    if !is_door {
+        return false;
+    }
+    let near_player = behaviour_context
+        .player_approach
+        .distance(next_coord)
+        .map(|distance| distance > 0 && distance <= DOORWAY_AMBUSH_DISTANCE)
+        .unwrap_or(false);
+    near_player && has_pack_nearby(entity, next_coord, world)
+}
+
 impl Agent {
     pub fn new(size: Size) -> Self {
         Self {
             last_seen_grid: LastSeenGrid::new(size),
             vision_distance: vision_distance::Circle::new_squared(40),
             behaviour: Behaviour::Wander { avoid: true },
+            boss: None,
+            spit_cooldown: 0,
         }
     }
+    pub fn new_boss(size: Size) -> Self {
+        Self {
+            boss: Some(Boss::new()),
+            ..Self::new(size)
+        }
+    }
+    pub fn is_following(&self) -> bool {
+        if let Behaviour::Follow = self.behaviour {
+            true
+        } else {
+            false
+        }
+    }
+    /// Spitters fire at the player from a distance rather than walking up to them
+    fn spit(&mut self, coord: Coord, world: &World, player: Entity) -> Option<NpcAction> {
+        self.spit_cooldown = self.spit_cooldown.saturating_sub(1);
+        let player_coord = world.entity_coord(player)?;
+        if self.spit_cooldown > 0 || coord.manhattan_distance(player_coord) < 2 {
+            return None;
+        }
+        if !has_line_of_sight(coord, player_coord, world, self.vision_distance) {
+            return None;
+        }
+        self.spit_cooldown = SPIT_COOLDOWN;
+        Some(NpcAction::Shoot(player_coord))
+    }
     pub fn act<R: Rng>(
         &mut self,
         entity: Entity,
@@ -302,6 +638,12 @@ impl Agent {
                 shadowcast_context,
             );
             if let Some(CanSeePlayer) = can_see_player {
+                if npc.disposition == Disposition::Hostile {
+                    behaviour_context.pack_sightings.push(PackSighting {
+                        seen_from: coord,
+                        player_coord,
+                    });
+                }
                 match npc.disposition {
                     Disposition::Hostile => Behaviour::Chase {
                         last_seen_player_coord: player_coord,
@@ -316,6 +658,7 @@ impl Agent {
                             Behaviour::Wander { avoid: true }
                         }
                     }
+                    Disposition::Friendly => Behaviour::Follow,
                 }
             } else {
                 match self.behaviour {
@@ -339,6 +682,13 @@ impl Agent {
                         // stop fleeing the player if you can't see them
                         Behaviour::Wander { avoid: true }
                     }
+                    Behaviour::Follow => match npc.disposition {
+                        Disposition::Friendly => Behaviour::Follow,
+                        // a friendly npc may have been turned hostile since its last turn
+                        Disposition::Hostile | Disposition::Afraid => {
+                            Behaviour::Wander { avoid: false }
+                        }
+                    },
                 }
             }
         } else {
@@ -352,6 +702,44 @@ impl Agent {
             );
             Behaviour::Wander { avoid: false }
         };
+        if npc.disposition == Disposition::Hostile {
+            if let Behaviour::Wander { .. }
+            | Behaviour::Chase {
+                accurate: false, ..
+            } = self.behaviour
+            {
+                if let Some(shared_player_coord) =
+                    behaviour_context.shared_player_coord(coord, world, self.vision_distance)
+                {
+                    self.behaviour = Behaviour::Chase {
+                        last_seen_player_coord: shared_player_coord,
+                        accurate: world.entity_coord(player) == Some(shared_player_coord),
+                    };
+                }
+            }
+        }
+        if world.components.spitter.contains(entity) && npc.disposition == Disposition::Hostile {
+            if let Some(action) = self.spit(coord, world, player) {
+                return action;
+            }
+        }
+        if let Some(boss) = self.boss.as_mut() {
+            if let Some(action) = boss.act(entity, coord, world, player, self.vision_distance) {
+                return action;
+            }
+        }
+        if npc.disposition == Disposition::Hostile {
+            if let Some(direction) = hunt_human(
+                entity,
+                coord,
+                world,
+                player,
+                self.vision_distance,
+                behaviour_context,
+            ) {
+                return NpcAction::Walk(direction);
+            }
+        }
         match self.behaviour {
             Behaviour::Wander { avoid } => {
                 let mut path_node = behaviour_context.wander_path.pop();
@@ -403,24 +791,64 @@ impl Agent {
                     Some(cardinal_direction) => NpcAction::Walk(cardinal_direction),
                 }
             }
+            Behaviour::Follow => {
+                // stay next to the player rather than walking into them
+                if behaviour_context
+                    .player_approach
+                    .distance(coord)
+                    .map(|distance| distance <= 1)
+                    .unwrap_or(false)
+                {
+                    return NpcAction::Wait;
+                }
+                behaviour_context
+                    .distance_map_search_context
+                    .search_first(
+                        &WorldCanEnterAvoidNpcs { world, entity },
+                        coord,
+                        5,
+                        &behaviour_context.player_approach,
+                    )
+                    .map(NpcAction::Walk)
+                    .unwrap_or(NpcAction::Wait)
+            }
             Behaviour::Chase {
                 last_seen_player_coord,
                 accurate,
             } => {
                 if accurate {
-                    let maybe_cardinal_direction =
+                    let maybe_cardinal_direction = pack_approach(
+                        entity,
+                        coord,
+                        last_seen_player_coord,
+                        world,
+                        behaviour_context,
+                    )
+                    .or_else(|| {
                         behaviour_context.distance_map_search_context.search_first(
                             &WorldCanEnterAvoidNpcs { world, entity },
                             coord,
                             5,
                             &behaviour_context.player_approach,
-                        );
+                        )
+                    });
                     match maybe_cardinal_direction {
                         None => {
                             self.behaviour = Behaviour::Wander { avoid: true };
                             NpcAction::Wait
                         }
-                        Some(cardinal_direction) => NpcAction::Walk(cardinal_direction),
+                        Some(cardinal_direction) => {
+                            if should_hold_at_doorway(
+                                entity,
+                                coord + cardinal_direction.coord(),
+                                world,
+                                behaviour_context,
+                            ) {
+                                NpcAction::Wait
+                            } else {
+                                NpcAction::Walk(cardinal_direction)
+                            }
+                        }
                     }
                 } else {
                     let result = behaviour_context
@@ -443,3 +871,47 @@ impl Agent {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Difficulty, GameMode};
+    use rand::SeedableRng;
+    use rand_isaac::Isaac64Rng;
+
+    #[test]
+    fn slime_turns_nearby_human() {
+        let size = Size::new(10, 10);
+        let mut world = World::new(size, 1, Difficulty::default(), GameMode::Campaign);
+        let mut rng = Isaac64Rng::seed_from_u64(0);
+        let slime = world.spawn_slime_goo(Coord::new(2, 2), &mut rng);
+        let human = world.spawn_human(Coord::new(4, 2));
+        // a player which isn't in the world, so the slime has only the human to go after
+        let player = world.entity_allocator.alloc();
+        let mut agent = Agent::new(size);
+        let mut behaviour_context = BehaviourContext::new(size);
+        let mut shadowcast_context = ShadowcastContext::default();
+        for _ in 0..4 {
+            behaviour_context.update(player, &world);
+            let action = agent.act(
+                slime,
+                &world,
+                player,
+                &mut behaviour_context,
+                &mut shadowcast_context,
+                &mut rng,
+            );
+            match action {
+                NpcAction::Walk(direction) => {
+                    let _ = world.character_walk_in_direction(slime, direction, &mut rng);
+                }
+                other => panic!("unexpected action: {:?}", other),
+            }
+            if !world.is_human(human) {
+                break;
+            }
+        }
+        assert!(!world.is_human(human));
+        assert_eq!(world.entity_npc(human).disposition, Disposition::Hostile);
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// Chosen when starting a new game. Scales enemy numbers and toughness, and how many cards are
+/// available to the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::Normal
+    }
+}
+
+impl Difficulty {
+    pub fn all() -> &'static [Difficulty] {
+        &[
+            Difficulty::Easy,
+            Difficulty::Normal,
+            Difficulty::Hard,
+            Difficulty::Nightmare,
+        ]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Nightmare => "Nightmare",
+        }
+    }
+
+    fn enemy_percent(self) -> u32 {
+        match self {
+            Difficulty::Easy => 75,
+            Difficulty::Normal => 100,
+            Difficulty::Hard => 125,
+            Difficulty::Nightmare => 150,
+        }
+    }
+
+    fn supply_percent(self) -> u32 {
+        match self {
+            Difficulty::Easy => 125,
+            Difficulty::Normal => 100,
+            Difficulty::Hard => 88,
+            Difficulty::Nightmare => 75,
+        }
+    }
+
+    /// Scales a count or quantity that makes the game harder (enemies and their hit points)
+    pub fn scale_enemies(self, base: u32) -> u32 {
+        (base * self.enemy_percent() / 100).max(1)
+    }
+
+    /// Scales a count or quantity that makes the game easier (items, starting cards, damage
+    /// dealt to enemies by sludge)
+    pub fn scale_supplies(self, base: u32) -> u32 {
+        (base * self.supply_percent() / 100).max(1)
+    }
+
+    /// Decks can shrink on harder difficulties but never grow beyond the base size, since the
+    /// ui has no room for longer decks
+    pub fn deck_max_size(self, base: usize) -> usize {
+        (self.scale_supplies(base as u32) as usize).min(base)
+    }
+}
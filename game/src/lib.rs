@@ -6,22 +6,34 @@ use serde::{Deserialize, Serialize};
 use shadowcast::Context as ShadowcastContext;
 use std::time::Duration;
 
+mod achievement;
 mod behaviour;
+mod difficulty;
+mod loadout;
+mod safehouse;
 mod terrain;
+mod unlock;
 mod visibility;
 mod world;
 
+pub use achievement::Achievement;
+use achievement::AchievementTracker;
 use behaviour::{Agent, BehaviourContext};
+pub use difficulty::Difficulty;
 use entity_table::ComponentTable;
 pub use entity_table::Entity;
+pub use loadout::Loadout;
 use procgen::SewerSpec;
+use safehouse::Safehouse;
+pub use safehouse::{SafehouseAction, SafehouseOption};
 use terrain::Terrain;
-pub use terrain::FINAL_LEVEL;
+pub use terrain::{GameMode, FINAL_LEVEL};
+pub use unlock::{Requirement, Unlock, Unlocks};
 pub use visibility::{CellVisibility, Omniscient, VisibilityGrid};
 use world::{make_player, AnimationContext, World, ANIMATION_FRAME_DURATION};
 pub use world::{
-    player, ActionError, CharacterInfo, EntityData, HitPoints, Layer, NpcAction, PlayerDied, Tile,
-    ToRenderEntity,
+    player, ActionError, CharacterInfo, EntityData, HitPoints, Layer, NpcAction, PlayerDied,
+    SludgeSpread, StatusEffect, StatusEffects, Tile, ToRenderEntity,
 };
 
 pub const MAP_SIZE: Size = Size::new_u16(19, 19);
@@ -44,8 +56,22 @@ pub enum Music {
 pub enum ExternalEvent {
     Explosion(Coord),
     LoopMusic(Music),
+    Achievement(Achievement),
+    /// An endless run has descended to a new floor
+    EndlessFloorReached(u32),
 }
 
+/// Humans found on a floor can be escorted to the stairs to rescue them
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EscortObjective {
+    pub humans_on_floor: usize,
+    pub humans_following: usize,
+    pub humans_rescued: u32,
+}
+
+/// Humans within this distance of the player when they take the stairs are rescued
+const RESCUE_DISTANCE: u32 = 3;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct AbilityChoice(pub Vec<player::Ability>);
 
@@ -55,13 +81,26 @@ pub enum GameControlFlow {
     LevelChange(AbilityChoice),
 }
 
+/// What to do with a card picked up while its deck is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickupResolution {
+    /// Discard the card this many positions from the top and take the new card
+    Discard(usize),
+    /// Take the new card in place of the top card, which is left on the floor
+    SwapTop,
+    /// Leave the new card on the floor
+    Leave,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum Input {
     Walk(CardinalDirection),
     Tech,
     TechWithCoord(Coord),
     Wait,
+    CloseDoors,
     Ability(u8),
+    AbilityWithDepth(u8, usize),
     GrantAbility(player::Ability),
 }
 
@@ -92,24 +131,39 @@ pub struct Game {
     dead_player: Option<EntityData>,
     turn_during_animation: Option<Turn>,
     gameplay_music: Vec<Music>,
+    humans_rescued: u32,
+    /// Rounds completed, where a round is the player's turn followed by the npcs' turn
+    rounds: u64,
+    safehouse: Option<Safehouse>,
+    achievement_tracker: AchievementTracker,
 }
 
 impl Game {
-    pub fn new<R: Rng>(config: &Config, base_rng: &mut R) -> Self {
+    pub fn new<R: Rng>(
+        config: &Config,
+        difficulty: Difficulty,
+        mode: GameMode,
+        loadout: Loadout,
+        unlocks: Unlocks,
+        base_rng: &mut R,
+    ) -> Self {
         let mut rng = Isaac64Rng::seed_from_u64(base_rng.gen());
         let animation_rng = Isaac64Rng::seed_from_u64(base_rng.gen());
         //let Terrain { world, agents, player } =
         //    terrain::from_str(include_str!("terrain.txt"), make_player(&mut rng), &mut rng);
         let Terrain {
-            world,
+            mut world,
             agents,
             player,
         } = terrain::sewer(
             0,
-            SewerSpec { size: MAP_SIZE },
-            make_player(&mut rng),
+            difficulty,
+            mode,
+            SewerSpec::new(MAP_SIZE),
+            make_player(difficulty, loadout, &unlocks, &mut rng),
             &mut rng,
         );
+        world.unlocks = unlocks;
         let last_player_info = world
             .character_info(player)
             .expect("couldn't get info for player");
@@ -136,6 +190,10 @@ impl Game {
             dead_player: None,
             turn_during_animation: None,
             gameplay_music,
+            humans_rescued: 0,
+            rounds: 0,
+            safehouse: None,
+            achievement_tracker: AchievementTracker::default(),
         };
         game.update_visibility(config);
         game.prime_npcs();
@@ -144,6 +202,20 @@ impl Game {
     pub fn size(&self) -> Size {
         self.world.size()
     }
+    pub fn difficulty(&self) -> Difficulty {
+        self.world.difficulty
+    }
+    pub fn mode(&self) -> GameMode {
+        self.world.mode
+    }
+    /// Achievements earned by winning this run
+    pub fn win_achievements(&self) -> Vec<Achievement> {
+        self.achievement_tracker
+            .win_achievements(self.world.difficulty)
+    }
+    pub fn is_boss_level(&self) -> bool {
+        terrain::is_boss_level(self.world.level, self.world.mode)
+    }
     fn cleanup(&mut self) {
         if let Some(PlayerDied(player_data)) = self.world.cleanup() {
             self.dead_player = Some(player_data);
@@ -152,6 +224,37 @@ impl Game {
     pub fn is_gameplay_blocked(&self) -> bool {
         self.world.is_gameplay_blocked()
     }
+    /// The card on the floor which the player is deciding whether to pick up
+    pub fn pending_pickup(&self) -> Option<player::Outcome> {
+        self.world.pending_pickup_card()
+    }
+    pub fn resolve_pickup(&mut self, resolution: PickupResolution) {
+        self.world.resolve_pickup(self.player, resolution);
+    }
+    /// True while the player is in the safehouse between floors
+    pub fn in_safehouse(&self) -> bool {
+        self.safehouse.is_some()
+    }
+    pub fn safehouse_options(&self) -> Vec<SafehouseOption> {
+        match (
+            self.safehouse.as_ref(),
+            self.world.entity_player(self.player),
+        ) {
+            (Some(safehouse), Some(player)) => safehouse.options(player),
+            _ => Vec::new(),
+        }
+    }
+    pub fn safehouse_action(&mut self, action: SafehouseAction) {
+        if let SafehouseAction::Leave = action {
+            self.safehouse = None;
+            return;
+        }
+        if let Some(safehouse) = self.safehouse.as_mut() {
+            if let Some(player) = self.world.entity_player_mut(self.player) {
+                safehouse.apply(action, player);
+            }
+        }
+    }
     pub fn update_visibility(&mut self, config: &Config) {
         if let Some(player_coord) = self.world.entity_coord(self.player) {
             self.visibility_grid.update(
@@ -176,6 +279,11 @@ impl Game {
             if countdown.as_millis() == 0 {
                 self.generate_level(config);
                 self.generate_frame_countdown = None;
+                self.safehouse = Some(Safehouse::new(
+                    self.world.level,
+                    &self.world.unlocks,
+                    &mut self.rng,
+                ));
                 return Some(GameControlFlow::LevelChange(AbilityChoice(
                     self.world.ability_choice(self.player, &mut self.rng),
                 )));
@@ -214,7 +322,7 @@ impl Game {
                 if let Some(countdown) = self.after_player_turn_countdown.as_mut() {
                     if countdown.as_millis() == 0 {
                         self.after_player_turn_countdown = None;
-                        self.after_turn();
+                        self.after_turn(Turn::Player);
                     } else {
                         *countdown = if let Some(remaining) = countdown.checked_sub(since_last_tick)
                         {
@@ -238,10 +346,12 @@ impl Game {
                     }
                     return None;
                 }
-                if let Turn::Player = turn_during_animation {
-                    self.npc_turn();
-                }
+                // cleared first, as the npcs' turn may start an animation of its own
                 self.turn_during_animation = None;
+                match turn_during_animation {
+                    Turn::Player => self.npc_turn(),
+                    Turn::Npc => self.after_turn(Turn::Npc),
+                }
             }
         }
         self.update_visibility(config);
@@ -302,6 +412,19 @@ impl Game {
     }
 
     fn player_turn(&mut self, input: Input) -> Result<(), ActionError> {
+        let stunned = self
+            .world
+            .has_status_effect(self.player, StatusEffect::Stunned);
+        let input = match input {
+            Input::GrantAbility(_) => input,
+            _ if stunned => Input::Wait,
+            _ => input,
+        };
+        // a peek only lasts until the player's next action
+        let peeking = self
+            .world
+            .entity_player_mut(self.player)
+            .and_then(|player| player.peeking.take());
         let result = match input {
             Input::Walk(direction) => {
                 self.world
@@ -318,12 +441,24 @@ impl Game {
                 self.world.wait(self.player, &mut self.rng);
                 Ok(())
             }
-            Input::Ability(n) => self.world.apply_ability(self.player, n, &mut self.rng),
+            Input::CloseDoors => self.world.character_close_doors(self.player, &mut self.rng),
+            Input::Ability(n) => self
+                .world
+                .apply_ability(self.player, n, None, &mut self.rng),
+            Input::AbilityWithDepth(n, depth) => {
+                self.world
+                    .apply_ability(self.player, n, Some(depth), &mut self.rng)
+            }
             Input::GrantAbility(ability) => {
                 self.world.grant_ability(self.player, ability);
                 Ok(())
             }
         };
+        if result.is_err() {
+            if let Some(player) = self.world.entity_player_mut(self.player) {
+                player.peeking = peeking;
+            }
+        }
         if result.is_ok() {
             if self.is_gameplay_blocked() {
                 self.after_player_turn_countdown = Some(Duration::from_millis(0));
@@ -331,6 +466,7 @@ impl Game {
             }
             self.turn_during_animation = Some(Turn::Player);
         }
+        self.update_achievements();
         result
     }
 
@@ -341,10 +477,13 @@ impl Game {
                 self.agents_to_remove.push(entity);
                 continue;
             }
-            let current_action = self
-                .world
-                .next_npc_action(entity)
-                .unwrap_or(NpcAction::Wait);
+            let current_action = if self.world.has_status_effect(entity, StatusEffect::Stunned) {
+                NpcAction::Wait
+            } else {
+                self.world
+                    .next_npc_action(entity)
+                    .unwrap_or(NpcAction::Wait)
+            };
             match current_action {
                 NpcAction::Wait => (),
                 NpcAction::Walk(direction) => {
@@ -352,6 +491,12 @@ impl Game {
                         self.world
                             .character_walk_in_direction(entity, direction, &mut self.rng);
                 }
+                NpcAction::Summon => self.world.boss_summon(entity, &mut self.rng),
+                NpcAction::Flood => self.world.boss_flood(entity, &mut self.rng),
+                NpcAction::Charge(direction) => {
+                    self.world.boss_charge(entity, direction, &mut self.rng)
+                }
+                NpcAction::Shoot(target) => self.world.npc_spit(entity, target),
             }
         }
         for entity in self.agents_to_remove.drain(..) {
@@ -373,23 +518,35 @@ impl Game {
         if self.is_gameplay_blocked() {
             self.turn_during_animation = Some(Turn::Npc);
         } else {
-            self.after_turn();
+            self.after_turn(Turn::Npc);
+        }
+    }
+    fn update_achievements(&mut self) {
+        for milestone in self.world.milestones.drain(..) {
+            self.achievement_tracker
+                .milestone(milestone, &mut self.events);
         }
     }
     fn generate_level(&mut self, config: &Config) {
+        self.update_achievements();
+        // the intro floor has nothing to clear
+        let cleared = self.world.level > 0 && self.world.num_hostile_npcs() == 0;
+        self.achievement_tracker
+            .floor_left(cleared, &mut self.events);
         let player_data = self.world.clone_entity_data(self.player);
         let Terrain {
-            world,
+            mut world,
             agents,
             player,
         } = terrain::sewer(
             self.world.level + 1,
-            SewerSpec {
-                size: self.world.size(),
-            },
+            self.world.difficulty,
+            self.world.mode,
+            SewerSpec::new(self.world.size()),
             player_data,
             &mut self.rng,
         );
+        world.unlocks = self.world.unlocks.clone();
         self.visibility_grid = VisibilityGrid::new(world.size());
         self.world = world;
         self.agents = agents;
@@ -397,7 +554,11 @@ impl Game {
         self.update_last_player_info();
         self.update_visibility(config);
         self.prime_npcs();
-        if self.world.level == terrain::FINAL_LEVEL {
+        if self.world.mode == GameMode::Endless {
+            self.events
+                .push(ExternalEvent::EndlessFloorReached(self.world.level));
+        }
+        if self.is_boss_level() {
             self.events.push(ExternalEvent::LoopMusic(Music::Boss));
         } else {
             self.events.push(ExternalEvent::LoopMusic(
@@ -405,10 +566,31 @@ impl Game {
             ));
         }
     }
-    fn after_turn(&mut self) {
+    fn after_turn(&mut self, turn: Turn) {
         self.cleanup();
+        if let Turn::Npc = turn {
+            self.rounds += 1;
+            // status effects count down once per round, at the end of the npcs' turn
+            self.world.tick_status_effects(&mut self.rng);
+            self.world.spread_sludge(&mut self.rng);
+            self.cleanup();
+        }
         if let Some(player_coord) = self.world.entity_coord(self.player) {
             if let Some(_stairs_entity) = self.world.get_stairs_at_coord(player_coord) {
+                if self.generate_frame_countdown.is_none() {
+                    let agents = &self.agents;
+                    let humans_rescued = self
+                        .world
+                        .humans_within(player_coord, RESCUE_DISTANCE)
+                        .filter(|&entity| {
+                            agents
+                                .get(entity)
+                                .map(|agent| agent.is_following())
+                                .unwrap_or(false)
+                        })
+                        .count();
+                    self.humans_rescued += humans_rescued as u32;
+                }
                 self.generate_frame_countdown = Some(Duration::from_millis(200));
             }
         }
@@ -419,6 +601,13 @@ impl Game {
         }
         self.world.sludge_damage(&mut self.rng);
         self.cleanup();
+        self.update_achievements();
+        if let Turn::Npc = turn {
+            if let Some(player) = self.world.entity_player(self.player) {
+                self.achievement_tracker
+                    .round_survived(player, &mut self.events);
+            }
+        }
     }
     pub fn is_generating(&self) -> bool {
         if let Some(countdown) = self.generate_frame_countdown {
@@ -472,4 +661,25 @@ impl Game {
     pub fn current_level(&self) -> u32 {
         self.world.level
     }
+    pub fn rounds(&self) -> u64 {
+        self.rounds
+    }
+    pub fn sludge_spread(&self) -> Option<SludgeSpread> {
+        self.world.sludge_spread
+    }
+    pub fn escort_objective(&self) -> EscortObjective {
+        EscortObjective {
+            humans_on_floor: self.world.num_humans(),
+            humans_following: self
+                .agents
+                .iter()
+                .filter(|&(entity, agent)| {
+                    self.world.entity_exists(entity)
+                        && self.world.is_human(entity)
+                        && agent.is_following()
+                })
+                .count(),
+            humans_rescued: self.humans_rescued,
+        }
+    }
 }
@@ -0,0 +1,250 @@
+use crate::player::{Ability, AbilityTarget, Attack, Defend, Tech};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Chosen when starting a new game. Determines the player's starting cards, the capacity of each
+/// deck, and the starting abilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Loadout {
+    Operative,
+    Tank,
+    Blinker,
+    Skewerer,
+}
+
+impl Default for Loadout {
+    fn default() -> Self {
+        Loadout::Operative
+    }
+}
+
+/// A starting attack card. Values are rolled from the half-open range `min..max`.
+#[derive(Debug, Clone, Copy)]
+pub enum AttackCard {
+    Hit(u32, u32),
+    Cleave(u32, u32),
+    Skewer(u32, u32),
+}
+
+impl AttackCard {
+    pub fn roll<R: Rng>(self, rng: &mut R) -> Attack {
+        match self {
+            AttackCard::Hit(min, max) => Attack::Hit(rng.gen_range(min..max)),
+            AttackCard::Cleave(min, max) => Attack::Cleave(rng.gen_range(min..max)),
+            AttackCard::Skewer(min, max) => Attack::Skewer(rng.gen_range(min..max)),
+        }
+    }
+}
+
+/// A starting defend card. Armour values are rolled from the half-open range `min..max`.
+#[derive(Debug, Clone, Copy)]
+pub enum DefendCard {
+    Armour(u32, u32),
+    Dodge,
+    Teleport,
+    Revenge,
+}
+
+impl DefendCard {
+    pub fn roll<R: Rng>(self, rng: &mut R) -> Defend {
+        match self {
+            DefendCard::Armour(min, max) => Defend::Armour(rng.gen_range(min..max)),
+            DefendCard::Dodge => Defend::Dodge,
+            DefendCard::Teleport => Defend::Teleport,
+            DefendCard::Revenge => Defend::Revenge,
+        }
+    }
+}
+
+/// Attack and defend cards are listed from the top of the deck down. The tech deck is shuffled.
+/// The ui has room for at most 24 cards across the tallest of the attack and defend decks, and
+/// the tech deck.
+pub struct LoadoutSpec {
+    pub attack: &'static [AttackCard],
+    pub attack_max_size: usize,
+    pub defend: &'static [DefendCard],
+    pub defend_max_size: usize,
+    pub tech: &'static [Tech],
+    pub tech_max_size: usize,
+    pub abilities: &'static [Ability],
+}
+
+static OPERATIVE: LoadoutSpec = LoadoutSpec {
+    attack: &[
+        AttackCard::Hit(4, 8),
+        AttackCard::Hit(4, 8),
+        AttackCard::Hit(4, 8),
+        AttackCard::Cleave(3, 7),
+        AttackCard::Hit(6, 12),
+        AttackCard::Hit(6, 12),
+        AttackCard::Hit(8, 20),
+        AttackCard::Hit(8, 20),
+    ],
+    attack_max_size: 16,
+    defend: &[
+        DefendCard::Armour(1, 2),
+        DefendCard::Armour(1, 2),
+        DefendCard::Armour(1, 2),
+        DefendCard::Armour(1, 3),
+        DefendCard::Armour(1, 3),
+        DefendCard::Armour(1, 3),
+        DefendCard::Teleport,
+        DefendCard::Armour(2, 5),
+        DefendCard::Armour(2, 5),
+    ],
+    defend_max_size: 16,
+    tech: &[
+        Tech::Attract,
+        Tech::Repel,
+        Tech::Repel,
+        Tech::Blink,
+        Tech::Blink,
+        Tech::Blink,
+    ],
+    tech_max_size: 8,
+    abilities: &[
+        Ability::Stash(AbilityTarget::Attack),
+        Ability::Stash(AbilityTarget::Defend),
+    ],
+};
+
+static TANK: LoadoutSpec = LoadoutSpec {
+    attack: &[
+        AttackCard::Hit(4, 8),
+        AttackCard::Hit(4, 8),
+        AttackCard::Cleave(3, 7),
+        AttackCard::Hit(6, 12),
+        AttackCard::Hit(6, 12),
+    ],
+    attack_max_size: 10,
+    defend: &[
+        DefendCard::Armour(1, 3),
+        DefendCard::Armour(1, 3),
+        DefendCard::Armour(1, 3),
+        DefendCard::Armour(2, 4),
+        DefendCard::Armour(2, 4),
+        DefendCard::Armour(2, 4),
+        DefendCard::Revenge,
+        DefendCard::Armour(2, 5),
+        DefendCard::Armour(2, 5),
+        DefendCard::Armour(2, 5),
+        DefendCard::Dodge,
+        DefendCard::Armour(3, 6),
+        DefendCard::Armour(3, 6),
+        DefendCard::Revenge,
+    ],
+    defend_max_size: 18,
+    tech: &[Tech::Attract, Tech::Repel, Tech::Repel, Tech::Blink],
+    tech_max_size: 6,
+    abilities: &[
+        Ability::Stash(AbilityTarget::Defend),
+        Ability::SwapTop2(AbilityTarget::Defend),
+        Ability::TransferToAttack,
+    ],
+};
+
+static BLINKER: LoadoutSpec = LoadoutSpec {
+    attack: &[
+        AttackCard::Hit(4, 8),
+        AttackCard::Hit(4, 8),
+        AttackCard::Hit(4, 8),
+        AttackCard::Hit(6, 12),
+        AttackCard::Hit(6, 12),
+        AttackCard::Hit(8, 20),
+    ],
+    attack_max_size: 12,
+    defend: &[
+        DefendCard::Armour(1, 2),
+        DefendCard::Armour(1, 2),
+        DefendCard::Dodge,
+        DefendCard::Armour(1, 3),
+        DefendCard::Armour(1, 3),
+        DefendCard::Teleport,
+    ],
+    defend_max_size: 12,
+    tech: &[
+        Tech::Attract,
+        Tech::Attract,
+        Tech::Repel,
+        Tech::Repel,
+        Tech::CritNext,
+        Tech::Blink,
+        Tech::Blink,
+        Tech::Blink,
+        Tech::Blink,
+        Tech::Blink,
+    ],
+    tech_max_size: 12,
+    abilities: &[
+        Ability::Stash(AbilityTarget::Tech),
+        Ability::SwapTop2(AbilityTarget::Tech),
+        Ability::Shuffle(AbilityTarget::Tech),
+    ],
+};
+
+static SKEWERER: LoadoutSpec = LoadoutSpec {
+    attack: &[
+        AttackCard::Skewer(6, 10),
+        AttackCard::Skewer(6, 10),
+        AttackCard::Hit(6, 12),
+        AttackCard::Skewer(8, 14),
+        AttackCard::Skewer(8, 14),
+        AttackCard::Hit(6, 12),
+        AttackCard::Cleave(5, 9),
+        AttackCard::Skewer(10, 20),
+        AttackCard::Skewer(10, 20),
+        AttackCard::Skewer(12, 24),
+    ],
+    attack_max_size: 16,
+    defend: &[
+        DefendCard::Armour(1, 2),
+        DefendCard::Armour(1, 2),
+        DefendCard::Dodge,
+        DefendCard::Armour(1, 2),
+    ],
+    defend_max_size: 8,
+    tech: &[Tech::CritNext, Tech::Repel, Tech::Blink, Tech::Blink],
+    tech_max_size: 8,
+    abilities: &[
+        Ability::Stash(AbilityTarget::Attack),
+        Ability::DuplicateTop(AbilityTarget::Attack),
+    ],
+};
+
+impl Loadout {
+    pub fn all() -> &'static [Loadout] {
+        &[
+            Loadout::Operative,
+            Loadout::Tank,
+            Loadout::Blinker,
+            Loadout::Skewerer,
+        ]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Loadout::Operative => "Operative",
+            Loadout::Tank => "Tank",
+            Loadout::Blinker => "Blinker",
+            Loadout::Skewerer => "Skewerer",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Loadout::Operative => "balanced decks",
+            Loadout::Tank => "heavy armour, few attacks",
+            Loadout::Blinker => "large tech deck",
+            Loadout::Skewerer => "skewers, little armour",
+        }
+    }
+
+    pub fn spec(self) -> &'static LoadoutSpec {
+        match self {
+            Loadout::Operative => &OPERATIVE,
+            Loadout::Tank => &TANK,
+            Loadout::Blinker => &BLINKER,
+            Loadout::Skewerer => &SKEWERER,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    player::{self, AbilityTarget, Outcome, Player},
+    unlock::Unlocks,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const REMOVE_CURSE_COST: u32 = 3;
+const UPGRADE_COST: u32 = 4;
+const BUY_COST: u32 = 6;
+const SACRIFICE_VALUE: u32 = 1;
+
+/// Something the player can do in the safehouse between floors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SafehouseAction {
+    /// Remove the curse card nearest the top of a stack
+    RemoveCurse(AbilityTarget),
+    /// Replace the top card of a stack with a stronger version of itself
+    Upgrade(AbilityTarget),
+    /// Buy the card at this index of the safehouse stock
+    Buy(usize),
+    /// Destroy the top card of a stack in exchange for credits
+    Sacrifice(AbilityTarget),
+    Leave,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SafehouseOption {
+    pub action: SafehouseAction,
+    /// The card which the action affects, if any
+    pub card: Option<Outcome>,
+    /// Credits spent by the action
+    pub cost: u32,
+    /// Credits earned by the action
+    pub gain: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Safehouse {
+    stock: Vec<Option<Outcome>>,
+}
+
+fn top_card(player: &Player, target: AbilityTarget) -> Option<Outcome> {
+    match target {
+        AbilityTarget::Attack => player.attack.peek().cloned().map(Outcome::Attack),
+        AbilityTarget::Defend => player.defend.peek().cloned().map(Outcome::Defend),
+        AbilityTarget::Tech => player.tech.peek().cloned().map(Outcome::Tech),
+    }
+}
+
+fn is_curse(card: Outcome) -> bool {
+    match card {
+        Outcome::Attack(attack) => attack.is_curse(),
+        Outcome::Defend(defend) => defend.is_curse(),
+        Outcome::Tech(tech) => tech.is_curse(),
+    }
+}
+
+fn nearest_top_curse(player: &Player, target: AbilityTarget) -> Option<Outcome> {
+    match target {
+        AbilityTarget::Attack => player
+            .attack
+            .iter()
+            .find(|a| a.is_curse())
+            .cloned()
+            .map(Outcome::Attack),
+        AbilityTarget::Defend => player
+            .defend
+            .iter()
+            .find(|d| d.is_curse())
+            .cloned()
+            .map(Outcome::Defend),
+        AbilityTarget::Tech => player
+            .tech
+            .iter()
+            .find(|t| t.is_curse())
+            .cloned()
+            .map(Outcome::Tech),
+    }
+}
+
+fn upgraded_top_card(player: &Player, target: AbilityTarget) -> Option<Outcome> {
+    match target {
+        AbilityTarget::Attack => player
+            .attack
+            .peek()
+            .and_then(|a| a.upgrade())
+            .map(Outcome::Attack),
+        AbilityTarget::Defend => player
+            .defend
+            .peek()
+            .and_then(|d| d.upgrade())
+            .map(Outcome::Defend),
+        AbilityTarget::Tech => None,
+    }
+}
+
+fn has_room(player: &Player, card: Outcome) -> bool {
+    match card {
+        Outcome::Attack(_) => !player.attack.is_full(),
+        Outcome::Defend(_) => !player.defend.is_full(),
+        Outcome::Tech(_) => !player.tech.is_full(),
+    }
+}
+
+const TARGETS: [AbilityTarget; 3] = [
+    AbilityTarget::Attack,
+    AbilityTarget::Defend,
+    AbilityTarget::Tech,
+];
+
+impl Safehouse {
+    pub fn new<R: Rng>(level: u32, unlocks: &Unlocks, rng: &mut R) -> Self {
+        let stock = vec![
+            Some(Outcome::Attack(player::choose_attack(
+                level, true, unlocks, rng,
+            ))),
+            Some(Outcome::Defend(player::choose_defend(level, true, rng))),
+            Some(Outcome::Tech(player::choose_tech(
+                level, true, unlocks, rng,
+            ))),
+        ];
+        Self { stock }
+    }
+
+    /// The actions which the player can currently afford and which would have an effect
+    pub fn options(&self, player: &Player) -> Vec<SafehouseOption> {
+        let mut options = Vec::new();
+        for &target in TARGETS.iter() {
+            if let Some(card) = nearest_top_curse(player, target) {
+                options.push(SafehouseOption {
+                    action: SafehouseAction::RemoveCurse(target),
+                    card: Some(card),
+                    cost: REMOVE_CURSE_COST,
+                    gain: 0,
+                });
+            }
+        }
+        for &target in TARGETS.iter() {
+            if let Some(card) = upgraded_top_card(player, target) {
+                options.push(SafehouseOption {
+                    action: SafehouseAction::Upgrade(target),
+                    card: Some(card),
+                    cost: UPGRADE_COST,
+                    gain: 0,
+                });
+            }
+        }
+        for (index, &card) in self.stock.iter().enumerate() {
+            if let Some(card) = card {
+                if has_room(player, card) {
+                    options.push(SafehouseOption {
+                        action: SafehouseAction::Buy(index),
+                        card: Some(card),
+                        cost: BUY_COST,
+                        gain: 0,
+                    });
+                }
+            }
+        }
+        options.retain(|option| option.cost <= player.credits);
+        for &target in TARGETS.iter() {
+            // curses can only be removed by paying for it
+            if let Some(card) = top_card(player, target).filter(|&card| !is_curse(card)) {
+                options.push(SafehouseOption {
+                    action: SafehouseAction::Sacrifice(target),
+                    card: Some(card),
+                    cost: 0,
+                    gain: SACRIFICE_VALUE,
+                });
+            }
+        }
+        options.push(SafehouseOption {
+            action: SafehouseAction::Leave,
+            card: None,
+            cost: 0,
+            gain: 0,
+        });
+        options
+    }
+
+    /// Returns false if the action couldn't be carried out
+    pub fn apply(&mut self, action: SafehouseAction, player: &mut Player) -> bool {
+        let option = if let Some(option) = self
+            .options(player)
+            .into_iter()
+            .find(|option| option.action == action)
+        {
+            option
+        } else {
+            return false;
+        };
+        match action {
+            SafehouseAction::RemoveCurse(target) => {
+                match target {
+                    AbilityTarget::Attack => {
+                        player.attack.remove_nearest_top(|a| a.is_curse());
+                    }
+                    AbilityTarget::Defend => {
+                        player.defend.remove_nearest_top(|d| d.is_curse());
+                    }
+                    AbilityTarget::Tech => {
+                        player.tech.remove_nearest_top(|t| t.is_curse());
+                    }
+                }
+                player.credits -= option.cost;
+            }
+            SafehouseAction::Upgrade(target) => {
+                match option.card {
+                    Some(Outcome::Attack(attack)) => {
+                        if let Some(top) = player.attack.peek_mut() {
+                            *top = attack;
+                        }
+                    }
+                    Some(Outcome::Defend(defend)) => {
+                        if let Some(top) = player.defend.peek_mut() {
+                            *top = defend;
+                        }
+                    }
+                    Some(Outcome::Tech(_)) | None => {
+                        log::warn!("no upgrade for {:?} stack", target);
+                        return false;
+                    }
+                }
+                player.credits -= option.cost;
+            }
+            SafehouseAction::Buy(index) => {
+                let _ = match self.stock[index].take() {
+                    Some(Outcome::Attack(attack)) => player.attack.push(attack),
+                    Some(Outcome::Defend(defend)) => player.defend.push(defend),
+                    Some(Outcome::Tech(tech)) => player.tech.push(tech),
+                    None => return false,
+                };
+                player.credits -= option.cost;
+            }
+            SafehouseAction::Sacrifice(target) => {
+                match target {
+                    AbilityTarget::Attack => {
+                        player.attack.pop();
+                    }
+                    AbilityTarget::Defend => {
+                        player.defend.pop();
+                    }
+                    AbilityTarget::Tech => {
+                        player.tech.pop();
+                    }
+                }
+                player.credits += option.gain;
+            }
+            SafehouseAction::Leave => (),
+        }
+        true
+    }
+}
@@ -2,17 +2,20 @@ use crate::behaviour::Agent;
 use crate::{
     world::EntityData,
     world::{Layer, Location},
-    World,
+    Difficulty, World,
 };
+use direction::CardinalDirection;
 use entity_table::{ComponentTable, Entity};
 use grid_2d::CoordIter;
 use grid_2d::{Coord, Size};
-use procgen::{Sewer, SewerCell, SewerSpec};
+use procgen::{Sewer, SewerCell, SewerConstraints, SewerGenerator, SewerSpec};
 use rand::{
     seq::{IteratorRandom, SliceRandom},
     Rng,
 };
 use rgb24::Rgb24;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
 
 pub struct Terrain {
     pub world: World,
@@ -24,7 +27,7 @@ pub struct Terrain {
 pub fn from_str<R: Rng>(s: &str, player_data: EntityData, rng: &mut R) -> Terrain {
     let rows = s.split('\n').filter(|s| !s.is_empty()).collect::<Vec<_>>();
     let size = Size::new_u16(rows[0].len() as u16, rows.len() as u16);
-    let mut world = World::new(size, 0);
+    let mut world = World::new(size, 0, Difficulty::default(), GameMode::default());
     let mut agents = ComponentTable::default();
     let mut player_data = Some(player_data);
     let mut player = None;
@@ -86,6 +89,9 @@ pub fn from_str<R: Rng>(s: &str, player_data: EntityData, rng: &mut R) -> Terrai
                 '~' => {
                     world.spawn_sludge(coord);
                 }
+                '=' => {
+                    world.spawn_bridge(coord);
+                }
                 '@' => {
                     world.spawn_floor(coord);
                     let location = Location {
@@ -138,6 +144,9 @@ enum NpcType {
     Divide,
     Teleport,
     Goo,
+    Swap,
+    Curse,
+    Spitter,
 }
 
 fn spawn_npc<R: Rng>(world: &mut World, npc_type: NpcType, coord: Coord, rng: &mut R) -> Entity {
@@ -145,10 +154,13 @@ fn spawn_npc<R: Rng>(world: &mut World, npc_type: NpcType, coord: Coord, rng: &m
         NpcType::Divide => world.spawn_slime_divide(coord, rng),
         NpcType::Teleport => world.spawn_slime_teleport(coord, rng),
         NpcType::Goo => world.spawn_slime_goo(coord, rng),
+        NpcType::Swap => world.spawn_slime_swap(coord, rng),
+        NpcType::Curse => world.spawn_slime_curse(coord),
+        NpcType::Spitter => world.spawn_slime_spitter(coord, rng),
     }
 }
 
-const ENEMY_TYPES: &[NpcType] = &[
+const ENEMY_TYPES_SHALLOW: &[NpcType] = &[
     NpcType::Divide,
     NpcType::Divide,
     NpcType::Divide,
@@ -162,6 +174,82 @@ const ENEMY_TYPES: &[NpcType] = &[
     NpcType::Teleport,
 ];
 
+const ENEMY_TYPES_MIDDLE: &[NpcType] = &[
+    NpcType::Divide,
+    NpcType::Divide,
+    NpcType::Divide,
+    NpcType::Divide,
+    NpcType::Goo,
+    NpcType::Goo,
+    NpcType::Goo,
+    NpcType::Goo,
+    NpcType::Teleport,
+    NpcType::Swap,
+    NpcType::Spitter,
+];
+
+const ENEMY_TYPES_DEEP: &[NpcType] = &[
+    NpcType::Divide,
+    NpcType::Divide,
+    NpcType::Divide,
+    NpcType::Goo,
+    NpcType::Goo,
+    NpcType::Goo,
+    NpcType::Teleport,
+    NpcType::Teleport,
+    NpcType::Swap,
+    NpcType::Spitter,
+    NpcType::Spitter,
+    NpcType::Curse,
+];
+
+#[derive(Clone, Copy)]
+enum UpgradeType {
+    Attack,
+    Defend,
+    Tech,
+}
+
+const ALL_UPGRADES: &[UpgradeType] = &[UpgradeType::Attack, UpgradeType::Defend, UpgradeType::Tech];
+
+impl UpgradeType {
+    fn spawn(self, world: &mut World, coord: Coord, level: u32) -> Entity {
+        match self {
+            Self::Attack => world.spawn_slime_attack_upgrade(coord, level),
+            Self::Defend => world.spawn_slime_defend_upgrade(coord, level),
+            Self::Tech => world.spawn_slime_tech_upgrade(coord, level),
+        }
+    }
+}
+
+struct SpawnTable {
+    enemy_types: &'static [NpcType],
+    /// Chance of placing a fleeing upgrade slime on the floor
+    upgrade_probability: f64,
+    /// Chance of placing a human who can be escorted to the stairs
+    human_probability: f64,
+}
+
+fn spawn_table(level: u32) -> SpawnTable {
+    match level {
+        0..=2 => SpawnTable {
+            enemy_types: ENEMY_TYPES_SHALLOW,
+            upgrade_probability: 0.25,
+            human_probability: 0.5,
+        },
+        3 | 4 => SpawnTable {
+            enemy_types: ENEMY_TYPES_MIDDLE,
+            upgrade_probability: 0.5,
+            human_probability: 0.5,
+        },
+        _ => SpawnTable {
+            enemy_types: ENEMY_TYPES_DEEP,
+            upgrade_probability: 0.75,
+            human_probability: 0.25,
+        },
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Item {
     Attack,
@@ -182,13 +270,126 @@ impl Item {
 const ALL_ITEMS: &[Item] = &[Item::Attack, Item::Defend, Item::Tech];
 const BALANCED_ITEMS: &[Item] = &[Item::Attack, Item::Defend, Item::Tech];
 
-fn sewer_mini<R: Rng>(spec: SewerSpec, player_data: EntityData, rng: &mut R) -> Terrain {
+/// A part of the map that can only be entered through a single door, which will be locked
+struct LockedRoom {
+    door: Coord,
+    /// Cells on the far side of the door
+    behind: Vec<Coord>,
+    /// Cells the player can reach without a key
+    reachable: HashSet<Coord>,
+}
+
+fn reachable_without_passing(sewer: &Sewer, blocked: Coord) -> HashSet<Coord> {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(sewer.start);
+    queue.push_back(sewer.start);
+    while let Some(coord) = queue.pop_front() {
+        for direction in CardinalDirection::all() {
+            let neighbour = coord + direction.coord();
+            if neighbour == blocked {
+                continue;
+            }
+            if let Some(&cell) = sewer.map.get(neighbour) {
+                if cell != SewerCell::Wall && seen.insert(neighbour) {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+    seen
+}
+
+fn choose_locked_room<R: Rng>(sewer: &Sewer, rng: &mut R) -> Option<LockedRoom> {
+    let mut doors = sewer
+        .map
+        .enumerate()
+        .filter_map(|(coord, &cell)| {
+            if cell == SewerCell::Door {
+                Some(coord)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    doors.shuffle(rng);
+    for door in doors {
+        let reachable = reachable_without_passing(sewer, door);
+        if !reachable.contains(&sewer.goal) {
+            // locking this door could leave the player stranded
+            continue;
+        }
+        let behind = sewer
+            .map
+            .enumerate()
+            .filter_map(|(coord, &cell)| {
+                if cell == SewerCell::Floor && !reachable.contains(&coord) {
+                    Some(coord)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if !behind.is_empty() {
+            return Some(LockedRoom {
+                door,
+                behind,
+                reachable,
+            });
+        }
+    }
+    None
+}
+
+/// Generates a sewer from `spec`, relaxing it when it can't be satisfied. The constraints are
+/// dropped first, and if the spec's generator still fails then the wave function collapse
+/// generator is retried until it produces a sewer.
+fn generate_sewer<R: Rng>(spec: SewerSpec, rng: &mut R) -> Sewer {
+    let error = match Sewer::generate(spec, rng) {
+        Ok(sewer) => return sewer,
+        Err(error) => error,
+    };
+    log::warn!(
+        "failed to generate sewer satisfying constraints after {} attempts",
+        error.attempts
+    );
+    let unconstrained_spec = SewerSpec {
+        constraints: SewerConstraints::default(),
+        ..spec
+    };
+    let error = match Sewer::generate(unconstrained_spec, rng) {
+        Ok(sewer) => return sewer,
+        Err(error) => error,
+    };
+    log::warn!(
+        "failed to generate sewer with {:?} after {} attempts",
+        spec.generator,
+        error.attempts
+    );
+    let wfc_spec = SewerSpec {
+        generator: SewerGenerator::Wfc,
+        ..unconstrained_spec
+    };
+    loop {
+        if let Some(sewer) = Sewer::try_generate(wfc_spec, rng) {
+            return sewer;
+        }
+    }
+}
+
+fn sewer_mini<R: Rng>(
+    difficulty: Difficulty,
+    mode: GameMode,
+    spec: SewerSpec,
+    player_data: EntityData,
+    rng: &mut R,
+) -> Terrain {
     const MINI_SIZE: Size = Size::new_u16(8, 8);
     let offset = (spec.size.to_coord().unwrap() - MINI_SIZE.to_coord().unwrap()) / 2;
-    let mut world = World::new(spec.size, 0);
+    let mut world = World::new(spec.size, 0, difficulty, mode);
     let agents = ComponentTable::default();
-    let mini_spec = SewerSpec { size: MINI_SIZE };
-    let sewer = Sewer::generate(mini_spec, rng);
+    let mini_spec = SewerSpec::new(MINI_SIZE);
+    let sewer = generate_sewer(mini_spec, rng);
     for (coord, cell) in sewer.map.enumerate() {
         let coord = coord + offset;
         match cell {
@@ -234,13 +435,16 @@ fn sewer_mini<R: Rng>(spec: SewerSpec, player_data: EntityData, rng: &mut R) ->
 
 fn sewer_normal<R: Rng>(
     level: u32,
+    difficulty: Difficulty,
+    mode: GameMode,
     spec: SewerSpec,
     player_data: EntityData,
     rng: &mut R,
 ) -> Terrain {
-    let mut world = World::new(spec.size, level);
+    let mut world = World::new(spec.size, level, difficulty, mode);
     let mut agents = ComponentTable::default();
-    let sewer = Sewer::generate(spec, rng);
+    let sewer = generate_sewer(spec, rng);
+    let locked_room = choose_locked_room(&sewer, rng);
     let mut npc_candidates = Vec::new();
     for (coord, cell) in sewer.map.enumerate() {
         match cell {
@@ -253,7 +457,11 @@ fn sewer_normal<R: Rng>(
             }
             SewerCell::Door => {
                 world.spawn_floor(coord);
-                world.spawn_door(coord);
+                if locked_room.as_ref().map(|room| room.door) == Some(coord) {
+                    world.spawn_locked_door(coord);
+                } else {
+                    world.spawn_door(coord);
+                }
             }
             SewerCell::Pool => {
                 world.spawn_sludge(coord);
@@ -286,11 +494,64 @@ fn sewer_normal<R: Rng>(
             }
         })
         .collect::<Vec<_>>();
-    let num_npcs = level as usize * 2 + 2;
-    let num_items = 4;
+    let spawn_table = spawn_table(level);
+    let mut reserved_coords = vec![sewer.start, sewer.goal];
+    if let Some(room) = locked_room.as_ref() {
+        reserved_coords.push(room.door);
+        // a special item behind the locked door makes it worth finding the key
+        let &reward_coord = room.behind.choose(rng).unwrap();
+        ALL_ITEMS
+            .choose(rng)
+            .unwrap()
+            .spawn(&mut world, reward_coord, true);
+        reserved_coords.push(reward_coord);
+        let mut key_candidates = room
+            .reachable
+            .iter()
+            .cloned()
+            .filter(|coord| !reserved_coords.contains(coord))
+            .collect::<Vec<_>>();
+        // sort so that generation is deterministic for a given seed
+        key_candidates.sort_by_key(|&coord| (coord.x, coord.y));
+        let pool_candidates = key_candidates
+            .iter()
+            .cloned()
+            .filter(|&coord| *sewer.map.get_checked(coord) == SewerCell::Pool)
+            .collect::<Vec<_>>();
+        if rng.gen_bool(0.5) && !pool_candidates.is_empty() {
+            // the key has sunk into a pool of sludge
+            let &key_coord = pool_candidates.choose(rng).unwrap();
+            world.spawn_key(key_coord);
+            reserved_coords.push(key_coord);
+        } else if let Some(&key_coord) = key_candidates
+            .iter()
+            .filter(|&&coord| *sewer.map.get_checked(coord) == SewerCell::Floor)
+            .choose(rng)
+        {
+            world.spawn_key(key_coord);
+            reserved_coords.push(key_coord);
+            // the key is guarded by a slime
+            if let Some(guard_coord) = CardinalDirection::all()
+                .map(|direction| key_coord + direction.coord())
+                .filter(|coord| {
+                    sewer.map.get(*coord) == Some(&SewerCell::Floor)
+                        && !reserved_coords.contains(coord)
+                })
+                .choose(rng)
+            {
+                let npc_type = spawn_table.enemy_types.choose(rng).unwrap().clone();
+                let entity = spawn_npc(&mut world, npc_type, guard_coord, rng);
+                agents.insert(entity, Agent::new(spec.size));
+                reserved_coords.push(guard_coord);
+            }
+        }
+    }
+    empty_coords.retain(|coord| !reserved_coords.contains(coord));
+    let num_npcs = difficulty.scale_enemies(level * 2 + 2) as usize;
+    let num_items = difficulty.scale_supplies(4) as usize;
     empty_coords.shuffle(rng);
     for &coord in empty_coords.iter().take(num_npcs) {
-        let npc_type = ENEMY_TYPES.choose(rng).unwrap().clone();
+        let npc_type = spawn_table.enemy_types.choose(rng).unwrap().clone();
         let entity = spawn_npc(&mut world, npc_type, coord, rng);
         agents.insert(entity, Agent::new(spec.size));
     }
@@ -298,12 +559,26 @@ fn sewer_normal<R: Rng>(
         let item = BALANCED_ITEMS.choose(rng).unwrap();
         item.spawn(&mut world, coord, false);
     }
-    let num_special_items = 3;
+    let mut extra_coords = empty_coords.iter().skip(num_npcs + num_items);
+    if rng.gen_bool(spawn_table.upgrade_probability) {
+        if let Some(&coord) = extra_coords.next() {
+            let upgrade = ALL_UPGRADES.choose(rng).unwrap();
+            let entity = upgrade.spawn(&mut world, coord, level);
+            agents.insert(entity, Agent::new(spec.size));
+        }
+    }
+    if rng.gen_bool(spawn_table.human_probability) {
+        if let Some(&coord) = extra_coords.next() {
+            let entity = world.spawn_human(coord);
+            agents.insert(entity, Agent::new(spec.size));
+        }
+    }
+    let num_special_items = difficulty.scale_supplies(3) as usize;
     let special_item_coords = sewer
         .map
         .enumerate()
         .filter_map(|(coord, &cell)| {
-            if cell == SewerCell::Pool {
+            if cell == SewerCell::Pool && !reserved_coords.contains(&coord) {
                 Some(coord)
             } else {
                 None
@@ -323,10 +598,41 @@ fn sewer_normal<R: Rng>(
 
 pub const FINAL_LEVEL: u32 = 6;
 
-fn sewer_final<R: Rng>(spec: SewerSpec, player_data: EntityData, rng: &mut R) -> Terrain {
-    let mut world = World::new(spec.size, FINAL_LEVEL);
+/// In endless mode a boss floor appears this often
+pub const ENDLESS_BOSS_INTERVAL: u32 = FINAL_LEVEL;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// The game is won by defeating the boss on the final floor
+    Campaign,
+    /// Floors keep generating past the final floor until the player dies
+    Endless,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Campaign
+    }
+}
+
+pub fn is_boss_level(level: u32, mode: GameMode) -> bool {
+    match mode {
+        GameMode::Campaign => level == FINAL_LEVEL,
+        GameMode::Endless => level > 0 && level % ENDLESS_BOSS_INTERVAL == 0,
+    }
+}
+
+fn sewer_boss<R: Rng>(
+    level: u32,
+    difficulty: Difficulty,
+    mode: GameMode,
+    spec: SewerSpec,
+    player_data: EntityData,
+    rng: &mut R,
+) -> Terrain {
+    let mut world = World::new(spec.size, level, difficulty, mode);
     let mut agents = ComponentTable::default();
-    let sewer = Sewer::generate(spec, rng);
+    let sewer = generate_sewer(spec, rng);
     let mut npc_candidates = Vec::new();
     for (coord, cell) in sewer.map.enumerate() {
         match cell {
@@ -357,6 +663,10 @@ fn sewer_final<R: Rng>(spec: SewerSpec, player_data: EntityData, rng: &mut R) ->
         layer: Some(Layer::Character),
     };
     let player = world.insert_entity_data(player_location, player_data);
+    if mode == GameMode::Endless {
+        // there's no victory in endless mode so the descent continues past the boss
+        world.spawn_stairs(sewer.goal);
+    }
     let mut empty_coords = sewer
         .map
         .enumerate()
@@ -372,19 +682,19 @@ fn sewer_final<R: Rng>(spec: SewerSpec, player_data: EntityData, rng: &mut R) ->
         })
         .collect::<Vec<_>>();
     empty_coords.sort_by_key(|&c| c.distance2(sewer.start));
-    let num_npcs = 2;
-    for _ in 0..num_npcs {
+    let num_bosses = 2;
+    for _ in 0..num_bosses {
         let coord = empty_coords.pop().unwrap();
         let entity = world.spawn_slime_boss(coord, rng);
-        agents.insert(entity, Agent::new(spec.size));
+        agents.insert(entity, Agent::new_boss(spec.size));
     }
-    let num_items = 5;
+    let num_items = difficulty.scale_supplies(5) as usize;
     empty_coords.shuffle(rng);
     for &coord in empty_coords.iter().take(num_items) {
         let item = BALANCED_ITEMS.choose(rng).unwrap();
         item.spawn(&mut world, coord, false);
     }
-    let num_special_items = 3;
+    let num_special_items = difficulty.scale_supplies(3) as usize;
     let special_item_coords = sewer
         .map
         .enumerate()
@@ -407,12 +717,32 @@ fn sewer_final<R: Rng>(spec: SewerSpec, player_data: EntityData, rng: &mut R) ->
     }
 }
 
-pub fn sewer<R: Rng>(level: u32, spec: SewerSpec, player_data: EntityData, rng: &mut R) -> Terrain {
+/// Each stretch of the sewer is built differently so later floors feel different from early ones
+fn generator_for_level(level: u32) -> SewerGenerator {
+    match level {
+        0..=2 => SewerGenerator::Wfc,
+        3 | 4 => SewerGenerator::RoomsAndCorridors,
+        _ => SewerGenerator::Caves,
+    }
+}
+
+pub fn sewer<R: Rng>(
+    level: u32,
+    difficulty: Difficulty,
+    mode: GameMode,
+    spec: SewerSpec,
+    player_data: EntityData,
+    rng: &mut R,
+) -> Terrain {
+    let spec = SewerSpec {
+        generator: generator_for_level(level),
+        ..spec
+    };
     if level == 0 {
-        sewer_mini(spec, player_data, rng)
-    } else if level == FINAL_LEVEL {
-        sewer_final(spec, player_data, rng)
+        sewer_mini(difficulty, mode, spec, player_data, rng)
+    } else if is_boss_level(level, mode) {
+        sewer_boss(level, difficulty, mode, spec, player_data, rng)
     } else {
-        sewer_normal(level, spec, player_data, rng)
+        sewer_normal(level, difficulty, mode, spec, player_data, rng)
     }
 }
@@ -0,0 +1,132 @@
+use crate::{
+    player::{Ability, Attack, Tech},
+    Achievement, Loadout,
+};
+use serde::{Deserialize, Serialize};
+
+/// Content which becomes available in future runs once its requirement is met
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Unlock {
+    TankLoadout,
+    BlinkerLoadout,
+    SkewererLoadout,
+    PoisonCards,
+    RangedTech,
+    DeckTricks,
+    CardTransfer,
+}
+
+/// What must be done across all runs to earn an unlock
+#[derive(Debug, Clone, Copy)]
+pub enum Requirement {
+    RunsFinished(u32),
+    Achievement(Achievement),
+}
+
+impl Unlock {
+    pub fn all() -> &'static [Unlock] {
+        &[
+            Unlock::TankLoadout,
+            Unlock::PoisonCards,
+            Unlock::BlinkerLoadout,
+            Unlock::DeckTricks,
+            Unlock::SkewererLoadout,
+            Unlock::CardTransfer,
+            Unlock::RangedTech,
+        ]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Unlock::TankLoadout => "Tank loadout",
+            Unlock::BlinkerLoadout => "Blinker loadout",
+            Unlock::SkewererLoadout => "Skewerer loadout",
+            Unlock::PoisonCards => "Poison attack cards",
+            Unlock::RangedTech => "Shotgun and Rocket tech cards",
+            Unlock::DeckTricks => "Peek, Bury, Shuffle and Duplicate abilities",
+            Unlock::CardTransfer => "Transfer and Merge abilities",
+        }
+    }
+
+    pub fn requirement(self) -> Requirement {
+        match self {
+            Unlock::TankLoadout => Requirement::RunsFinished(1),
+            Unlock::PoisonCards => Requirement::RunsFinished(2),
+            Unlock::BlinkerLoadout => Requirement::RunsFinished(3),
+            Unlock::DeckTricks => Requirement::Achievement(Achievement::FloorWithoutSludge),
+            Unlock::SkewererLoadout => {
+                Requirement::Achievement(Achievement::SurviveWithEmptyDefend)
+            }
+            Unlock::CardTransfer => Requirement::RunsFinished(5),
+            Unlock::RangedTech => Requirement::RunsFinished(8),
+        }
+    }
+}
+
+/// The unlocks available during a run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Unlocks {
+    unlocked: Vec<Unlock>,
+}
+
+impl Unlocks {
+    pub fn everything() -> Self {
+        Self {
+            unlocked: Unlock::all().to_vec(),
+        }
+    }
+
+    pub fn contains(&self, unlock: Unlock) -> bool {
+        self.unlocked.contains(&unlock)
+    }
+
+    /// Returns true if the unlock wasn't already present
+    pub fn insert(&mut self, unlock: Unlock) -> bool {
+        if self.contains(unlock) {
+            false
+        } else {
+            self.unlocked.push(unlock);
+            true
+        }
+    }
+
+    fn allows(&self, unlock: Option<Unlock>) -> bool {
+        unlock.map(|unlock| self.contains(unlock)).unwrap_or(true)
+    }
+
+    pub fn allows_loadout(&self, loadout: Loadout) -> bool {
+        self.allows(match loadout {
+            Loadout::Operative => None,
+            Loadout::Tank => Some(Unlock::TankLoadout),
+            Loadout::Blinker => Some(Unlock::BlinkerLoadout),
+            Loadout::Skewerer => Some(Unlock::SkewererLoadout),
+        })
+    }
+
+    pub fn allows_ability(&self, ability: Ability) -> bool {
+        self.allows(match ability {
+            Ability::Stash(_) | Ability::SwapTop2(_) | Ability::Discard(_) => None,
+            Ability::Peek(_)
+            | Ability::Bury(_)
+            | Ability::Shuffle(_)
+            | Ability::DuplicateTop(_) => Some(Unlock::DeckTricks),
+            Ability::TransferToDefend | Ability::TransferToAttack | Ability::MergeTop2 => {
+                Some(Unlock::CardTransfer)
+            }
+        })
+    }
+
+    pub fn allows_attack(&self, attack: Attack) -> bool {
+        self.allows(match attack {
+            Attack::Poison(_) => Some(Unlock::PoisonCards),
+            Attack::Hit(_) | Attack::Cleave(_) | Attack::Skewer(_) | Attack::Miss => None,
+        })
+    }
+
+    pub fn allows_tech(&self, tech: Tech) -> bool {
+        self.allows(match tech {
+            Tech::Shotgun | Tech::Rocket => Some(Unlock::RangedTech),
+            _ => None,
+        })
+    }
+}
@@ -1,20 +1,28 @@
 use crate::{
+    achievement::Milestone,
     world::{
-        data::{DoorState, DropItemOnDeath, Item, OnCollision, OnDamage, ProjectileDamage, Tile},
+        data::{
+            Disposition, DoorState, DropItemOnDeath, Item, OnCollision, OnDamage, ProjectileDamage,
+            StatusEffect, StatusEffects, Tile,
+        },
         explosion, player,
         realtime_periodic::{core::ScheduledRealtimePeriodicState, movement},
         spatial::{Layer, Location, SpatialTable},
         ExternalEvent, World,
     },
-    VisibilityGrid,
+    PickupResolution, VisibilityGrid,
 };
 use direction::{CardinalDirection, Direction};
 use entity_table::Entity;
-use grid_2d::Coord;
+use grid_2d::{Coord, CoordIter};
 use rand::{seq::IteratorRandom, seq::SliceRandom, Rng};
 use std::collections::{HashSet, VecDeque};
 use std::time::Duration;
 
+const POISON_TURNS: u32 = 5;
+const BRIDGE_MAX_CROSSINGS: u32 = 6;
+const CREDITS_PER_KILL: u32 = 1;
+
 #[derive(Clone, Copy)]
 pub enum Error {
     BlinkToNonVisibleCell,
@@ -28,6 +36,13 @@ pub enum Error {
     NotEnoughAttacks,
     NotEnoughDefends,
     NotEnoughTechs,
+    FireWithoutTarget,
+    FireAtSelf,
+    Rooted,
+    DoorLocked,
+    NoDoorToClose,
+    BuryWithoutDepth,
+    CantMergeAttacks,
 }
 
 impl World {
@@ -35,6 +50,7 @@ impl World {
         &mut self,
         entity: Entity,
         ability_slot: u8,
+        depth: Option<usize>,
         rng: &mut R,
     ) -> Result<(), Error> {
         let player = self.components.player.get_mut(entity).unwrap();
@@ -65,6 +81,85 @@ impl World {
                 Discard(Tech) => {
                     player.tech.pop().ok_or_else(|| Error::NotEnoughTechs)?;
                 }
+                Peek(target) => {
+                    if player.deck_len(target) == 0 {
+                        return Err(match target {
+                            Attack => Error::NotEnoughAttacks,
+                            Defend => Error::NotEnoughDefends,
+                            Tech => Error::NotEnoughTechs,
+                        });
+                    }
+                    player.peeking = Some(target);
+                }
+                Bury(target) => {
+                    let not_enough = match target {
+                        Attack => Error::NotEnoughAttacks,
+                        Defend => Error::NotEnoughDefends,
+                        Tech => Error::NotEnoughTechs,
+                    };
+                    if player.deck_len(target) < 2 {
+                        return Err(not_enough);
+                    }
+                    let depth = depth.ok_or(Error::BuryWithoutDepth)?;
+                    match target {
+                        Attack => player.attack.bury(depth),
+                        Defend => player.defend.bury(depth),
+                        Tech => player.tech.bury(depth),
+                    }
+                    .map_err(|_| not_enough)?;
+                }
+                Shuffle(Attack) => player
+                    .attack
+                    .shuffle(rng)
+                    .map_err(|_| Error::NotEnoughAttacks)?,
+                Shuffle(Defend) => player
+                    .defend
+                    .shuffle(rng)
+                    .map_err(|_| Error::NotEnoughDefends)?,
+                Shuffle(Tech) => player
+                    .tech
+                    .shuffle(rng)
+                    .map_err(|_| Error::NotEnoughTechs)?,
+                DuplicateTop(Attack) => player
+                    .attack
+                    .duplicate_top()
+                    .map_err(|_| Error::NotEnoughAttacks)?,
+                DuplicateTop(Defend) => player
+                    .defend
+                    .duplicate_top()
+                    .map_err(|_| Error::NotEnoughDefends)?,
+                DuplicateTop(Tech) => player
+                    .tech
+                    .duplicate_top()
+                    .map_err(|_| Error::NotEnoughTechs)?,
+                TransferToDefend => {
+                    if player.defend.is_full() {
+                        return Err(Error::DefendDeckFull);
+                    }
+                    let attack = player.attack.pop().ok_or(Error::NotEnoughAttacks)?;
+                    let _ = player.defend.push(attack.to_defend());
+                }
+                TransferToAttack => {
+                    if player.attack.is_full() {
+                        return Err(Error::AttackDeckFull);
+                    }
+                    let defend = player.defend.pop().ok_or(Error::NotEnoughDefends)?;
+                    let _ = player.attack.push(defend.to_attack());
+                }
+                MergeTop2 => {
+                    let top_2 = {
+                        let mut top = player.attack.iter().cloned();
+                        (top.next(), top.next())
+                    };
+                    match top_2 {
+                        (Some(player::Attack::Hit(a)), Some(player::Attack::Hit(b))) => {
+                            player.attack.pop();
+                            player.attack.pop();
+                            let _ = player.attack.push(player::Attack::Hit(a + b));
+                        }
+                        _ => return Err(Error::CantMergeAttacks),
+                    }
+                }
             }
             self.wait(entity, rng);
         } else {
@@ -78,42 +173,90 @@ impl World {
             self.after_player_move(entity, coord, rng);
         }
     }
+    fn roll_item_card<R: Rng>(&self, item: Item, rng: &mut R) -> player::Outcome {
+        use player::Outcome;
+        match item {
+            Item::Attack { special } => Outcome::Attack(player::choose_attack(
+                self.level,
+                special,
+                &self.unlocks,
+                rng,
+            )),
+            Item::Defend { special } => {
+                Outcome::Defend(player::choose_defend(self.level, special, rng))
+            }
+            Item::Tech { special } => {
+                Outcome::Tech(player::choose_tech(self.level, special, &self.unlocks, rng))
+            }
+        }
+    }
     fn pick_up_item<R: Rng>(&mut self, character: Entity, item_entity: Entity, rng: &mut R) {
+        use player::Outcome;
         if self.components.to_remove.contains(character) {
             return;
         }
+        let card = if let Some(&card) = self.components.revealed_card.get(item_entity) {
+            card
+        } else if let Some(&item) = self.components.item.get(item_entity) {
+            self.roll_item_card(item, rng)
+        } else {
+            return;
+        };
         let player = self.components.player.get_mut(character).unwrap();
-        if let Some(item) = self.components.item.get(item_entity) {
-            let taken = match item {
-                Item::Attack { special } => {
-                    if player.attack.is_full() {
-                        false
-                    } else {
-                        let attack = player::choose_attack(self.level, *special, rng);
-                        let _ = player.attack.push(attack);
-                        true
-                    }
-                }
-                Item::Defend { special } => {
-                    if player.defend.is_full() {
-                        false
-                    } else {
-                        let defend = player::choose_defend(self.level, *special, rng);
-                        let _ = player.defend.push(defend);
-                        true
-                    }
-                }
-                Item::Tech { special } => {
-                    if player.tech.is_full() {
-                        false
-                    } else {
-                        let tech = player::choose_tech(self.level, *special, rng);
-                        let _ = player.tech.push(tech);
-                        true
-                    }
-                }
-            };
-            if taken {
+        let result = match card {
+            Outcome::Attack(attack) => player.attack.push(attack),
+            Outcome::Defend(defend) => player.defend.push(defend),
+            Outcome::Tech(tech) => player.tech.push(tech),
+        };
+        if result.is_ok() {
+            self.components.to_remove.insert(item_entity, ());
+        } else {
+            // the deck is full, so reveal the card and let the player decide what to do
+            self.components.revealed_card.insert(item_entity, card);
+            self.pending_pickup = Some(item_entity);
+        }
+    }
+
+    pub fn pending_pickup_card(&self) -> Option<player::Outcome> {
+        self.pending_pickup
+            .and_then(|item_entity| self.components.revealed_card.get(item_entity).cloned())
+    }
+
+    pub fn resolve_pickup(&mut self, character: Entity, resolution: PickupResolution) {
+        use player::Outcome;
+        let item_entity = if let Some(item_entity) = self.pending_pickup.take() {
+            item_entity
+        } else {
+            return;
+        };
+        let card = if let Some(&card) = self.components.revealed_card.get(item_entity) {
+            card
+        } else {
+            return;
+        };
+        let index_from_top = match resolution {
+            PickupResolution::Leave => return,
+            PickupResolution::SwapTop => 0,
+            PickupResolution::Discard(index_from_top) => index_from_top,
+        };
+        let player = self.components.player.get_mut(character).unwrap();
+        let replaced = match card {
+            Outcome::Attack(attack) => player
+                .attack
+                .replace(index_from_top, attack)
+                .map(Outcome::Attack),
+            Outcome::Defend(defend) => player
+                .defend
+                .replace(index_from_top, defend)
+                .map(Outcome::Defend),
+            Outcome::Tech(tech) => player.tech.replace(index_from_top, tech).map(Outcome::Tech),
+        };
+        match (resolution, replaced) {
+            (_, None) => log::warn!("no card at index {} to replace", index_from_top),
+            (PickupResolution::SwapTop, Some(replaced)) => {
+                self.components.revealed_card.insert(item_entity, replaced);
+            }
+            (_, Some(_)) => {
                 self.components.to_remove.insert(item_entity, ());
             }
         }
@@ -122,12 +265,18 @@ impl World {
         if let Some(&cell) = self.spatial_table.layers_at(target_coord) {
             if let Some(floor_entity) = cell.floor {
                 if self.components.sludge.contains(floor_entity) {
+                    self.milestones.push(Milestone::TouchedSludge);
                     self.apply_defend(character, rng);
                 }
             }
             if let Some(feature_entity) = cell.feature {
                 if self.components.item.contains(feature_entity) {
                     self.pick_up_item(character, feature_entity, rng);
+                } else if self.components.key.contains(feature_entity) {
+                    if let Some(player) = self.components.player.get_mut(character) {
+                        player.keys += 1;
+                        self.components.to_remove.insert(feature_entity, ());
+                    }
                 }
             }
         }
@@ -138,6 +287,16 @@ impl World {
         direction: CardinalDirection,
         rng: &mut R,
     ) -> Result<(), Error> {
+        if let Some(status_effects) = self.components.status_effects.get(character) {
+            if status_effects.contains(StatusEffect::Stunned) {
+                return Ok(());
+            }
+            if let Some(turns_remaining) = status_effects.turns_remaining(StatusEffect::Slowed) {
+                if turns_remaining % 2 == 0 {
+                    return Ok(());
+                }
+            }
+        }
         if let Some(move_half_speed) = self.components.move_half_speed.get_mut(character) {
             if move_half_speed.skip_next_move {
                 move_half_speed.skip_next_move = false;
@@ -151,15 +310,44 @@ impl World {
             panic!("failed to find coord for {:?}", character);
         };
         let target_coord = current_coord + direction.coord();
+        if self.has_status_effect(character, StatusEffect::Rooted) {
+            let occupant = self
+                .spatial_table
+                .layers_at(target_coord)
+                .and_then(|cell| cell.character);
+            if let Some(occupant) = occupant {
+                self.melee_attack(character, occupant, direction, rng);
+            } else if self.components.player.contains(character) {
+                return Err(Error::Rooted);
+            }
+            return Ok(());
+        }
+        let is_player = self.components.player.contains(character);
+        let mut oozing_through_door = false;
         if let Some(&cell) = self.spatial_table.layers_at(target_coord) {
             if let Some(feature_entity) = cell.feature {
                 if self.components.solid.contains(feature_entity) {
-                    if let Some(DoorState::Closed) =
-                        self.components.door_state.get(feature_entity).cloned()
-                    {
-                        self.open_door(feature_entity);
-                    } else {
-                        return Err(Error::WalkIntoSolidCell);
+                    match self.components.door_state.get(feature_entity).cloned() {
+                        Some(DoorState::Closed) => {
+                            if is_player {
+                                self.open_door(feature_entity);
+                            } else {
+                                // slimes can't work door handles so they squeeze underneath
+                                oozing_through_door = true;
+                            }
+                        }
+                        Some(DoorState::Locked) => {
+                            if !is_player {
+                                return Err(Error::WalkIntoSolidCell);
+                            }
+                            let player = self.components.player.get_mut(character).unwrap();
+                            if player.keys == 0 {
+                                return Err(Error::DoorLocked);
+                            }
+                            player.keys -= 1;
+                            self.open_door(feature_entity);
+                        }
+                        Some(DoorState::Open) | None => return Err(Error::WalkIntoSolidCell),
                     }
                 }
             }
@@ -173,7 +361,15 @@ impl World {
         {
             self.melee_attack(character, occupant, direction, rng);
         } else {
-            if self.components.player.contains(character) {
+            if oozing_through_door {
+                self.apply_status_effect(character, StatusEffect::Slowed, 2);
+            }
+            if self.has_status_effect(character, StatusEffect::SludgeCoated) {
+                self.change_floor_to_sludge(current_coord);
+            } else {
+                self.corrode_bridge(current_coord);
+            }
+            if is_player {
                 self.after_player_move(character, target_coord, rng);
             }
         }
@@ -250,6 +446,10 @@ impl World {
             Hit(n) => self.damage_character(victim, n, rng),
             Cleave(n) => self.cleave(attacker, n, rng),
             Skewer(n) => self.skewer(attacker, n, direction, rng),
+            Poison(n) => {
+                self.apply_status_effect(victim, StatusEffect::Poisoned, POISON_TURNS);
+                self.damage_character(victim, n, rng);
+            }
         }
     }
 
@@ -279,7 +479,19 @@ impl World {
     }
 
     fn revenge<R: Rng>(&mut self, entity: Entity, rng: &mut R) {
+        let this_coord = self.spatial_table.coord_of(entity).unwrap();
+        let bosses = Direction::all()
+            .filter_map(|direction| self.spatial_table.layers_at(this_coord + direction.coord()))
+            .filter_map(|cell| cell.character)
+            .filter(|&character| self.components.tile.get(character) == Some(&Tile::SlimeBoss))
+            .collect::<Vec<_>>();
         self.cleave(entity, 100, rng);
+        if bosses
+            .into_iter()
+            .any(|boss| self.components.to_remove.contains(boss))
+        {
+            self.milestones.push(Milestone::KilledBossWithRevenge);
+        }
     }
 
     fn apply_defend<R: Rng>(&mut self, victim: Entity, rng: &mut R) {
@@ -351,16 +563,86 @@ impl World {
         rng: &mut R,
     ) {
         if self.components.player.get(attacker).is_some() {
-            self.player_melee_attack(attacker, victim, direction, rng);
-        } else if self.components.player.get(victim).is_some() {
-            self.npc_melee_attack(attacker, victim, rng);
+            if self.components.human.contains(victim) {
+                self.player_swap_with_human(attacker, victim, rng);
+            } else {
+                self.player_melee_attack(attacker, victim, direction, rng);
+            }
+        } else if let Some(disposition) = self.components.npc.get(attacker).map(|n| n.disposition)
+        {
+            match disposition {
+                Disposition::Friendly => (),
+                Disposition::Hostile | Disposition::Afraid => {
+                    if self.components.player.get(victim).is_some() {
+                        self.npc_melee_attack(attacker, victim, rng);
+                    } else if disposition == Disposition::Hostile
+                        && self.components.human.contains(victim)
+                    {
+                        self.make_former_human(victim);
+                    }
+                }
+            }
         }
     }
 
+    fn player_swap_with_human<R: Rng>(&mut self, player: Entity, human: Entity, rng: &mut R) {
+        let player_coord = self.spatial_table.coord_of(player).unwrap();
+        let human_coord = self.spatial_table.coord_of(human).unwrap();
+        self.spatial_table.remove(player);
+        self.spatial_table.update_coord(human, player_coord).unwrap();
+        self.spatial_table
+            .update(
+                player,
+                Location {
+                    coord: human_coord,
+                    layer: Some(Layer::Character),
+                },
+            )
+            .unwrap();
+        self.after_player_move(player, human_coord, rng);
+    }
+
     fn open_door(&mut self, door: Entity) {
         self.components.solid.remove(door);
         self.components.opacity.remove(door);
         self.components.tile.insert(door, Tile::DoorOpen);
+        self.components.door_state.insert(door, DoorState::Open);
+    }
+
+    fn close_door(&mut self, door: Entity) {
+        self.components.solid.insert(door, ());
+        self.components.opacity.insert(door, 255);
+        self.components.tile.insert(door, Tile::DoorClosed);
+        self.components.door_state.insert(door, DoorState::Closed);
+    }
+
+    pub fn character_close_doors<R: Rng>(
+        &mut self,
+        character: Entity,
+        rng: &mut R,
+    ) -> Result<(), Error> {
+        let coord = self.spatial_table.coord_of(character).unwrap();
+        let doors_to_close = CardinalDirection::all()
+            .filter_map(|direction| {
+                let cell = self.spatial_table.layers_at(coord + direction.coord())?;
+                let feature = cell.feature?;
+                if cell.character.is_none()
+                    && self.components.door_state.get(feature) == Some(&DoorState::Open)
+                {
+                    Some(feature)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if doors_to_close.is_empty() {
+            return Err(Error::NoDoorToClose);
+        }
+        for door in doors_to_close {
+            self.close_door(door);
+        }
+        self.wait(character, rng);
+        Ok(())
     }
 
     pub fn character_fire_bullet(&mut self, character: Entity, target: Coord) {
@@ -388,7 +670,7 @@ impl World {
     ) -> Result<(), Error> {
         use player::Tech::*;
         let player = self.components.player.get_mut(entity).unwrap();
-        if let Some(tech) = player.tech.peek() {
+        if let Some(&tech) = player.tech.peek() {
             match tech {
                 Blink => {
                     if let Some(spatial_cell) = self.spatial_table.layers_at(coord) {
@@ -402,6 +684,7 @@ impl World {
                             };
                             if can_blink {
                                 player.tech.pop();
+                                self.milestones.push(Milestone::UsedBlink);
                                 self.blink(entity, coord, rng);
                                 Ok(())
                             } else {
@@ -414,6 +697,19 @@ impl World {
                         Err(Error::BlinkToNonVisibleCell)
                     }
                 }
+                Shotgun | Rocket => {
+                    if self.spatial_table.coord_of(entity) == Some(coord) {
+                        Err(Error::FireAtSelf)
+                    } else {
+                        player.tech.pop();
+                        if tech == Shotgun {
+                            self.character_fire_shotgun(entity, coord, rng);
+                        } else {
+                            self.character_fire_rocket(entity, coord);
+                        }
+                        Ok(())
+                    }
+                }
                 _ => return self.apply_tech(entity, rng),
             }
         } else {
@@ -421,6 +717,65 @@ impl World {
         }
     }
 
+    pub fn apply_status_effect(&mut self, entity: Entity, effect: StatusEffect, turns: u32) {
+        if !self.components.character.contains(entity) {
+            return;
+        }
+        if let Some(status_effects) = self.components.status_effects.get_mut(entity) {
+            status_effects.apply(effect, turns);
+        } else {
+            let mut status_effects = StatusEffects::default();
+            status_effects.apply(effect, turns);
+            self.components
+                .status_effects
+                .insert(entity, status_effects);
+        }
+    }
+
+    fn apply_status_effect_in_range(&mut self, entity: Entity, effect: StatusEffect, turns: u32) {
+        const RANGE: u32 = 8;
+        let this_coord = self.spatial_table.coord_of(entity).unwrap();
+        let to_affect = self
+            .components
+            .npc
+            .iter()
+            .filter_map(|(entity, npc)| {
+                if npc.disposition == Disposition::Friendly {
+                    return None;
+                }
+                let coord = self.spatial_table.coord_of(entity)?;
+                if this_coord.distance2(coord) < RANGE * RANGE {
+                    Some(entity)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        for entity in to_affect {
+            self.apply_status_effect(entity, effect, turns);
+        }
+    }
+
+    pub fn tick_status_effects<R: Rng>(&mut self, rng: &mut R) {
+        const POISON_DAMAGE: u32 = 2;
+        let entities = self
+            .components
+            .status_effects
+            .entities()
+            .collect::<Vec<_>>();
+        for entity in entities {
+            if self.has_status_effect(entity, StatusEffect::Poisoned) {
+                self.damage_character(entity, POISON_DAMAGE, rng);
+            }
+            if let Some(status_effects) = self.components.status_effects.get_mut(entity) {
+                status_effects.tick();
+                if status_effects.is_empty() {
+                    self.components.status_effects.remove(entity);
+                }
+            }
+        }
+    }
+
     fn attract(&mut self, entity: Entity) {
         const RANGE: u32 = 12;
         const ATTRACT_BY: u32 = 4;
@@ -517,6 +872,10 @@ impl World {
                     log::warn!("attempted to blink without destination coord");
                     result = Err(Error::BlinkWithoutDestination);
                 }
+                Shotgun | Rocket => {
+                    log::warn!("attempted to fire without target coord");
+                    result = Err(Error::FireWithoutTarget);
+                }
                 CritNext => {
                     if player.attack.push(player::Attack::Hit(99)).is_err() {
                         result = Err(Error::AttackDeckFull);
@@ -534,6 +893,9 @@ impl World {
                 }
                 Attract => self.attract(entity),
                 Repel => self.repel(entity),
+                Stun => self.apply_status_effect_in_range(entity, StatusEffect::Stunned, 3),
+                Root => self.apply_status_effect_in_range(entity, StatusEffect::Rooted, 5),
+                Mark => self.apply_status_effect_in_range(entity, StatusEffect::Marked, 8),
                 Skip => {
                     player.attack.pop();
                     if player.defend.pop().is_none() {
@@ -583,6 +945,14 @@ impl World {
         self.spawn_rocket(character_coord, target);
     }
 
+    pub fn npc_spit(&mut self, character: Entity, target: Coord) {
+        let character_coord = self.spatial_table.coord_of(character).unwrap();
+        if character_coord == target {
+            return;
+        }
+        self.spawn_sludge_spit(character_coord, target);
+    }
+
     pub fn projectile_stop<R: Rng>(
         &mut self,
         projectile_entity: Entity,
@@ -612,6 +982,13 @@ impl World {
                         self.entity_allocator.free(projectile_entity);
                         self.realtime_components.remove_entity(projectile_entity);
                     }
+                    OnCollision::Sludge => {
+                        self.change_floor_to_sludge(current_coord);
+                        self.spatial_table.remove(projectile_entity);
+                        self.components.remove_entity(projectile_entity);
+                        self.entity_allocator.free(projectile_entity);
+                        self.realtime_components.remove_entity(projectile_entity);
+                    }
                     OnCollision::RemoveRealtime => {
                         self.realtime_components.remove_entity(projectile_entity);
                         self.components.realtime.remove(projectile_entity);
@@ -640,6 +1017,13 @@ impl World {
                 .unwrap_or_default();
             if let Some(&spatial_cell) = self.spatial_table.layers_at(next_coord) {
                 if let Some(character_entity) = spatial_cell.character {
+                    if let Some(OnCollision::Sludge) =
+                        self.components.on_collision.get(projectile_entity)
+                    {
+                        if self.components.player.contains(character_entity) {
+                            self.apply_defend(character_entity, rng);
+                        }
+                    }
                     if let Some(&projectile_damage) =
                         self.components.projectile_damage.get(projectile_entity)
                     {
@@ -728,6 +1112,10 @@ impl World {
                     if let Some(spawn_coord) = spawn_coord {
                         let mut new_entity_data = self.components.clone_entity_data(entity);
                         new_entity_data.next_action = None;
+                        new_entity_data
+                            .status_effects
+                            .get_or_insert_with(Default::default)
+                            .apply(StatusEffect::Slowed, 2);
                         self.insert_entity_data(
                             Location {
                                 coord: spawn_coord,
@@ -763,12 +1151,128 @@ impl World {
         }
     }
 
+    fn is_sludge_at_coord(&self, coord: Coord) -> bool {
+        self.spatial_table
+            .layers_at(coord)
+            .and_then(|cell| cell.floor)
+            .map(|floor| self.components.sludge.contains(floor))
+            .unwrap_or(false)
+    }
+
+    pub fn boss_summon<R: Rng>(&mut self, boss: Entity, rng: &mut R) {
+        const RANGE: u32 = 10;
+        const NUM_SLIMES: usize = 2;
+        let boss_coord = if let Some(coord) = self.spatial_table.coord_of(boss) {
+            coord
+        } else {
+            return;
+        };
+        let spawn_coords = CoordIter::new(self.size())
+            .filter(|&coord| {
+                if coord.manhattan_distance(boss_coord) > RANGE {
+                    return false;
+                }
+                let cell = self.spatial_table.layers_at_checked(coord);
+                cell.floor.is_some()
+                    && cell.feature.is_none()
+                    && cell.character.is_none()
+                    && !self.is_sludge_at_coord(coord)
+                    && CardinalDirection::all()
+                        .any(|direction| self.is_sludge_at_coord(coord + direction.coord()))
+            })
+            .choose_multiple(rng, NUM_SLIMES);
+        for coord in spawn_coords {
+            match rng.gen_range(0..3) {
+                0 => {
+                    self.spawn_slime_goo(coord, rng);
+                }
+                1 => {
+                    self.spawn_slime_divide(coord, rng);
+                }
+                _ => {
+                    self.spawn_slime_teleport(coord, rng);
+                }
+            }
+        }
+    }
+
+    pub fn boss_flood<R: Rng>(&mut self, boss: Entity, rng: &mut R) {
+        const RADIUS_SQUARED: u32 = 5;
+        let boss_coord = if let Some(coord) = self.spatial_table.coord_of(boss) {
+            coord
+        } else {
+            return;
+        };
+        let flood_coords = CoordIter::new(self.size())
+            .filter(|&coord| {
+                if coord.distance2(boss_coord) > RADIUS_SQUARED {
+                    return false;
+                }
+                let cell = self.spatial_table.layers_at_checked(coord);
+                if cell.floor.is_none() || self.is_sludge_at_coord(coord) {
+                    return false;
+                }
+                if let Some(feature) = cell.feature {
+                    if self.components.stairs.contains(feature)
+                        || (self.components.solid.contains(feature)
+                            && !self.components.door_state.contains(feature))
+                    {
+                        return false;
+                    }
+                }
+                true
+            })
+            .collect::<Vec<_>>();
+        for coord in flood_coords {
+            self.change_floor_to_sludge(coord);
+            if let Some(character) = self.spatial_table.layers_at_checked(coord).character {
+                if self.components.player.contains(character) {
+                    self.apply_defend(character, rng);
+                }
+            }
+        }
+    }
+
+    pub fn boss_charge<R: Rng>(&mut self, boss: Entity, direction: CardinalDirection, rng: &mut R) {
+        const DISTANCE: u32 = 10;
+        let mut coord = if let Some(coord) = self.spatial_table.coord_of(boss) {
+            coord
+        } else {
+            return;
+        };
+        for _ in 0..DISTANCE {
+            let next_coord = coord + direction.coord();
+            if !self.can_npc_traverse_feature_at_coord(next_coord) {
+                break;
+            }
+            if self.is_solid_feature_at_coord(next_coord) {
+                break;
+            }
+            if let Some(occupant) = self.get_character_at_coord(next_coord) {
+                if self.components.player.contains(occupant) {
+                    self.npc_melee_attack(boss, occupant, rng);
+                    self.character_push_in_direction(occupant, direction.direction());
+                }
+                break;
+            }
+            if self.spatial_table.update_coord(boss, next_coord).is_err() {
+                break;
+            }
+            coord = next_coord;
+        }
+    }
+
     pub fn damage_character<R: Rng>(
         &mut self,
         character: Entity,
         hit_points_to_lose: u32,
         rng: &mut R,
     ) {
+        let hit_points_to_lose = if self.has_status_effect(character, StatusEffect::Marked) {
+            hit_points_to_lose * 2
+        } else {
+            hit_points_to_lose
+        };
         if let Some(hit_points) = self.components.hit_points.get_mut(character) {
             let coord = self.spatial_table.coord_of(character).unwrap();
             let dies = match hit_points.current.checked_sub(hit_points_to_lose) {
@@ -787,6 +1291,7 @@ impl World {
                         if let Some(coord) = self.spatial_table.coord_of(character) {
                             self.change_floor_to_sludge(coord);
                         }
+                        self.apply_status_effect(character, StatusEffect::SludgeCoated, 4);
                     }
                     OnDamage::Divide => self.divide(character, rng),
                     OnDamage::DivideAndSpawn => self.divide_and_spawn(character, rng),
@@ -822,6 +1327,11 @@ impl World {
                                                 },
                                             )
                                             .unwrap();
+                                        self.apply_status_effect(
+                                            player_entity,
+                                            StatusEffect::Stunned,
+                                            2,
+                                        );
                                     }
                                 }
                             }
@@ -838,11 +1348,11 @@ impl World {
                             match ability_target {
                                 Attack => {
                                     let _ = player.attack.insert_random(
-                                        player::choose_attack_upgrade(*level, rng),
+                                        player::choose_attack_upgrade(*level, &self.unlocks, rng),
                                         rng,
                                     );
                                     let _ = player.attack.insert_random(
-                                        player::choose_attack_upgrade(*level, rng),
+                                        player::choose_attack_upgrade(*level, &self.unlocks, rng),
                                         rng,
                                     );
                                 }
@@ -854,7 +1364,7 @@ impl World {
                                 }
                                 Tech => {
                                     let _ = player.tech.insert_random(
-                                        player::choose_tech_upgrade(*level, rng),
+                                        player::choose_tech_upgrade(*level, &self.unlocks, rng),
                                         rng,
                                     );
                                 }
@@ -915,6 +1425,19 @@ impl World {
 
     fn character_die<R: Rng>(&mut self, character: Entity, rng: &mut R) {
         self.components.to_remove.insert(character, ());
+        let is_hostile = self
+            .components
+            .npc
+            .get(character)
+            .map(|npc| npc.disposition == Disposition::Hostile)
+            .unwrap_or(false);
+        if is_hostile {
+            let maybe_player_entity = self.components.player.entities().next();
+            if let Some(player_entity) = maybe_player_entity {
+                let player = self.components.player.get_mut(player_entity).unwrap();
+                player.credits += CREDITS_PER_KILL;
+            }
+        }
         if let Some(drop_item_on_death) = self.components.drop_item_on_death.get(character) {
             if let Some(coord) = self.spatial_table.coord_of(character) {
                 if let Some(cell) = self.spatial_table.layers_at(coord) {
@@ -1007,8 +1530,73 @@ impl World {
         self.components.remove_entity(projectile_entity);
     }
 
+    /// Each time a character steps off a bridge it corrodes a little, eventually collapsing
+    /// into the sludge below
+    fn corrode_bridge(&mut self, coord: Coord) {
+        let bridge = if let Some(floor) = self.spatial_table.layers_at_checked(coord).floor {
+            if self.components.tile.get(floor) == Some(&Tile::Bridge) {
+                floor
+            } else {
+                return;
+            }
+        } else {
+            return;
+        };
+        let crossings = self
+            .components
+            .bridge_crossings
+            .get(bridge)
+            .cloned()
+            .unwrap_or(0)
+            + 1;
+        if crossings >= BRIDGE_MAX_CROSSINGS {
+            self.change_floor_to_sludge(coord);
+        } else {
+            self.components.bridge_crossings.insert(bridge, crossings);
+        }
+    }
+
+    pub fn spread_sludge<R: Rng>(&mut self, rng: &mut R) {
+        const CELLS_PER_SPREAD: usize = 2;
+        if let Some(sludge_spread) = self.sludge_spread.as_mut() {
+            if sludge_spread.turns_until_spread > 1 {
+                sludge_spread.turns_until_spread -= 1;
+                return;
+            }
+            sludge_spread.turns_until_spread = sludge_spread.turns_per_spread;
+        } else {
+            return;
+        }
+        let spread_coords = CoordIter::new(self.size())
+            .filter(|&coord| {
+                let cell = self.spatial_table.layers_at_checked(coord);
+                let is_plain_floor = cell
+                    .floor
+                    .map(|floor| self.components.tile.get(floor) == Some(&Tile::Floor))
+                    .unwrap_or(false);
+                let is_blocked = cell
+                    .feature
+                    .map(|feature| {
+                        self.components.stairs.contains(feature)
+                            || self.components.door_state.contains(feature)
+                    })
+                    .unwrap_or(false);
+                is_plain_floor
+                    && !is_blocked
+                    && cell.character.is_none()
+                    && CardinalDirection::all()
+                        .any(|direction| self.is_sludge_at_coord(coord + direction.coord()))
+            })
+            .choose_multiple(rng, CELLS_PER_SPREAD);
+        for coord in spread_coords {
+            self.change_floor_to_sludge(coord);
+        }
+    }
+
     pub fn sludge_damage<R: Rng>(&mut self, rng: &mut R) {
         const DAMAGE: u32 = 4;
+        // sludge only hurts npcs, so it's on the player's side
+        let damage = self.difficulty.scale_supplies(DAMAGE);
         for entity in self
             .components
             .npc
@@ -1030,7 +1618,7 @@ impl World {
             })
             .collect::<Vec<_>>()
         {
-            self.damage_character(entity, DAMAGE, rng);
+            self.damage_character(entity, damage, rng);
         }
     }
 }
@@ -1,11 +1,12 @@
 use crate::visibility::Light;
 pub use crate::world::{
     explosion_spec,
-    player::{AbilityTarget, Player},
+    player::{AbilityTarget, Outcome, Player},
     spatial::{Layer, Location},
 };
 use direction::CardinalDirection;
 use entity_table::declare_entity_module;
+use grid_2d::Coord;
 use rgb24::Rgb24;
 use serde::{Deserialize, Serialize};
 
@@ -36,7 +37,13 @@ declare_entity_module! {
         on_damage: OnDamage,
         move_half_speed: MoveHalfSpeed,
         item: Item,
+        revealed_card: Outcome,
+        status_effects: StatusEffects,
         drop_item_on_death: DropItemOnDeath,
+        human: (),
+        spitter: (),
+        key: (),
+        bridge_crossings: u32,
     }
 }
 pub use components::Components;
@@ -49,6 +56,7 @@ pub enum Tile {
     Floor,
     DoorClosed,
     DoorOpen,
+    DoorLocked,
     Stairs,
     Sludge0,
     Sludge1,
@@ -58,13 +66,20 @@ pub enum Tile {
     SlimeSwap,
     SlimeGoo,
     SlimeCurse,
+    SlimeSpitter,
     SlimeAttackUpgrade,
     SlimeDefendUpgrade,
     SlimeTechUpgrade,
     SlimeBoss,
+    Human,
+    FormerHuman,
+    Bullet,
+    Rocket,
+    SludgeSpit,
     AttackItem { special: bool },
     DefendItem { special: bool },
     TechItem { special: bool },
+    Key,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -78,6 +93,7 @@ pub enum Item {
 pub enum Disposition {
     Hostile,
     Afraid,
+    Friendly,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -90,6 +106,7 @@ pub enum OnCollision {
     Explode(explosion_spec::Explosion),
     Remove,
     RemoveRealtime,
+    Sludge,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -129,12 +146,22 @@ impl HitPoints {
 pub enum DoorState {
     Open,
     Closed,
+    /// Can only be opened by a player carrying a key. Npcs can't get through at all.
+    Locked,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NpcAction {
     Walk(CardinalDirection),
     Wait,
+    /// Raise slimes out of nearby sludge pools
+    Summon,
+    /// Turn the floor around the actor into sludge
+    Flood,
+    /// Rush in a straight line until something gets in the way
+    Charge(CardinalDirection),
+    /// Spit a projectile at the given coordinate
+    Shoot(Coord),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -161,3 +188,78 @@ pub enum DropItemOnDeath {
     GuaranteeSpecial,
     RandomNormal,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusEffect {
+    /// Only moves every other turn
+    Slowed,
+    /// Loses its turns
+    Stunned,
+    /// Loses hit points every turn
+    Poisoned,
+    /// Can attack but can't move
+    Rooted,
+    /// Leaves a trail of sludge behind it
+    SludgeCoated,
+    /// Takes double damage
+    Marked,
+}
+
+/// Status effects applied to a character, each with the number of turns it has remaining
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusEffects {
+    effects: Vec<(StatusEffect, u32)>,
+}
+
+impl StatusEffects {
+    pub fn apply(&mut self, effect: StatusEffect, turns: u32) {
+        if let Some((_, remaining)) = self.effects.iter_mut().find(|(e, _)| *e == effect) {
+            *remaining = (*remaining).max(turns);
+        } else {
+            self.effects.push((effect, turns));
+        }
+    }
+    pub fn turns_remaining(&self, effect: StatusEffect) -> Option<u32> {
+        self.effects
+            .iter()
+            .find(|(e, _)| *e == effect)
+            .map(|&(_, remaining)| remaining)
+    }
+    pub fn contains(&self, effect: StatusEffect) -> bool {
+        self.turns_remaining(effect).is_some()
+    }
+    pub fn iter(&self) -> impl '_ + Iterator<Item = (StatusEffect, u32)> {
+        self.effects.iter().cloned()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+    /// Counts down every effect by one turn, removing those which have expired
+    pub fn tick(&mut self) {
+        for (_, remaining) in self.effects.iter_mut() {
+            *remaining = remaining.saturating_sub(1);
+        }
+        self.effects.retain(|&(_, remaining)| remaining > 0);
+    }
+}
+
+/// Sludge pools creep onto neighbouring floor tiles every few turns
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SludgeSpread {
+    pub turns_per_spread: u32,
+    pub turns_until_spread: u32,
+}
+
+impl SludgeSpread {
+    /// Sludge spreads faster the deeper the floor. It doesn't spread at all on the first floor.
+    pub fn for_level(level: u32) -> Option<Self> {
+        if level == 0 {
+            return None;
+        }
+        let turns_per_spread = 12u32.saturating_sub(level * 2).max(4);
+        Some(Self {
+            turns_per_spread,
+            turns_until_spread: turns_per_spread,
+        })
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{terrain, visibility::Light, ExternalEvent};
+use crate::{
+    achievement::Milestone,
+    terrain::{self, GameMode},
+    unlock::Unlocks,
+    visibility::Light,
+    Difficulty, ExternalEvent,
+};
 use entity_table::{Entity, EntityAllocator};
 use grid_2d::{Coord, Size};
 use rand::{
@@ -16,7 +22,10 @@ pub mod player;
 
 mod data;
 use data::{Components, Npc};
-pub use data::{Disposition, EntityData, HitPoints, Layer, Location, NpcAction, Tile};
+pub use data::{
+    Disposition, EntityData, HitPoints, Layer, Location, NpcAction, SludgeSpread, StatusEffect,
+    StatusEffects, Tile,
+};
 
 mod realtime_periodic;
 pub use realtime_periodic::animation::{
@@ -42,10 +51,19 @@ pub struct World {
     pub components: Components,
     pub realtime_components: RealtimeComponents,
     pub spatial_table: SpatialTable,
+    pub pending_pickup: Option<Entity>,
+    pub sludge_spread: Option<SludgeSpread>,
+    pub difficulty: Difficulty,
+    pub mode: GameMode,
+    /// Drained by the game after each turn to track achievements
+    pub milestones: Vec<Milestone>,
+    /// Set by the game after the world is generated, since unlocks only affect cards and
+    /// abilities chosen during play
+    pub unlocks: Unlocks,
 }
 
 impl World {
-    pub fn new(size: Size, level: u32) -> Self {
+    pub fn new(size: Size, level: u32, difficulty: Difficulty, mode: GameMode) -> Self {
         let entity_allocator = EntityAllocator::default();
         let components = Components::default();
         let realtime_components = RealtimeComponents::default();
@@ -56,6 +74,12 @@ impl World {
             realtime_components,
             spatial_table,
             level,
+            pending_pickup: None,
+            sludge_spread: SludgeSpread::for_level(level),
+            difficulty,
+            mode,
+            milestones: Vec::new(),
+            unlocks: Unlocks::default(),
         }
     }
 }
@@ -70,6 +94,8 @@ impl World {
         let ignore_lighting_component = &self.components.ignore_lighting;
         let hit_points = &self.components.hit_points;
         let next_action = &self.components.next_action;
+        let status_effects = &self.components.status_effects;
+        let revealed_card = &self.components.revealed_card;
         tile_component.iter().filter_map(move |(entity, &tile)| {
             if let Some(location) = spatial_table.location_of(entity) {
                 let fade = realtime_fade_component
@@ -80,6 +106,8 @@ impl World {
                 let ignore_lighting = ignore_lighting_component.contains(entity);
                 let hit_points = hit_points.get(entity).cloned();
                 let next_action = next_action.get(entity).cloned();
+                let status_effects = status_effects.get(entity).cloned();
+                let revealed_card = revealed_card.get(entity).cloned();
                 Some(ToRenderEntity {
                     coord: location.coord,
                     layer: location.layer,
@@ -90,6 +118,8 @@ impl World {
                     ignore_lighting,
                     hit_points,
                     next_action,
+                    status_effects,
+                    revealed_card,
                 })
             } else {
                 None
@@ -143,6 +173,9 @@ impl World {
     pub fn entity_player(&self, entity: Entity) -> Option<&player::Player> {
         self.components.player.get(entity)
     }
+    pub fn entity_player_mut(&mut self, entity: Entity) -> Option<&mut player::Player> {
+        self.components.player.get_mut(entity)
+    }
     pub fn entity_npc(&self, entity: Entity) -> &Npc {
         self.components.npc.get(entity).unwrap()
     }
@@ -177,14 +210,16 @@ impl World {
         let current_abilities = player.ability.iter().cloned().collect::<HashSet<_>>();
         let mut choices = player::Ability::all()
             .iter()
-            .filter(|a| !current_abilities.contains(a))
+            .filter(|a| !current_abilities.contains(a) && self.unlocks.allows_ability(**a))
             .cloned()
             .choose_multiple(rng, 3);
         choices.shuffle(rng);
         choices
     }
     pub fn is_won(&self) -> bool {
-        self.level == terrain::FINAL_LEVEL && self.components.npc.is_empty()
+        self.mode == GameMode::Campaign
+            && self.level == terrain::FINAL_LEVEL
+            && self.components.npc.is_empty()
     }
 }
 
@@ -198,6 +233,8 @@ pub struct ToRenderEntity {
     pub ignore_lighting: bool,
     pub hit_points: Option<HitPoints>,
     pub next_action: Option<NpcAction>,
+    pub status_effects: Option<StatusEffects>,
+    pub revealed_card: Option<player::Outcome>,
 }
 
 #[derive(Serialize, Deserialize)]
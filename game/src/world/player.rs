@@ -1,4 +1,11 @@
-use rand::{seq::SliceRandom, Rng};
+use crate::{
+    unlock::{Unlock, Unlocks},
+    Difficulty, Loadout,
+};
+use rand::{
+    seq::{IteratorRandom, SliceRandom},
+    Rng,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -6,11 +13,37 @@ pub enum Attack {
     Hit(u32),
     Cleave(u32),
     Skewer(u32),
+    Poison(u32),
     Miss,
 }
 
 pub const EMPTY_ATTACK: Attack = Attack::Hit(4);
 
+impl Attack {
+    /// The card an attack becomes when it's transferred to the defend stack
+    pub fn to_defend(self) -> Defend {
+        match self {
+            Attack::Hit(n) | Attack::Cleave(n) | Attack::Skewer(n) | Attack::Poison(n) => {
+                Defend::Armour((n / 5).max(1))
+            }
+            Attack::Miss => Defend::SkipAttack,
+        }
+    }
+    pub fn is_curse(self) -> bool {
+        self == Attack::Miss
+    }
+    /// The stronger version of this card bought in the safehouse
+    pub fn upgrade(self) -> Option<Attack> {
+        match self {
+            Attack::Hit(n) => Some(Attack::Hit(n + (n / 4).max(2))),
+            Attack::Cleave(n) => Some(Attack::Cleave(n + (n / 4).max(2))),
+            Attack::Skewer(n) => Some(Attack::Skewer(n + (n / 4).max(2))),
+            Attack::Poison(n) => Some(Attack::Poison(n + (n / 4).max(1))),
+            Attack::Miss => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Defend {
     Armour(u32),
@@ -20,6 +53,29 @@ pub enum Defend {
     SkipAttack,
 }
 
+impl Defend {
+    /// The card a defend becomes when it's transferred to the attack stack
+    pub fn to_attack(self) -> Attack {
+        match self {
+            Defend::Armour(n) => Attack::Hit(n * 5),
+            Defend::Dodge => Attack::Hit(5),
+            Defend::Teleport => Attack::Skewer(5),
+            Defend::Revenge => Attack::Cleave(10),
+            Defend::SkipAttack => Attack::Miss,
+        }
+    }
+    pub fn is_curse(self) -> bool {
+        self == Defend::SkipAttack
+    }
+    /// The stronger version of this card bought in the safehouse
+    pub fn upgrade(self) -> Option<Defend> {
+        match self {
+            Defend::Armour(n) => Some(Defend::Armour(n + 1)),
+            Defend::Dodge | Defend::Teleport | Defend::Revenge | Defend::SkipAttack => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Tech {
     Blink,
@@ -29,8 +85,16 @@ pub enum Tech {
     MissNext,
     TeleportNext,
     Skip,
+    Shotgun,
+    Rocket,
+    Stun,
+    Root,
+    Mark,
 }
 
+/// The number of cards highlighted by `Ability::Peek`
+pub const PEEK_COUNT: usize = 3;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AbilityTarget {
     Attack,
@@ -43,6 +107,19 @@ pub enum Ability {
     Stash(AbilityTarget),
     SwapTop2(AbilityTarget),
     Discard(AbilityTarget),
+    /// Reveal the top `PEEK_COUNT` cards of a deck until the next action
+    Peek(AbilityTarget),
+    /// Move the top card to a chosen depth
+    Bury(AbilityTarget),
+    Shuffle(AbilityTarget),
+    /// Copy the top card, losing the bottom card to make room
+    DuplicateTop(AbilityTarget),
+    /// Convert the top attack into a defend
+    TransferToDefend,
+    /// Convert the top defend into an attack
+    TransferToAttack,
+    /// Combine the top two attacks into one if they are both hits
+    MergeTop2,
 }
 
 impl Ability {
@@ -57,11 +134,29 @@ impl Ability {
             Ability::Discard(AbilityTarget::Attack),
             Ability::Discard(AbilityTarget::Defend),
             Ability::Discard(AbilityTarget::Tech),
+            Ability::Peek(AbilityTarget::Attack),
+            Ability::Peek(AbilityTarget::Defend),
+            Ability::Peek(AbilityTarget::Tech),
+            Ability::Bury(AbilityTarget::Attack),
+            Ability::Bury(AbilityTarget::Defend),
+            Ability::Bury(AbilityTarget::Tech),
+            Ability::Shuffle(AbilityTarget::Attack),
+            Ability::Shuffle(AbilityTarget::Defend),
+            Ability::Shuffle(AbilityTarget::Tech),
+            Ability::DuplicateTop(AbilityTarget::Attack),
+            Ability::DuplicateTop(AbilityTarget::Defend),
+            Ability::DuplicateTop(AbilityTarget::Tech),
+            Ability::TransferToDefend,
+            Ability::TransferToAttack,
+            Ability::MergeTop2,
         ]
     }
 }
 
 impl Tech {
+    pub fn is_curse(self) -> bool {
+        self == Tech::MissNext
+    }
     pub fn requires_aim(self) -> bool {
         use Tech::*;
         match self {
@@ -72,6 +167,11 @@ impl Tech {
             MissNext => false,
             TeleportNext => false,
             Skip => false,
+            Shotgun => true,
+            Rocket => true,
+            Stun => false,
+            Root => false,
+            Mark => false,
         }
     }
 }
@@ -104,6 +204,9 @@ impl<T> Deck<T> {
     pub fn peek(&self) -> Option<&T> {
         self.items.last()
     }
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        self.items.last_mut()
+    }
     pub fn push(&mut self, item: T) -> Result<(), DeckIsFull> {
         if self.items.len() < self.max_size {
             self.items.push(item);
@@ -129,6 +232,48 @@ impl<T> Deck<T> {
         self.items.insert(0, top);
         Ok(())
     }
+    /// Removes the card at `index_from_top` (0 is the top card) and puts `item` on top
+    pub fn replace(&mut self, index_from_top: usize, item: T) -> Option<T> {
+        let index = self.items.len().checked_sub(index_from_top + 1)?;
+        let removed = self.items.remove(index);
+        self.items.push(item);
+        Some(removed)
+    }
+    /// Moves the top card so that `depth` cards end up above it
+    pub fn bury(&mut self, depth: usize) -> Result<(), NotEnoughCards> {
+        if depth == 0 || depth >= self.items.len() {
+            return Err(NotEnoughCards);
+        }
+        let top = self.items.pop().unwrap();
+        let index = self.items.len() - depth;
+        self.items.insert(index, top);
+        Ok(())
+    }
+    pub fn shuffle<R: Rng>(&mut self, rng: &mut R) -> Result<(), NotEnoughCards> {
+        if self.items.len() < 2 {
+            return Err(NotEnoughCards);
+        }
+        self.items.shuffle(rng);
+        Ok(())
+    }
+    /// Puts a copy of the top card on top, removing the bottom card
+    pub fn duplicate_top(&mut self) -> Result<(), NotEnoughCards>
+    where
+        T: Clone,
+    {
+        if self.items.len() < 2 {
+            return Err(NotEnoughCards);
+        }
+        self.items.remove(0);
+        let top = self.items.last().cloned().unwrap();
+        self.items.push(top);
+        Ok(())
+    }
+    /// Removes the card nearest the top for which `f` returns true
+    pub fn remove_nearest_top<F: FnMut(&T) -> bool>(&mut self, f: F) -> Option<T> {
+        let index = self.items.iter().rposition(f)?;
+        Some(self.items.remove(index))
+    }
     pub fn insert_random<R: Rng>(&mut self, item: T, rng: &mut R) -> Result<(), DeckIsFull> {
         if self.items.len() == self.max_size {
             return Err(DeckIsFull);
@@ -174,6 +319,12 @@ pub struct Player {
     pub defend: Deck<Defend>,
     pub tech: Deck<Tech>,
     pub ability: AbilityTable,
+    pub keys: u32,
+    /// Currency earned from kills and spent in the safehouse between floors
+    pub credits: u32,
+    /// The deck whose top cards are revealed by a peek
+    #[serde(default)]
+    pub peeking: Option<AbilityTarget>,
 }
 
 fn rev<T>(mut vec: Vec<T>) -> Vec<T> {
@@ -186,86 +337,117 @@ fn shuf<T, R: Rng>(mut vec: Vec<T>, rng: &mut R) -> Vec<T> {
     vec
 }
 
+/// Removes cards from the end of `vec`, or repeats its cards from the start, until it has `len`
+/// cards
+fn resize<T: Clone>(mut vec: Vec<T>, len: usize) -> Vec<T> {
+    let mut i = 0;
+    while vec.len() < len {
+        vec.push(vec[i].clone());
+        i += 1;
+    }
+    vec.truncate(len);
+    vec
+}
+
 impl Player {
-    pub fn new<R: Rng>(rng: &mut R) -> Self {
-        use Ability::*;
-        use Attack::*;
-        use Defend::*;
-        use Tech::*;
+    /// Starting abilities which haven't been unlocked yet are left out
+    pub fn new<R: Rng>(
+        difficulty: Difficulty,
+        loadout: Loadout,
+        unlocks: &Unlocks,
+        rng: &mut R,
+    ) -> Self {
+        let spec = loadout.spec();
+        let starting_size = |base: usize| difficulty.scale_supplies(base as u32) as usize;
+        let attack_max_size = difficulty.deck_max_size(spec.attack_max_size);
+        let defend_max_size = difficulty.deck_max_size(spec.defend_max_size);
+        let tech_max_size = difficulty.deck_max_size(spec.tech_max_size);
+        let attack = spec
+            .attack
+            .iter()
+            .map(|card| card.roll(rng))
+            .collect::<Vec<_>>();
+        let defend = spec
+            .defend
+            .iter()
+            .map(|card| card.roll(rng))
+            .collect::<Vec<_>>();
         Self {
             attack: Deck {
-                #[rustfmt::skip]
-                items: rev(vec![
-                    Hit(rng.gen_range(4..8)),
-                    Hit(rng.gen_range(4..8)),
-                    Hit(rng.gen_range(4..8)),
-                    Cleave(rng.gen_range(3..7)),
-                    Hit(rng.gen_range(6..12)),
-                    Hit(rng.gen_range(6..12)),
-                    Hit(rng.gen_range(8..20)),
-                    Hit(rng.gen_range(8..20)),
-                ]),
-                max_size: 16,
+                items: rev(resize(
+                    attack,
+                    starting_size(spec.attack.len()).min(attack_max_size),
+                )),
+                max_size: attack_max_size,
             },
             defend: Deck {
-                #[rustfmt::skip]
-                items: rev(vec![
-                    Armour(rng.gen_range(1..2)),
-                    Armour(rng.gen_range(1..2)),
-                    Armour(rng.gen_range(1..2)),
-                    Armour(rng.gen_range(1..3)),
-                    Armour(rng.gen_range(1..3)),
-                    Armour(rng.gen_range(1..3)),
-                    Teleport,
-                    Armour(rng.gen_range(2..5)),
-                    Armour(rng.gen_range(2..5)),
-                ]),
-                max_size: 16,
+                items: rev(resize(
+                    defend,
+                    starting_size(spec.defend.len()).min(defend_max_size),
+                )),
+                max_size: defend_max_size,
             },
             tech: Deck {
-                #[rustfmt::skip]
-                items: shuf(vec![
-                   Attract,
-                    Repel,
-                    Repel,
-                    Blink,
-                    Blink,
-                    Blink,
-                ], rng),
-                max_size: 8,
+                items: shuf(
+                    resize(
+                        spec.tech.to_vec(),
+                        starting_size(spec.tech.len()).min(tech_max_size),
+                    ),
+                    rng,
+                ),
+                max_size: tech_max_size,
             },
             ability: AbilityTable {
-                #[rustfmt::skip]
-                abilities: vec![
-                    Stash(AbilityTarget::Attack),
-                    Stash(AbilityTarget::Defend),
-                ],
+                abilities: spec
+                    .abilities
+                    .iter()
+                    .cloned()
+                    .filter(|&ability| unlocks.allows_ability(ability))
+                    .collect(),
                 max_size: 8,
             },
+            keys: 0,
+            credits: 0,
+            peeking: None,
+        }
+    }
+    pub fn deck_len(&self, target: AbilityTarget) -> usize {
+        match target {
+            AbilityTarget::Attack => self.attack.len(),
+            AbilityTarget::Defend => self.defend.len(),
+            AbilityTarget::Tech => self.tech.len(),
         }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Outcome {
     Attack(Attack),
     Defend(Defend),
     Tech(Tech),
 }
 
-pub fn choose_attack<R: Rng>(level: u32, special: bool, rng: &mut R) -> Attack {
+pub fn choose_attack<R: Rng>(level: u32, special: bool, unlocks: &Unlocks, rng: &mut R) -> Attack {
+    // poison is the last kind of attack, and is only included once unlocked
+    let num_kinds = if unlocks.contains(Unlock::PoisonCards) {
+        4
+    } else {
+        3
+    };
     if special {
-        match rng.gen_range(0..3) {
+        match rng.gen_range(0..num_kinds) {
             0 => Attack::Hit(rng.gen_range(10 + level * 2..20 + level * 8)),
             1 => Attack::Cleave(rng.gen_range(10 + level * 2..20 + level * 3)),
             2 => Attack::Skewer(rng.gen_range(10 + level * 2..20 + level * 3)),
+            3 => Attack::Poison(rng.gen_range(5 + level..10 + level * 2)),
             _ => unreachable!(),
         }
     } else {
-        match rng.gen_range(0..3) {
+        match rng.gen_range(0..num_kinds) {
             0 => Attack::Hit(rng.gen_range(5 + level * 2..10 + level * 8)),
             1 => Attack::Cleave(rng.gen_range(5 + level * 2..10 + level * 3)),
             2 => Attack::Skewer(rng.gen_range(5 + level * 2..10 + level * 3)),
+            3 => Attack::Poison(rng.gen_range(2 + level..5 + level * 2)),
             _ => unreachable!(),
         }
     }
@@ -284,9 +466,39 @@ pub fn choose_defend<R: Rng>(level: u32, special: bool, rng: &mut R) -> Defend {
     }
 }
 
-pub fn choose_tech<R: Rng>(_level: u32, special: bool, rng: &mut R) -> Tech {
+/// Floor from which ranged weapons start appearing as tech cards
+pub const RANGED_TECH_MIN_LEVEL: u32 = 3;
+
+pub fn choose_tech<R: Rng>(level: u32, special: bool, unlocks: &Unlocks, rng: &mut R) -> Tech {
     if special {
-        Tech::Blink
+        if level >= RANGED_TECH_MIN_LEVEL {
+            (&[Tech::Blink, Tech::Shotgun, Tech::Rocket])
+                .iter()
+                .filter(|&&tech| unlocks.allows_tech(tech))
+                .choose(rng)
+                .unwrap()
+                .clone()
+        } else {
+            Tech::Blink
+        }
+    } else if level >= RANGED_TECH_MIN_LEVEL {
+        (&[
+            Tech::Blink,
+            Tech::Blink,
+            Tech::Blink,
+            Tech::Repel,
+            Tech::Repel,
+            Tech::Attract,
+            Tech::Stun,
+            Tech::Root,
+            Tech::Mark,
+            Tech::Shotgun,
+        ])
+            .iter()
+            .filter(|&&tech| unlocks.allows_tech(tech))
+            .choose(rng)
+            .unwrap()
+            .clone()
     } else {
         (&[
             Tech::Blink,
@@ -295,6 +507,9 @@ pub fn choose_tech<R: Rng>(_level: u32, special: bool, rng: &mut R) -> Tech {
             Tech::Repel,
             Tech::Repel,
             Tech::Attract,
+            Tech::Stun,
+            Tech::Root,
+            Tech::Mark,
         ])
             .choose(rng)
             .unwrap()
@@ -302,11 +517,13 @@ pub fn choose_tech<R: Rng>(_level: u32, special: bool, rng: &mut R) -> Tech {
     }
 }
 
-pub fn choose_attack_upgrade<R: Rng>(level: u32, rng: &mut R) -> Attack {
+pub fn choose_attack_upgrade<R: Rng>(level: u32, unlocks: &Unlocks, rng: &mut R) -> Attack {
     use Attack::*;
     match level {
-        _ => &[Hit(30), Hit(20), Cleave(10), Skewer(10)],
+        _ => &[Hit(30), Hit(20), Cleave(10), Skewer(10), Poison(10)],
     }
+    .iter()
+    .filter(|&&attack| unlocks.allows_attack(attack))
     .choose(rng)
     .unwrap()
     .clone()
@@ -322,11 +539,36 @@ pub fn choose_defend_upgrade<R: Rng>(level: u32, rng: &mut R) -> Defend {
     .clone()
 }
 
-pub fn choose_tech_upgrade<R: Rng>(level: u32, rng: &mut R) -> Tech {
+pub fn choose_tech_upgrade<R: Rng>(level: u32, unlocks: &Unlocks, rng: &mut R) -> Tech {
     use Tech::*;
     match level {
-        _ => &[Blink, CritNext, Attract, Repel, TeleportNext, Skip],
+        0..=2 => &[
+            Blink,
+            CritNext,
+            Attract,
+            Repel,
+            TeleportNext,
+            Skip,
+            Stun,
+            Root,
+            Mark,
+        ][..],
+        _ => &[
+            Blink,
+            CritNext,
+            Attract,
+            Repel,
+            TeleportNext,
+            Skip,
+            Stun,
+            Root,
+            Mark,
+            Shotgun,
+            Rocket,
+        ][..],
     }
+    .iter()
+    .filter(|&&tech| unlocks.allows_tech(tech))
     .choose(rng)
     .unwrap()
     .clone()
@@ -1,4 +1,7 @@
-use crate::world::{data::Tile, World};
+use crate::world::{
+    data::{Disposition, DoorState, StatusEffect, Tile},
+    World,
+};
 use entity_table::Entity;
 use grid_2d::Coord;
 use line_2d::LineSegment;
@@ -49,9 +52,12 @@ impl World {
     pub fn can_npc_traverse_feature_at_coord(&self, coord: Coord) -> bool {
         if let Some(spatial_cell) = self.spatial_table.layers_at(coord) {
             if let Some(feature) = spatial_cell.feature {
-                self.components.door_state.contains(feature)
-                    || !(self.components.solid.contains(feature)
+                if let Some(&door_state) = self.components.door_state.get(feature) {
+                    door_state != DoorState::Locked
+                } else {
+                    !(self.components.solid.contains(feature)
                         || self.components.stairs.contains(feature))
+                }
             } else {
                 true
             }
@@ -118,4 +124,49 @@ impl World {
                 }
             })
     }
+
+    pub fn has_status_effect(&self, entity: Entity, effect: StatusEffect) -> bool {
+        self.components
+            .status_effects
+            .get(entity)
+            .map(|status_effects| status_effects.contains(effect))
+            .unwrap_or(false)
+    }
+
+    pub fn is_human(&self, entity: Entity) -> bool {
+        self.components.human.contains(entity)
+    }
+
+    pub fn num_humans(&self) -> usize {
+        self.components
+            .human
+            .entities()
+            .filter(|&entity| self.entity_exists(entity))
+            .count()
+    }
+
+    pub fn num_hostile_npcs(&self) -> usize {
+        self.components
+            .npc
+            .iter()
+            .filter(|&(entity, npc)| {
+                self.entity_exists(entity) && npc.disposition == Disposition::Hostile
+            })
+            .count()
+    }
+
+    pub fn humans_within<'a>(
+        &'a self,
+        coord: Coord,
+        distance: u32,
+    ) -> impl 'a + Iterator<Item = Entity> {
+        self.components.human.entities().filter(move |&entity| {
+            self.entity_exists(entity)
+                && self
+                    .spatial_table
+                    .coord_of(entity)
+                    .map(|human_coord| human_coord.manhattan_distance(coord) <= distance)
+                    .unwrap_or(false)
+        })
+    }
 }
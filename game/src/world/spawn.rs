@@ -1,9 +1,10 @@
 use crate::{
+    terrain,
     visibility::Light,
     world::{
         data::{
             CollidesWith, Disposition, DoorState, DropItemOnDeath, EntityData, HitPoints, Item,
-            Layer, Location, MoveHalfSpeed, Npc, OnCollision, OnDamage, Tile,
+            Layer, Location, MoveHalfSpeed, Npc, OnCollision, OnDamage, ProjectileDamage, Tile,
         },
         explosion, player,
         realtime_periodic::{
@@ -13,6 +14,7 @@ use crate::{
         },
         World,
     },
+    Difficulty, Loadout, Unlocks,
 };
 use entity_table::Entity;
 use grid_2d::Coord;
@@ -22,11 +24,19 @@ use rgb24::Rgb24;
 use shadowcast::vision_distance::Circle;
 use std::time::Duration;
 
-pub fn make_player<R: Rng>(rng: &mut R) -> EntityData {
+/// Extra npc hit points for each floor descended past the final floor
+const ENDLESS_HIT_POINTS_PERCENT_PER_FLOOR: u32 = 20;
+
+pub fn make_player<R: Rng>(
+    difficulty: Difficulty,
+    loadout: Loadout,
+    unlocks: &Unlocks,
+    rng: &mut R,
+) -> EntityData {
     EntityData {
         tile: Some(Tile::Player),
         character: Some(()),
-        player: Some(player::Player::new(rng)),
+        player: Some(player::Player::new(difficulty, loadout, unlocks, rng)),
         light: Some(Light {
             colour: Rgb24::new(200, 187, 150),
             vision_distance: Circle::new_squared(60),
@@ -47,6 +57,13 @@ impl World {
         entity
     }
 
+    fn npc_hit_points(&self, base: u32) -> HitPoints {
+        // only reachable in endless mode
+        let floors_past_final = self.level.saturating_sub(terrain::FINAL_LEVEL);
+        let percent = 100 + floors_past_final * ENDLESS_HIT_POINTS_PERCENT_PER_FLOOR;
+        HitPoints::new_full(self.difficulty.scale_enemies(base) * percent / 100)
+    }
+
     pub fn spawn_wall(&mut self, coord: Coord) -> Entity {
         let entity = self.entity_allocator.alloc();
         self.spatial_table
@@ -91,17 +108,23 @@ impl World {
                 },
             )
             .unwrap();
+        self.components.character.insert(entity, ());
+        self.make_former_human(entity);
+        entity
+    }
+
+    pub(super) fn make_former_human(&mut self, entity: Entity) {
+        self.components.human.remove(entity);
+        self.components.tile.insert(entity, Tile::FormerHuman);
         self.components.npc.insert(
             entity,
             Npc {
                 disposition: Disposition::Hostile,
             },
         );
-        self.components.character.insert(entity, ());
         self.components
             .hit_points
             .insert(entity, HitPoints::new_full(2));
-        panic!("missing tile")
     }
 
     pub fn spawn_human(&mut self, coord: Coord) -> Entity {
@@ -115,17 +138,19 @@ impl World {
                 },
             )
             .unwrap();
+        self.components.tile.insert(entity, Tile::Human);
         self.components.npc.insert(
             entity,
             Npc {
-                disposition: Disposition::Afraid,
+                disposition: Disposition::Friendly,
             },
         );
         self.components.character.insert(entity, ());
+        self.components.human.insert(entity, ());
         self.components
             .hit_points
             .insert(entity, HitPoints::new_full(20));
-        panic!("missing tile")
+        entity
     }
 
     pub fn spawn_floor(&mut self, coord: Coord) -> Entity {
@@ -292,7 +317,15 @@ impl World {
                 character: true,
             },
         );
-        panic!("missing tiles")
+        self.components.projectile_damage.insert(
+            entity,
+            ProjectileDamage {
+                hit_points: 3,
+                push_back: false,
+            },
+        );
+        self.components.tile.insert(entity, Tile::Bullet);
+        entity
     }
 
     pub fn spawn_rocket(&mut self, start: Coord, target: Coord) -> Entity {
@@ -380,7 +413,58 @@ impl World {
                 character: true,
             },
         );
-        panic!("missing tiles")
+        self.components.tile.insert(entity, Tile::Rocket);
+        entity
+    }
+
+    pub fn spawn_sludge_spit(&mut self, start: Coord, target: Coord) -> Entity {
+        let entity = self.entity_allocator.alloc();
+        self.spatial_table
+            .update(
+                entity,
+                Location {
+                    coord: start,
+                    layer: None,
+                },
+            )
+            .unwrap();
+        self.components.realtime.insert(entity, ());
+        self.components.blocks_gameplay.insert(entity, ());
+        self.components
+            .on_collision
+            .insert(entity, OnCollision::Sludge);
+        self.realtime_components.movement.insert(
+            entity,
+            ScheduledRealtimePeriodicState {
+                state: movement::spec::Movement {
+                    path: target - start,
+                    cardinal_step_duration: Duration::from_millis(60),
+                    repeat: movement::spec::Repeat::Once,
+                }
+                .build(),
+                until_next_event: Duration::from_millis(0),
+            },
+        );
+        self.components.light.insert(
+            entity,
+            Light {
+                colour: Rgb24::new(0, 255, 0),
+                vision_distance: Circle::new_squared(20),
+                diminish: Rational {
+                    numerator: 1,
+                    denominator: 10,
+                },
+            },
+        );
+        self.components.collides_with.insert(
+            entity,
+            CollidesWith {
+                solid: true,
+                character: true,
+            },
+        );
+        self.components.tile.insert(entity, Tile::SludgeSpit);
+        entity
     }
 
     pub fn spawn_explosion_emitter(
@@ -474,7 +558,7 @@ impl World {
                 until_next_event: Duration::from_millis(0),
             },
         );
-        panic!("missing tiles")
+        emitter_entity
     }
 
     pub fn spawn_door(&mut self, coord: Coord) -> Entity {
@@ -495,6 +579,24 @@ impl World {
         entity
     }
 
+    pub fn spawn_locked_door(&mut self, coord: Coord) -> Entity {
+        let entity = self.entity_allocator.alloc();
+        self.spatial_table
+            .update(
+                entity,
+                Location {
+                    coord,
+                    layer: Some(Layer::Feature),
+                },
+            )
+            .unwrap();
+        self.components.tile.insert(entity, Tile::DoorLocked);
+        self.components.opacity.insert(entity, 255);
+        self.components.solid.insert(entity, ());
+        self.components.door_state.insert(entity, DoorState::Locked);
+        entity
+    }
+
     pub fn spawn_stairs(&mut self, coord: Coord) -> Entity {
         let entity = self.entity_allocator.alloc();
         self.spatial_table
@@ -635,7 +737,7 @@ impl World {
             .insert(entity, DropItemOnDeath::RandomNormal);
         self.components
             .hit_points
-            .insert(entity, HitPoints::new_full(rng.gen_range(20..40)));
+            .insert(entity, self.npc_hit_points(rng.gen_range(20..40)));
         entity
     }
 
@@ -664,7 +766,7 @@ impl World {
             .insert(entity, DropItemOnDeath::RandomNormal);
         self.components
             .hit_points
-            .insert(entity, HitPoints::new_full(rng.gen_range(10..20)));
+            .insert(entity, self.npc_hit_points(rng.gen_range(10..20)));
         entity
     }
 
@@ -693,7 +795,7 @@ impl World {
             .insert(entity, DropItemOnDeath::RandomNormal);
         self.components
             .hit_points
-            .insert(entity, HitPoints::new_full(rng.gen_range(2..8)));
+            .insert(entity, self.npc_hit_points(rng.gen_range(2..8)));
         entity
     }
 
@@ -723,7 +825,39 @@ impl World {
             .insert(entity, DropItemOnDeath::GuaranteeSpecial);
         self.components
             .hit_points
-            .insert(entity, HitPoints::new_full(rng.gen_range(8..16)));
+            .insert(entity, self.npc_hit_points(rng.gen_range(8..16)));
+        entity
+    }
+
+    pub fn spawn_slime_spitter<R: Rng>(&mut self, coord: Coord, rng: &mut R) -> Entity {
+        let entity = self.entity_allocator.alloc();
+        self.spatial_table
+            .update(
+                entity,
+                Location {
+                    coord,
+                    layer: Some(Layer::Character),
+                },
+            )
+            .unwrap();
+        self.components.tile.insert(entity, Tile::SlimeSpitter);
+        self.components.npc.insert(
+            entity,
+            Npc {
+                disposition: Disposition::Hostile,
+            },
+        );
+        self.components.character.insert(entity, ());
+        self.components.spitter.insert(entity, ());
+        self.components
+            .move_half_speed
+            .insert(entity, MoveHalfSpeed::default());
+        self.components
+            .drop_item_on_death
+            .insert(entity, DropItemOnDeath::RandomNormal);
+        self.components
+            .hit_points
+            .insert(entity, self.npc_hit_points(rng.gen_range(6..12)));
         entity
     }
 
@@ -752,7 +886,7 @@ impl World {
             .insert(entity, OnDamage::DivideAndSpawn);
         self.components
             .hit_points
-            .insert(entity, HitPoints::new_full(99));
+            .insert(entity, self.npc_hit_points(99));
         entity
     }
 
@@ -786,7 +920,7 @@ impl World {
         );
         self.components
             .hit_points
-            .insert(entity, HitPoints::new_full(12));
+            .insert(entity, self.npc_hit_points(12));
         self.components
             .move_half_speed
             .insert(entity, MoveHalfSpeed::default());
@@ -823,7 +957,7 @@ impl World {
         );
         self.components
             .hit_points
-            .insert(entity, HitPoints::new_full(12));
+            .insert(entity, self.npc_hit_points(12));
         self.components
             .move_half_speed
             .insert(entity, MoveHalfSpeed::default());
@@ -858,7 +992,7 @@ impl World {
         );
         self.components
             .hit_points
-            .insert(entity, HitPoints::new_full(12));
+            .insert(entity, self.npc_hit_points(12));
         self.components
             .move_half_speed
             .insert(entity, MoveHalfSpeed::default());
@@ -887,7 +1021,7 @@ impl World {
         self.components.on_damage.insert(entity, OnDamage::Curse);
         self.components
             .hit_points
-            .insert(entity, HitPoints::new_full(12));
+            .insert(entity, self.npc_hit_points(12));
         entity
     }
 
@@ -948,4 +1082,20 @@ impl World {
         self.components.item.insert(entity, Item::Tech { special });
         entity
     }
+
+    pub fn spawn_key(&mut self, coord: Coord) -> Entity {
+        let entity = self.entity_allocator.alloc();
+        self.spatial_table
+            .update(
+                entity,
+                Location {
+                    coord,
+                    layer: Some(Layer::Feature),
+                },
+            )
+            .unwrap();
+        self.components.tile.insert(entity, Tile::Key);
+        self.components.key.insert(entity, ());
+        entity
+    }
 }
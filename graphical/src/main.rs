@@ -1,25 +1,31 @@
 #![windows_subsystem = "windows"]
 use chargrid_graphical as graphical;
-use slime99_app::{app, AutoPlay, Frontend, Fullscreen};
+use slime99_app::{
+    app, load_config, AutoPlay, Font, Frontend, Fullscreen, GraphicsConfig, WindowSize,
+};
 use slime99_native::{meap, NativeCommon};
 
 const FULLSCREEN_SUPPORTED: bool = true;
 
 const CELL_SIZE: f64 = 16.;
+const WINDOW_WIDTH_IN_CELLS: f64 = 60.;
+const WINDOW_HEIGHT_IN_CELLS: f64 = 40.;
 
 #[cfg(target_os = "windows")]
 mod graphical_env {
     use super::graphical::WindowHandle;
-    use slime99_app::Env;
+    use slime99_app::{Env, WindowSize};
     use std::cell::RefCell;
     pub struct GraphicalEnv {
         window_handle: WindowHandle,
+        remember_window_size: bool,
         shadow_fullscreen: RefCell<bool>,
     }
     impl GraphicalEnv {
-        pub fn new(window_handle: WindowHandle) -> Self {
+        pub fn new(window_handle: WindowHandle, remember_window_size: bool) -> Self {
             Self {
                 window_handle,
+                remember_window_size,
                 shadow_fullscreen: RefCell::new(false),
             }
         }
@@ -41,19 +47,26 @@ mod graphical_env {
             self.window_handle.set_fullscreen(fullscreen);
             *self.shadow_fullscreen.borrow_mut() = fullscreen;
         }
+        fn window_size(&self) -> Option<WindowSize> {
+            super::window_size(&self.window_handle, self.remember_window_size)
+        }
     }
 }
 
 #[cfg(not(target_os = "windows"))]
 mod graphical_env {
     use super::graphical::WindowHandle;
-    use slime99_app::Env;
+    use slime99_app::{Env, WindowSize};
     pub struct GraphicalEnv {
         window_handle: WindowHandle,
+        remember_window_size: bool,
     }
     impl GraphicalEnv {
-        pub fn new(window_handle: WindowHandle) -> Self {
-            Self { window_handle }
+        pub fn new(window_handle: WindowHandle, remember_window_size: bool) -> Self {
+            Self {
+                window_handle,
+                remember_window_size,
+            }
         }
     }
     impl Env for GraphicalEnv {
@@ -72,15 +85,66 @@ mod graphical_env {
         fn set_fullscreen_init(&self, fullscreen: bool) {
             self.window_handle.set_fullscreen(fullscreen)
         }
+        fn window_size(&self) -> Option<WindowSize> {
+            super::window_size(&self.window_handle, self.remember_window_size)
+        }
     }
 }
 
 use graphical::*;
 use graphical_env::*;
 
+fn window_size(window_handle: &WindowHandle, remember_window_size: bool) -> Option<WindowSize> {
+    if !remember_window_size {
+        return None;
+    }
+    let Dimensions { width, height } = window_handle.window_size();
+    Some(WindowSize {
+        width: width as u32,
+        height: height as u32,
+    })
+}
+
+/// The size of the primary monitor in the same logical pixels as `window_dimensions`. This is
+/// needed before the window exists to choose a scale which fills the screen when fullscreen.
+fn monitor_size() -> Option<WindowSize> {
+    let event_loop = winit::event_loop::EventLoop::new();
+    let monitor = event_loop.primary_monitor()?;
+    let size = monitor.size().to_logical::<f64>(monitor.scale_factor());
+    Some(WindowSize {
+        width: size.width as u32,
+        height: size.height as u32,
+    })
+}
+
+fn font_parser() -> impl meap::Parser<Item = Option<Font>> {
+    use meap::Parser;
+    meap::choose_at_most_one!(
+        flag("thin-font").some_if(Font::Thin),
+        flag("thick-font").some_if(Font::Thick),
+    )
+}
+
+/// A cell scale passed on the command line, which must be a positive number
+struct ScaleArg(f64);
+
+impl std::str::FromStr for ScaleArg {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let scale = s.parse::<f64>().map_err(|e| e.to_string())?;
+        if GraphicsConfig::is_valid_scale(scale) {
+            Ok(Self(scale))
+        } else {
+            Err(format!("scale must be a positive number (got {})", s))
+        }
+    }
+}
+
 struct Args {
     native_common: NativeCommon,
     fullscreen: Option<Fullscreen>,
+    scale: Option<f64>,
+    font: Option<Font>,
 }
 
 impl Args {
@@ -89,18 +153,34 @@ impl Args {
             let {
                 native_common = NativeCommon::parser();
                 fullscreen = flag('f').name("fullscreen").desc("start in fullscreen");
+                scale = opt_opt::<ScaleArg, _>("FLOAT", 'z').name("scale").desc("multiply the size of each cell (overrides the saved setting)");
+                font = font_parser();
             } in {{
                 let fullscreen = if fullscreen {
                     Some(Fullscreen)
                 } else {
                     None
                 };
-                Self { native_common, fullscreen }
+                let scale = scale.map(|ScaleArg(scale)| scale);
+                Self { native_common, fullscreen, scale, font }
             }}
         }
     }
 }
 
+fn font_bytes(font: Font) -> FontBytes {
+    let thin = include_bytes!("./fonts/PxPlus_IBM_CGAthin-with-quadrant-blocks.ttf");
+    let thick = include_bytes!("./fonts/PxPlus_IBM_CGA-with-quadrant-blocks.ttf");
+    let normal = match font {
+        Font::Thin => thin.to_vec(),
+        Font::Thick => thick.to_vec(),
+    };
+    FontBytes {
+        normal,
+        bold: thick.to_vec(),
+    }
+}
+
 fn main() {
     use meap::Parser;
     env_logger::init();
@@ -113,26 +193,64 @@ fn main() {
                 save_file,
                 audio_player,
                 game_config,
+                narration,
             },
         fullscreen,
+        scale,
+        font,
     } = Args::parser().with_help_default().parse_env_or_exit();
-    let context = Context::new(ContextDescriptor {
-        font_bytes: FontBytes {
-            normal: include_bytes!("./fonts/PxPlus_IBM_CGAthin-with-quadrant-blocks.ttf").to_vec(),
-            bold: include_bytes!("./fonts/PxPlus_IBM_CGA-with-quadrant-blocks.ttf").to_vec(),
+    let config = load_config(&file_storage);
+    let mut graphics_config = config.graphics;
+    if !GraphicsConfig::is_valid_scale(graphics_config.scale) {
+        // the config file may have been edited by hand
+        graphics_config.scale = GraphicsConfig::default().scale;
+        graphics_config.window_size = None;
+    }
+    if let Some(scale) = scale {
+        graphics_config.scale = scale;
+        graphics_config.window_size = None;
+    }
+    if let Some(font) = font {
+        graphics_config.font = font;
+    }
+    let fullscreen_scale = if fullscreen.is_some() || config.fullscreen {
+        monitor_size().map(|monitor_size| {
+            GraphicsConfig::fullscreen_scale(
+                monitor_size,
+                (WINDOW_WIDTH_IN_CELLS, WINDOW_HEIGHT_IN_CELLS),
+                CELL_SIZE,
+            )
+        })
+    } else {
+        None
+    };
+    let cell_scale = fullscreen_scale.unwrap_or(graphics_config.scale);
+    let cell_size = CELL_SIZE * cell_scale;
+    let window_dimensions = match graphics_config.window_size {
+        Some(WindowSize { width, height })
+            if fullscreen_scale.is_none() && width > 0 && height > 0 =>
+        {
+            Dimensions {
+                width: width as f64,
+                height: height as f64,
+            }
+        }
+        _ => Dimensions {
+            width: WINDOW_WIDTH_IN_CELLS * cell_size,
+            height: WINDOW_HEIGHT_IN_CELLS * cell_size,
         },
+    };
+    let context = Context::new(ContextDescriptor {
+        font_bytes: font_bytes(graphics_config.font),
         title: "slime99".to_string(),
-        window_dimensions: Dimensions {
-            width: 960.,
-            height: 640.,
-        },
+        window_dimensions,
         cell_dimensions: Dimensions {
-            width: CELL_SIZE,
-            height: CELL_SIZE,
+            width: cell_size,
+            height: cell_size,
         },
         font_dimensions: Dimensions {
-            width: CELL_SIZE,
-            height: CELL_SIZE,
+            width: cell_size,
+            height: cell_size,
         },
         font_source_dimensions: Dimensions {
             width: CELL_SIZE as f32,
@@ -140,10 +258,12 @@ fn main() {
         },
         underline_width: 0.1,
         underline_top_offset: 0.8,
-        resizable: false,
+        resizable: true,
     })
     .unwrap();
-    let env = GraphicalEnv::new(context.window_handle());
+    // a scale passed on the command line is only for this session, so don't remember the size
+    // of the window it produced
+    let env = GraphicalEnv::new(context.window_handle(), scale.is_none());
     let app = app(
         game_config,
         Frontend::Graphical,
@@ -155,6 +275,7 @@ fn main() {
         Some(AutoPlay),
         fullscreen,
         Box::new(env),
+        narration,
     );
     context.run_app(app);
 }
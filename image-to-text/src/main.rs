@@ -1,23 +1,252 @@
-fn main() {
-    use meap::prelude::*;
-    let in_path = opt_req::<String, _>("PATH", 'i')
-        .name("in")
-        .desc("path to input image file")
-        .with_help_default()
-        .parse_env_or_exit();
-    let in_image = image::open(in_path).unwrap().to_rgb();
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+
+/// Colours of every glyph understood by the level-file format, plus the `$` and `?` used by
+/// some WFC samples
+#[rustfmt::skip]
+const DEFAULT_PALETTE: &[(char, [u8; 3])] = &[
+    ('#', [0, 0, 0]),
+    ('.', [255, 255, 255]),
+    ('$', [0, 0, 255]),
+    ('?', [255, 0, 0]),
+    ('+', [127, 63, 0]),
+    ('>', [255, 255, 0]),
+    ('~', [0, 255, 0]),
+    ('=', [127, 127, 0]),
+    ('*', [255, 255, 127]),
+    ('@', [0, 255, 255]),
+    ('d', [255, 0, 255]),
+    ('s', [127, 0, 255]),
+    ('t', [0, 127, 255]),
+    ('g', [0, 127, 0]),
+    ('u', [255, 127, 0]),
+    ('c', [127, 0, 127]),
+    ('f', [255, 127, 127]),
+    ('h', [255, 191, 127]),
+    ('A', [191, 0, 0]),
+    ('D', [0, 191, 0]),
+    ('T', [0, 0, 191]),
+];
+
+enum Error {
+    Io(String, io::Error),
+    Image(String, image::ImageError),
+    PaletteJson(String, serde_json::Error),
+    PaletteKeyNotSingleChar(String),
+    PaletteDuplicateColour([u8; 3], char, char),
+    UnrecognisedColour { x: u32, y: u32, colour: [u8; 3] },
+    UnrecognisedChar { x: u32, y: u32, ch: char },
+    EmptyText,
+    RaggedRow { y: u32, width: u32, expected: u32 },
+    MissingInput,
+    MissingOutput,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "{}: {}", path, e),
+            Self::Image(path, e) => write!(f, "{}: {}", path, e),
+            Self::PaletteJson(path, e) => write!(f, "{}: invalid palette: {}", path, e),
+            Self::PaletteKeyNotSingleChar(key) => {
+                write!(f, "palette key {:?} is not a single character", key)
+            }
+            Self::PaletteDuplicateColour(colour, a, b) => write!(
+                f,
+                "palette maps both {:?} and {:?} to colour {:?}",
+                a, b, colour
+            ),
+            Self::UnrecognisedColour { x, y, colour } => write!(
+                f,
+                "unrecognised colour {:?} at pixel ({}, {})",
+                colour, x, y
+            ),
+            Self::UnrecognisedChar { x, y, ch } => write!(
+                f,
+                "unrecognised character {:?} at column {}, line {}",
+                ch,
+                x + 1,
+                y + 1
+            ),
+            Self::EmptyText => write!(f, "input text contains no rows"),
+            Self::RaggedRow { y, width, expected } => write!(
+                f,
+                "line {} is {} characters wide but the first line is {} characters wide",
+                y + 1,
+                width,
+                expected
+            ),
+            Self::MissingInput => write!(f, "missing input path (use -i)"),
+            Self::MissingOutput => write!(f, "text-to-image requires an output path"),
+        }
+    }
+}
+
+struct Palette {
+    colour_to_char: HashMap<[u8; 3], char>,
+    char_to_colour: HashMap<char, [u8; 3]>,
+}
+
+impl Palette {
+    fn from_entries<I: IntoIterator<Item = (char, [u8; 3])>>(entries: I) -> Result<Self, Error> {
+        let mut colour_to_char = HashMap::new();
+        let mut char_to_colour = HashMap::new();
+        for (ch, colour) in entries {
+            if let Some(existing) = colour_to_char.insert(colour, ch) {
+                return Err(Error::PaletteDuplicateColour(colour, existing, ch));
+            }
+            char_to_colour.insert(ch, colour);
+        }
+        Ok(Self {
+            colour_to_char,
+            char_to_colour,
+        })
+    }
+
+    fn builtin() -> Self {
+        Self::from_entries(DEFAULT_PALETTE.iter().cloned()).unwrap()
+    }
+
+    /// Palette files are json objects mapping single characters to [r, g, b] colours
+    fn load(path: &str) -> Result<Self, Error> {
+        let text = fs::read_to_string(path).map_err(|e| Error::Io(path.to_string(), e))?;
+        let entries: BTreeMap<String, [u8; 3]> =
+            serde_json::from_str(&text).map_err(|e| Error::PaletteJson(path.to_string(), e))?;
+        let entries = entries
+            .into_iter()
+            .map(|(key, colour)| {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => Ok((ch, colour)),
+                    _ => Err(Error::PaletteKeyNotSingleChar(key)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_entries(entries)
+    }
+
+    fn to_json(&self) -> String {
+        let entries = self
+            .char_to_colour
+            .iter()
+            .map(|(ch, &colour)| (ch.to_string(), colour))
+            .collect::<BTreeMap<_, _>>();
+        serde_json::to_string_pretty(&entries).unwrap()
+    }
+}
+
+fn image_to_text(in_path: &str, palette: &Palette) -> Result<String, Error> {
+    let in_image = image::open(in_path)
+        .map_err(|e| Error::Image(in_path.to_string(), e))?
+        .to_rgb();
+    let mut text = String::new();
     for y in 0..in_image.height() {
         for x in 0..in_image.width() {
-            let [r, g, b] = in_image.get_pixel(x, y).0;
-            let ch = match (r, g, b) {
-                (0, 0, 0) => '#',
-                (255, 255, 255) => '.',
-                (0, 0, 255) => '$',
-                (255, 0, 0) => '?',
-                other => panic!("unrecognised colour: {:?}", other),
-            };
-            print!("{}", ch);
+            let colour = in_image.get_pixel(x, y).0;
+            let &ch = palette
+                .colour_to_char
+                .get(&colour)
+                .ok_or(Error::UnrecognisedColour { x, y, colour })?;
+            text.push(ch);
         }
-        println!("");
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+fn text_to_image(in_path: &str, out_path: &str, palette: &Palette) -> Result<(), Error> {
+    let text = fs::read_to_string(in_path).map_err(|e| Error::Io(in_path.to_string(), e))?;
+    let rows = text
+        .split('\n')
+        .map(|row| row.trim_end_matches('\r'))
+        .filter(|row| !row.is_empty())
+        .map(|row| row.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let width = rows.first().ok_or(Error::EmptyText)?.len() as u32;
+    let mut out_image = image::RgbImage::new(width, rows.len() as u32);
+    for (y, row) in rows.iter().enumerate() {
+        let y = y as u32;
+        if row.len() as u32 != width {
+            return Err(Error::RaggedRow {
+                y,
+                width: row.len() as u32,
+                expected: width,
+            });
+        }
+        for (x, &ch) in row.iter().enumerate() {
+            let x = x as u32;
+            let &colour = palette
+                .char_to_colour
+                .get(&ch)
+                .ok_or(Error::UnrecognisedChar { x, y, ch })?;
+            out_image.put_pixel(x, y, image::Rgb(colour));
+        }
+    }
+    out_image
+        .save(out_path)
+        .map_err(|e| Error::Image(out_path.to_string(), e))
+}
+
+struct Args {
+    in_path: Option<String>,
+    out_path: Option<String>,
+    palette_path: Option<String>,
+    text_to_image: bool,
+    print_palette: bool,
+}
+
+impl Args {
+    fn parser() -> impl meap::Parser<Item = Self> {
+        meap::let_map! {
+            let {
+                in_path = opt_opt::<String, _>("PATH", 'i').name("in")
+                    .desc("path to input file (an image, or text with --text-to-image)");
+                out_path = opt_opt::<String, _>("PATH", 'o').name("out")
+                    .desc("path to output file (defaults to stdout for text output)");
+                palette_path = opt_opt::<String, _>("PATH", 'p').name("palette")
+                    .desc("json file mapping characters to [r, g, b] colours");
+                text_to_image = flag('t').name("text-to-image")
+                    .desc("convert a text level into an image instead");
+                print_palette = flag("print-palette")
+                    .desc("print the palette in json format and exit");
+            } in {
+                Self { in_path, out_path, palette_path, text_to_image, print_palette }
+            }
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    let palette = match args.palette_path.as_ref() {
+        Some(path) => Palette::load(path)?,
+        None => Palette::builtin(),
+    };
+    if args.print_palette {
+        println!("{}", palette.to_json());
+        return Ok(());
+    }
+    let in_path = args.in_path.ok_or(Error::MissingInput)?;
+    if args.text_to_image {
+        let out_path = args.out_path.ok_or(Error::MissingOutput)?;
+        text_to_image(&in_path, &out_path, &palette)
+    } else {
+        let text = image_to_text(&in_path, &palette)?;
+        match args.out_path {
+            Some(out_path) => fs::write(&out_path, text).map_err(|e| Error::Io(out_path, e)),
+            None => io::stdout()
+                .write_all(text.as_bytes())
+                .map_err(|e| Error::Io("stdout".to_string(), e)),
+        }
+    }
+}
+
+fn main() {
+    use meap::Parser;
+    let args = Args::parser().with_help_default().parse_env_or_exit();
+    if let Err(e) = run(args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
     }
 }
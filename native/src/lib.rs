@@ -5,7 +5,7 @@ use general_audio_static::{
 use general_storage_static::backend::{FileStorage, IfDirectoryMissing};
 pub use general_storage_static::StaticStorage;
 pub use meap;
-use slime99_app::{AppAudioPlayer, Controls, GameConfig, Omniscient, RngSeed};
+use slime99_app::{AppAudioPlayer, Controls, GameConfig, Narration, Omniscient, RngSeed};
 use std::env;
 use std::fs::File;
 use std::io::Read;
@@ -22,6 +22,7 @@ pub struct NativeCommon {
     pub controls: Controls,
     pub audio_player: AppAudioPlayer,
     pub game_config: GameConfig,
+    pub narration: Option<Narration>,
 }
 
 fn read_controls_file(path: &PathBuf) -> Option<Controls> {
@@ -44,6 +45,8 @@ impl NativeCommon {
                 delete_save = flag("delete-save").desc("delete save game file");
                 omniscient = flag("omniscient").desc("enable omniscience");
                 mute = flag('m').name("mute").desc("mute audio");
+                narration_file = opt_opt::<String, _>("PATH", "narrate")
+                    .desc("write a plain-text description of each turn to a file (for screen readers)");
             } in {{
                 let rng_seed = rng_seed.map(RngSeed::U64).unwrap_or(RngSeed::Random);
                 let controls_file = if let Some(controls_file) = controls_file {
@@ -81,6 +84,15 @@ impl NativeCommon {
                         None
                     }
                 };
+                let narration = narration_file.and_then(|narration_file| {
+                    match File::create(&narration_file) {
+                        Ok(file) => Some(Narration::new(Box::new(file))),
+                        Err(e) => {
+                            log::warn!("couldn't create narration file {}: {}", narration_file, e);
+                            None
+                        }
+                    }
+                });
                 Self {
                     rng_seed,
                     save_file,
@@ -88,6 +100,7 @@ impl NativeCommon {
                     controls,
                     audio_player,
                     game_config,
+                    narration,
                 }
             }}
         }
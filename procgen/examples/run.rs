@@ -1,59 +1,327 @@
 use grid_2d::{Coord, Size};
-use procgen::{Sewer, SewerCell, SewerSpec};
+use procgen::{GenerationFailed, Sewer, SewerCell, SewerGenerator, SewerMetrics, SewerSpec};
 use rand::{Rng, SeedableRng};
 use rand_isaac::Isaac64Rng;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy)]
+enum Format {
+    Ascii,
+    Json,
+    Png,
+    Level,
+}
+
+impl Format {
+    fn parser() -> impl meap::Parser<Item = Self> {
+        use meap::Parser;
+        use Format::*;
+        meap::choose_at_most_one!(
+            flag("ascii").some_if(Ascii),
+            flag("json").some_if(Json),
+            flag("png").some_if(Png),
+            flag("level").some_if(Level),
+        )
+        .with_default_general(Ascii)
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Ascii => "txt",
+            Self::Json => "json",
+            Self::Png => "png",
+            Self::Level => "level.txt",
+        }
+    }
+}
 
 struct Args {
     size: Size,
-    rng: Isaac64Rng,
+    generator: SewerGenerator,
+    rng_seed: u64,
+    format: Format,
+    png_scale: u32,
+    output: Option<PathBuf>,
+    batch: Option<(u64, PathBuf)>,
 }
 
 impl Args {
     fn parser() -> impl meap::Parser<Item = Self> {
         meap::let_map! {
             let {
-                rng_seed = opt_opt::<u64, _>("INT", 'r').name("rng-seed").desc("rng seed")
+                rng_seed = opt_opt::<u64, _>("INT", 'r').name("rng-seed")
+                    .desc("rng seed (the first seed when generating a batch)")
                     .with_default_lazy_general(|| rand::thread_rng().gen());
                 width = opt_opt("INT", 'x').name("width").with_default(40);
                 height = opt_opt("INT", 'y').name("height").with_default(20);
+                generator = opt_opt::<String, _>("NAME", 'g').name("generator")
+                    .desc("one of: wfc, rooms, caves")
+                    .with_default("wfc".to_string());
+                format = Format::parser();
+                png_scale = opt_opt("INT", 's').name("png-scale")
+                    .desc("width and height in pixels of each cell in png output")
+                    .with_default(8);
+                output = opt_opt::<String, _>("PATH", 'o').name("output")
+                    .desc("file to write to instead of stdout (required for png output)");
+                batch_count = opt_opt::<u64, _>("INT", 'n').name("batch-count")
+                    .desc("number of consecutive seeds to generate into the batch dir");
+                batch_dir = opt_opt::<String, _>("PATH", 'd').name("batch-dir")
+                    .desc("directory to write a batch of maps to, one file per seed");
             } in {{
-                println!("RNG Seed: {}", rng_seed);
-                let rng = Isaac64Rng::seed_from_u64(rng_seed);
                 let size = Size::new(width, height);
+                let generator = match generator.as_str() {
+                    "wfc" => SewerGenerator::Wfc,
+                    "rooms" => SewerGenerator::RoomsAndCorridors,
+                    "caves" => SewerGenerator::Caves,
+                    other => {
+                        eprintln!("Unknown generator: {}", other);
+                        std::process::exit(1);
+                    }
+                };
+                let batch = match (batch_count, batch_dir) {
+                    (None, None) => None,
+                    (count, Some(dir)) => Some((count.unwrap_or(1), dir.into())),
+                    (Some(_), None) => {
+                        eprintln!("--batch-count requires --batch-dir");
+                        std::process::exit(1);
+                    }
+                };
                 Self {
-                    rng,
                     size,
+                    generator,
+                    rng_seed,
+                    format,
+                    png_scale,
+                    output: output.map(|output| output.into()),
+                    batch,
                 }
             }}
         }
     }
 }
 
+fn ascii_char(cell: SewerCell) -> char {
+    match cell {
+        SewerCell::Floor => '.',
+        SewerCell::Wall => '█',
+        SewerCell::Pool => '~',
+        SewerCell::Bridge => '=',
+        SewerCell::Door => '+',
+    }
+}
+
+/// The characters understood by the game's `terrain::from_str`
+fn level_char(cell: SewerCell) -> char {
+    match cell {
+        SewerCell::Floor => '.',
+        SewerCell::Wall => '#',
+        SewerCell::Pool => '~',
+        SewerCell::Bridge => '=',
+        SewerCell::Door => '+',
+    }
+}
+
+fn map_rows<F: Fn(SewerCell) -> char>(sewer: &Sewer, f: F) -> Vec<String> {
+    sewer
+        .map
+        .rows()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(j, &cell)| {
+                    let coord = Coord::new(j as i32, i as i32);
+                    if coord == sewer.start {
+                        '@'
+                    } else if coord == sewer.goal {
+                        '>'
+                    } else {
+                        f(cell)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn write_ascii<W: Write>(sewer: &Sewer, mut w: W) -> io::Result<()> {
+    writeln!(w, "    abcdefghijklmnopqrstuvwxyz")?;
+    for (i, row) in map_rows(sewer, ascii_char).into_iter().enumerate() {
+        writeln!(w, "{:2}: {}", i, row)?;
+    }
+    writeln!(w, "{:#?}", sewer.metrics())
+}
+
+fn write_level<W: Write>(sewer: &Sewer, mut w: W) -> io::Result<()> {
+    for row in map_rows(sewer, level_char) {
+        writeln!(w, "{}", row)?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct MetricsJson {
+    walkable_area: usize,
+    num_rooms: usize,
+    num_doors: usize,
+    num_bridges: usize,
+    pool_area: usize,
+    shortest_path_length: Option<usize>,
+    num_routes: usize,
+    num_dead_ends: usize,
+}
+
+impl From<SewerMetrics> for MetricsJson {
+    fn from(metrics: SewerMetrics) -> Self {
+        Self {
+            walkable_area: metrics.walkable_area,
+            num_rooms: metrics.num_rooms,
+            num_doors: metrics.num_doors,
+            num_bridges: metrics.num_bridges,
+            pool_area: metrics.pool_area,
+            shortest_path_length: metrics.shortest_path_length,
+            num_routes: metrics.num_routes,
+            num_dead_ends: metrics.num_dead_ends,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SewerJson {
+    rng_seed: u64,
+    width: u32,
+    height: u32,
+    start: (i32, i32),
+    goal: (i32, i32),
+    lights: Vec<(i32, i32)>,
+    /// Rows of the map in the level-file format
+    map: Vec<String>,
+    metrics: MetricsJson,
+}
+
+fn write_json<W: Write>(sewer: &Sewer, rng_seed: u64, w: W) -> io::Result<()> {
+    let size = sewer.map.size();
+    let json = SewerJson {
+        rng_seed,
+        width: size.width(),
+        height: size.height(),
+        start: (sewer.start.x, sewer.start.y),
+        goal: (sewer.goal.x, sewer.goal.y),
+        lights: sewer
+            .lights
+            .iter()
+            .map(|light| (light.coord.x, light.coord.y))
+            .collect(),
+        map: map_rows(sewer, level_char),
+        metrics: sewer.metrics().into(),
+    };
+    serde_json::to_writer_pretty(w, &json)?;
+    Ok(())
+}
+
+fn png_colour(cell: SewerCell) -> [u8; 3] {
+    match cell {
+        SewerCell::Floor => [187, 187, 187],
+        SewerCell::Wall => [31, 31, 31],
+        SewerCell::Pool => [0, 127, 63],
+        SewerCell::Bridge => [127, 79, 31],
+        SewerCell::Door => [223, 127, 0],
+    }
+}
+
+fn write_png(sewer: &Sewer, scale: u32, path: &Path) -> image::ImageResult<()> {
+    let size = sewer.map.size();
+    let image = image::RgbImage::from_fn(size.width() * scale, size.height() * scale, |x, y| {
+        let coord = Coord::new((x / scale) as i32, (y / scale) as i32);
+        let colour = if coord == sewer.start {
+            [0, 0, 255]
+        } else if coord == sewer.goal {
+            [255, 0, 0]
+        } else if sewer.lights.iter().any(|light| light.coord == coord) {
+            [0, 255, 127]
+        } else {
+            png_colour(*sewer.map.get_checked(coord))
+        };
+        image::Rgb(colour)
+    });
+    image.save(path)
+}
+
+fn generate(spec: SewerSpec, rng_seed: u64) -> Sewer {
+    let mut rng = Isaac64Rng::seed_from_u64(rng_seed);
+    match Sewer::generate(spec, &mut rng) {
+        Ok(sewer) => sewer,
+        Err(GenerationFailed { attempts }) => {
+            eprintln!(
+                "Failed to generate sewer with seed {} after {} attempts",
+                rng_seed, attempts
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+fn write_sewer(
+    sewer: &Sewer,
+    rng_seed: u64,
+    format: Format,
+    png_scale: u32,
+    output: Option<&Path>,
+) -> Result<(), String> {
+    if let Format::Png = format {
+        let path = output.ok_or_else(|| "png output requires an output file".to_string())?;
+        return write_png(sewer, png_scale, path).map_err(|e| e.to_string());
+    }
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(File::create(path).map_err(|e| e.to_string())?),
+        None => Box::new(io::stdout()),
+    };
+    match format {
+        Format::Ascii => write_ascii(sewer, writer),
+        Format::Json => write_json(sewer, rng_seed, writer),
+        Format::Level => write_level(sewer, writer),
+        Format::Png => unreachable!(),
+    }
+    .map_err(|e| e.to_string())
+}
+
 fn main() {
     use meap::Parser;
-    let Args { size, mut rng } = Args::parser().with_help_default().parse_env_or_exit();
-    let spec = SewerSpec { size };
-    let sewer = Sewer::generate(spec, &mut rng);
-    println!("    abcdefghijklmnopqrstuvwxyz");
-    for (i, row) in sewer.map.rows().enumerate() {
-        print!("{:2}: ", i);
-        for (j, cell) in row.into_iter().enumerate() {
-            let coord = Coord::new(j as i32, i as i32);
-            let ch = if coord == sewer.start {
-                '@'
-            } else if coord == sewer.goal {
-                '>'
-            } else {
-                match cell {
-                    SewerCell::Floor => '.',
-                    SewerCell::Wall => '█',
-                    SewerCell::Pool => '~',
-                    SewerCell::Bridge => '=',
-                    SewerCell::Door => '+',
+    let Args {
+        size,
+        generator,
+        rng_seed,
+        format,
+        png_scale,
+        output,
+        batch,
+    } = Args::parser().with_help_default().parse_env_or_exit();
+    let spec = SewerSpec {
+        generator,
+        ..SewerSpec::new(size)
+    };
+    let result = if let Some((count, dir)) = batch {
+        fs::create_dir_all(&dir)
+            .map_err(|e| e.to_string())
+            .and_then(|()| {
+                for rng_seed in (0..count).map(|i| rng_seed.wrapping_add(i)) {
+                    let sewer = generate(spec, rng_seed);
+                    let path = dir.join(format!("{}.{}", rng_seed, format.extension()));
+                    write_sewer(&sewer, rng_seed, format, png_scale, Some(&path))?;
+                    eprintln!("Wrote {}", path.display());
                 }
-            };
-            print!("{}", ch);
-        }
-        println!("");
+                Ok(())
+            })
+    } else {
+        eprintln!("RNG Seed: {}", rng_seed);
+        let sewer = generate(spec, rng_seed);
+        write_sewer(&sewer, rng_seed, format, png_scale, output.as_deref())
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
     }
 }
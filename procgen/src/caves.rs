@@ -0,0 +1,68 @@
+use crate::{
+    bridge_pools_between, choose_start_and_goal, ensure_single_connected_area, LevelGenerator,
+    Sewer, SewerCell,
+};
+use direction::Direction;
+use grid_2d::{Coord, Grid, Size};
+use rand::Rng;
+
+const WALL_PROBABILITY: f64 = 0.45;
+const POOL_PROBABILITY: f64 = 0.4;
+const NUM_SMOOTHING_STEPS: usize = 5;
+
+/// Generates open, irregular caves by smoothing random noise with a cellular automaton. A second
+/// automaton run over the cave floor decides where the pools go.
+#[derive(Clone, Copy)]
+pub struct CaveGenerator;
+
+fn random_grid<R: Rng>(size: Size, probability: f64, rng: &mut R) -> Grid<bool> {
+    Grid::new_fn(size, |_| rng.gen_bool(probability))
+}
+
+/// A cell is set if at least 5 of the 9 cells in its neighbourhood (including itself) are set.
+/// Cells outside the grid count as set.
+fn smooth_step(grid: &Grid<bool>) -> Grid<bool> {
+    Grid::new_fn(grid.size(), |coord| {
+        let num_neighbours_set = Direction::all()
+            .filter(|d| grid.get(coord + d.coord()).cloned().unwrap_or(true))
+            .count()
+            + *grid.get_checked(coord) as usize;
+        num_neighbours_set >= 5
+    })
+}
+
+fn smooth(mut grid: Grid<bool>) -> Grid<bool> {
+    for _ in 0..NUM_SMOOTHING_STEPS {
+        grid = smooth_step(&grid);
+    }
+    grid
+}
+
+fn is_on_edge(coord: Coord, size: Size) -> bool {
+    coord.x == 0
+        || coord.y == 0
+        || coord.x == size.width() as i32 - 1
+        || coord.y == size.height() as i32 - 1
+}
+
+impl LevelGenerator for CaveGenerator {
+    fn try_generate<R: Rng>(&self, size: Size, rng: &mut R) -> Option<Sewer> {
+        let walls = smooth(random_grid(size, WALL_PROBABILITY, rng));
+        let pools = smooth(random_grid(size, POOL_PROBABILITY, rng));
+        let mut map = Grid::new_fn(size, |coord| {
+            if *walls.get_checked(coord) || is_on_edge(coord, size) {
+                SewerCell::Wall
+            } else if *pools.get_checked(coord) {
+                SewerCell::Pool
+            } else {
+                SewerCell::Floor
+            }
+        });
+        ensure_single_connected_area(&mut map)?;
+        let (start, goal) = choose_start_and_goal(&map, rng)?;
+        if !bridge_pools_between(&mut map, start, goal) {
+            return None;
+        }
+        Sewer::new_with_pool_lights(start, goal, map, rng)
+    }
+}
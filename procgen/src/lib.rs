@@ -5,6 +5,13 @@ use std::collections::{HashMap, HashSet, VecDeque};
 use std::num::NonZeroU32;
 use wfc::{overlapping::OverlappingPatterns, retry, wrap, ForbidNothing, RunOwn};
 
+mod caves;
+mod metrics;
+mod rooms_and_corridors;
+pub use caves::CaveGenerator;
+pub use metrics::{Bound, SewerConstraints, SewerMetrics};
+pub use rooms_and_corridors::RoomsAndCorridorsGenerator;
+
 #[rustfmt::skip]
 const WFC_INPUT: &[&str] = &[
 "................................",
@@ -71,12 +78,15 @@ fn wfc_map<R: Rng>(
     output_size: Size,
     pattern_size: NonZeroU32,
     rng: &mut R,
-) -> Grid<CellA> {
+) -> Option<Grid<CellA>> {
+    const NUM_WFC_RETRIES: usize = 16;
     let mut output_grid = Grid::new_clone(output_size, CellA::Open);
     let overlapping_patterns = OverlappingPatterns::new_all_orientations(input_grid, pattern_size);
     let global_stats = overlapping_patterns.global_stats();
     let run = RunOwn::new_wrap_forbid(output_size, &global_stats, wrap::WrapXY, ForbidNothing, rng);
-    let wave = run.collapse_retrying(retry::Forever, rng);
+    let wave = run
+        .collapse_retrying(retry::NumTimes(NUM_WFC_RETRIES), rng)
+        .ok()?;
     for (coord, wave_cell) in wave.grid().enumerate() {
         let pattern_id = wave_cell
             .chosen_pattern_id()
@@ -84,7 +94,7 @@ fn wfc_map<R: Rng>(
         let cell = overlapping_patterns.pattern_top_left_value(pattern_id);
         *output_grid.get_checked_mut(coord) = *cell;
     }
-    output_grid
+    Some(output_grid)
 }
 
 struct PoolCandidates {
@@ -613,6 +623,15 @@ pub enum SewerCell {
     Door,
 }
 
+impl SewerCell {
+    fn is_walkable(self) -> bool {
+        match self {
+            SewerCell::Floor | SewerCell::Door | SewerCell::Bridge => true,
+            SewerCell::Wall | SewerCell::Pool => false,
+        }
+    }
+}
+
 fn make_cell_d_grid(grid: &Grid<CellC>) -> Grid<SewerCell> {
     Grid::new_grid_map_ref(grid, |cell| match cell {
         CellC::Floor(_) => SewerCell::Floor,
@@ -627,7 +646,8 @@ fn add_bridge_candidate(grid: &mut Grid<SewerCell>, candidate: &BridgeCandidate)
     }
 }
 
-fn ensure_single_connected_area(grid: &mut Grid<SewerCell>) {
+/// Fills in all but the largest connected area with walls. Fails if the grid has no floor.
+fn ensure_single_connected_area(grid: &mut Grid<SewerCell>) -> Option<()> {
     let mut areas = Vec::new();
     let mut seen = HashSet::new();
     let mut flood_fill_buffer = VecDeque::new();
@@ -657,8 +677,7 @@ fn ensure_single_connected_area(grid: &mut Grid<SewerCell>) {
         .iter()
         .map(|a| a.len())
         .enumerate()
-        .max_by_key(|&(_index, len)| len)
-        .unwrap()
+        .max_by_key(|&(_index, len)| len)?
         .0;
     for (index, area) in areas.iter_mut().enumerate() {
         if index != index_of_largest_area {
@@ -667,6 +686,7 @@ fn ensure_single_connected_area(grid: &mut Grid<SewerCell>) {
             }
         }
     }
+    Some(())
 }
 
 fn all_floor_adjacent_floor_coords(grid: &Grid<SewerCell>) -> Vec<Coord> {
@@ -704,43 +724,80 @@ fn pool_light_coords<R: Rng>(grid: &Grid<SewerCell>, rng: &mut R) -> Vec<Coord>
     coords
 }
 
-#[derive(Clone, Copy)]
-pub struct SewerSpec {
-    pub size: Size,
+/// Bridge over the fewest pool cells needed for the goal to be reachable from the start without
+/// entering a pool. Returns `false` if the goal can't be reached from the start at all.
+fn bridge_pools_between(grid: &mut Grid<SewerCell>, start: Coord, goal: Coord) -> bool {
+    // 0-1 breadth-first search where stepping onto a pool costs 1 and any other step is free
+    let mut costs: Grid<Option<u32>> = Grid::new_grid_map_ref(grid, |_| None);
+    let mut came_from: HashMap<Coord, Coord> = HashMap::new();
+    let mut queue = VecDeque::new();
+    *costs.get_checked_mut(start) = Some(0);
+    queue.push_back(start);
+    while let Some(coord) = queue.pop_front() {
+        let cost = costs.get_checked(coord).unwrap();
+        for direction in CardinalDirection::all() {
+            let neighbour_coord = coord + direction.coord();
+            let neighbour_cost = match grid.get(neighbour_coord) {
+                None | Some(SewerCell::Wall) => continue,
+                Some(SewerCell::Pool) => cost + 1,
+                Some(SewerCell::Floor) | Some(SewerCell::Door) | Some(SewerCell::Bridge) => cost,
+            };
+            if costs
+                .get_checked(neighbour_coord)
+                .map(|existing_cost| neighbour_cost < existing_cost)
+                .unwrap_or(true)
+            {
+                *costs.get_checked_mut(neighbour_coord) = Some(neighbour_cost);
+                came_from.insert(neighbour_coord, coord);
+                if neighbour_cost == cost {
+                    queue.push_front(neighbour_coord);
+                } else {
+                    queue.push_back(neighbour_coord);
+                }
+            }
+        }
+    }
+    if costs.get_checked(goal).is_none() {
+        return false;
+    }
+    let mut coord = goal;
+    while let Some(&prev) = came_from.get(&coord) {
+        let cell = grid.get_checked_mut(coord);
+        if *cell == SewerCell::Pool {
+            *cell = SewerCell::Bridge;
+        }
+        coord = prev;
+    }
+    true
 }
 
-pub enum SewerLightType {
-    Pool,
+/// Choose a start coord, and a goal coord which is among the farthest 10% of candidates from
+/// the start
+fn choose_start_and_goal<R: Rng>(grid: &Grid<SewerCell>, rng: &mut R) -> Option<(Coord, Coord)> {
+    let mut player_and_goal_candidates = all_floor_adjacent_floor_coords(grid);
+    player_and_goal_candidates.shuffle(rng);
+    let start = player_and_goal_candidates.pop()?;
+    player_and_goal_candidates.sort_by_key(|coord| coord.distance2(start));
+    let goal_start_offset = 9 * (player_and_goal_candidates.len() / 10);
+    let goal = player_and_goal_candidates[goal_start_offset..]
+        .choose(rng)?
+        .clone();
+    Some((start, goal))
 }
 
-pub struct SewerLight {
-    pub typ: SewerLightType,
-    pub coord: Coord,
+pub trait LevelGenerator {
+    fn try_generate<R: Rng>(&self, size: Size, rng: &mut R) -> Option<Sewer>;
 }
 
-pub struct Sewer {
-    pub start: Coord,
-    pub goal: Coord,
-    pub map: Grid<SewerCell>,
-    pub lights: Vec<SewerLight>,
-}
+/// The original generator, which uses wave function collapse to make twisting tunnels, then
+/// floods some of them to make pools
+#[derive(Clone, Copy)]
+pub struct WfcGenerator;
 
-impl Sewer {
-    pub fn generate<R: Rng>(spec: SewerSpec, rng: &mut R) -> Self {
-        loop {
-            if let Some(sewer) = Self::try_generate(spec, rng) {
-                return sewer;
-            }
-        }
-    }
-    pub fn try_generate<R: Rng>(spec: SewerSpec, rng: &mut R) -> Option<Self> {
+impl LevelGenerator for WfcGenerator {
+    fn try_generate<R: Rng>(&self, size: Size, rng: &mut R) -> Option<Sewer> {
         let pattern_size = NonZeroU32::new(3).unwrap();
-        let map = wfc_map(
-            input_grid_from_strs(WFC_INPUT),
-            spec.size,
-            pattern_size,
-            rng,
-        );
+        let map = wfc_map(input_grid_from_strs(WFC_INPUT), size, pattern_size, rng)?;
         let mut pool_candidates = PoolCandidates::new(&map);
         for candidate in 0..pool_candidates.num {
             let shrink_by = rng.gen_range(2..4);
@@ -768,15 +825,100 @@ impl Sewer {
         for coord in door_coords {
             *map.get_checked_mut(coord) = SewerCell::Door;
         }
-        ensure_single_connected_area(&mut map);
-        let mut player_and_goal_candidates = all_floor_adjacent_floor_coords(&map);
-        player_and_goal_candidates.shuffle(rng);
-        let start = player_and_goal_candidates.pop()?;
-        player_and_goal_candidates.sort_by_key(|coord| coord.distance2(start));
-        let goal_start_offset = 9 * (player_and_goal_candidates.len() / 10);
-        let goal = player_and_goal_candidates[goal_start_offset..]
-            .choose(rng)?
-            .clone();
+        ensure_single_connected_area(&mut map)?;
+        let (start, goal) = choose_start_and_goal(&map, rng)?;
+        Sewer::new_with_pool_lights(start, goal, map, rng)
+    }
+}
+
+/// Selects which `LevelGenerator` is used to generate a sewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SewerGenerator {
+    Wfc,
+    RoomsAndCorridors,
+    Caves,
+}
+
+impl LevelGenerator for SewerGenerator {
+    fn try_generate<R: Rng>(&self, size: Size, rng: &mut R) -> Option<Sewer> {
+        match self {
+            Self::Wfc => WfcGenerator.try_generate(size, rng),
+            Self::RoomsAndCorridors => RoomsAndCorridorsGenerator.try_generate(size, rng),
+            Self::Caves => CaveGenerator.try_generate(size, rng),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SewerSpec {
+    pub size: Size,
+    pub generator: SewerGenerator,
+    pub constraints: SewerConstraints,
+    pub max_attempts: u32,
+}
+
+impl SewerSpec {
+    pub const DEFAULT_MAX_ATTEMPTS: u32 = 1000;
+
+    pub fn new(size: Size) -> Self {
+        Self {
+            size,
+            generator: SewerGenerator::Wfc,
+            constraints: SewerConstraints::default(),
+            max_attempts: Self::DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+}
+
+pub enum SewerLightType {
+    Pool,
+}
+
+pub struct SewerLight {
+    pub typ: SewerLightType,
+    pub coord: Coord,
+}
+
+pub struct Sewer {
+    pub start: Coord,
+    pub goal: Coord,
+    pub map: Grid<SewerCell>,
+    pub lights: Vec<SewerLight>,
+}
+
+/// Returned when no sewer satisfying a spec's constraints could be generated
+/// within its maximum number of attempts.
+#[derive(Debug, Clone, Copy)]
+pub struct GenerationFailed {
+    pub attempts: u32,
+}
+
+impl Sewer {
+    pub fn generate<R: Rng>(spec: SewerSpec, rng: &mut R) -> Result<Self, GenerationFailed> {
+        for _ in 0..spec.max_attempts {
+            if let Some(sewer) = Self::try_generate(spec, rng) {
+                if spec.constraints.is_satisfied_by(&sewer.metrics()) {
+                    return Ok(sewer);
+                }
+            }
+        }
+        Err(GenerationFailed {
+            attempts: spec.max_attempts,
+        })
+    }
+    pub fn metrics(&self) -> SewerMetrics {
+        SewerMetrics::new(self)
+    }
+    pub fn try_generate<R: Rng>(spec: SewerSpec, rng: &mut R) -> Option<Self> {
+        spec.generator.try_generate(spec.size, rng)
+    }
+    /// Lights are only placed in pools, so maps without pools are rejected
+    fn new_with_pool_lights<R: Rng>(
+        start: Coord,
+        goal: Coord,
+        map: Grid<SewerCell>,
+        rng: &mut R,
+    ) -> Option<Self> {
         if !map.iter().any(|&cell| cell == SewerCell::Pool) {
             return None;
         }
@@ -787,12 +929,11 @@ impl Sewer {
                 typ: SewerLightType::Pool,
             })
             .collect::<Vec<_>>();
-        let sewer = Sewer {
+        Some(Sewer {
             start,
             goal,
             map,
             lights,
-        };
-        Some(sewer)
+        })
     }
 }
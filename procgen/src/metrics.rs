@@ -0,0 +1,387 @@
+use crate::{Sewer, SewerCell};
+use direction::CardinalDirection;
+use grid_2d::{Coord, Grid};
+use std::collections::{HashSet, VecDeque};
+
+/// Stop counting routes once this many have been found
+const MAX_ROUTES: usize = 64;
+
+/// Limit on the work done while counting routes, as the number of simple paths through a
+/// densely-connected room graph can be huge
+const MAX_ROUTE_SEARCH_STEPS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bound {
+    pub min: Option<usize>,
+    pub max: Option<usize>,
+}
+
+impl Bound {
+    pub const fn none() -> Self {
+        Self {
+            min: None,
+            max: None,
+        }
+    }
+    pub const fn at_least(min: usize) -> Self {
+        Self {
+            min: Some(min),
+            max: None,
+        }
+    }
+    pub const fn at_most(max: usize) -> Self {
+        Self {
+            min: None,
+            max: Some(max),
+        }
+    }
+    pub const fn between(min: usize, max: usize) -> Self {
+        Self {
+            min: Some(min),
+            max: Some(max),
+        }
+    }
+    pub fn is_unbounded(self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+    pub fn contains(self, value: usize) -> bool {
+        self.min.map(|min| value >= min).unwrap_or(true)
+            && self.max.map(|max| value <= max).unwrap_or(true)
+    }
+}
+
+/// Bounds on the metrics of a generated sewer. Each bound defaults to unbounded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SewerConstraints {
+    pub walkable_area: Bound,
+    pub num_rooms: Bound,
+    pub num_doors: Bound,
+    pub num_bridges: Bound,
+    pub pool_area: Bound,
+    pub shortest_path_length: Bound,
+    pub num_routes: Bound,
+    pub num_dead_ends: Bound,
+}
+
+impl SewerConstraints {
+    pub fn is_satisfied_by(&self, metrics: &SewerMetrics) -> bool {
+        let shortest_path_length_ok = match metrics.shortest_path_length {
+            Some(shortest_path_length) => {
+                self.shortest_path_length.contains(shortest_path_length)
+            }
+            None => self.shortest_path_length.is_unbounded(),
+        };
+        shortest_path_length_ok
+            && self.walkable_area.contains(metrics.walkable_area)
+            && self.num_rooms.contains(metrics.num_rooms)
+            && self.num_doors.contains(metrics.num_doors)
+            && self.num_bridges.contains(metrics.num_bridges)
+            && self.pool_area.contains(metrics.pool_area)
+            && self.num_routes.contains(metrics.num_routes)
+            && self.num_dead_ends.contains(metrics.num_dead_ends)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SewerMetrics {
+    /// Number of cells that can be walked on without entering a pool
+    pub walkable_area: usize,
+    /// Number of areas of floor separated from one another by walls, doors, pools or bridges
+    pub num_rooms: usize,
+    pub num_doors: usize,
+    /// Number of bridges, where each bridge is a connected run of bridge cells
+    pub num_bridges: usize,
+    pub pool_area: usize,
+    /// Number of steps from the start to the goal without entering a pool, if this is possible
+    pub shortest_path_length: Option<usize>,
+    /// Number of different sequences of rooms which lead from the start to the goal, counting
+    /// each door or bridge between a pair of rooms separately (capped at `MAX_ROUTES`)
+    pub num_routes: usize,
+    /// Number of walkable cells with exactly one walkable neighbour
+    pub num_dead_ends: usize,
+}
+
+fn classify_components<F: Fn(SewerCell) -> bool>(
+    grid: &Grid<SewerCell>,
+    f: F,
+) -> (Grid<Option<usize>>, usize) {
+    let mut components: Grid<Option<usize>> = Grid::new_grid_map_ref(grid, |_| None);
+    let mut flood_fill_buffer = VecDeque::new();
+    let mut id = 0;
+    for (coord, &cell) in grid.enumerate() {
+        if f(cell) && components.get_checked(coord).is_none() {
+            *components.get_checked_mut(coord) = Some(id);
+            flood_fill_buffer.push_back(coord);
+            while let Some(coord) = flood_fill_buffer.pop_front() {
+                for direction in CardinalDirection::all() {
+                    let neighbour_coord = coord + direction.coord();
+                    if let Some(&cell) = grid.get(neighbour_coord) {
+                        if f(cell) && components.get_checked(neighbour_coord).is_none() {
+                            *components.get_checked_mut(neighbour_coord) = Some(id);
+                            flood_fill_buffer.push_back(neighbour_coord);
+                        }
+                    }
+                }
+            }
+            id += 1;
+        }
+    }
+    (components, id)
+}
+
+fn shortest_path_length(grid: &Grid<SewerCell>, start: Coord, goal: Coord) -> Option<usize> {
+    let mut distances: Grid<Option<usize>> = Grid::new_grid_map_ref(grid, |_| None);
+    let mut queue = VecDeque::new();
+    *distances.get_mut(start)? = Some(0);
+    queue.push_back(start);
+    while let Some(coord) = queue.pop_front() {
+        let distance = distances.get_checked(coord).unwrap();
+        if coord == goal {
+            return Some(distance);
+        }
+        for direction in CardinalDirection::all() {
+            let neighbour_coord = coord + direction.coord();
+            if let Some(&cell) = grid.get(neighbour_coord) {
+                if cell.is_walkable() && distances.get_checked(neighbour_coord).is_none() {
+                    *distances.get_checked_mut(neighbour_coord) = Some(distance + 1);
+                    queue.push_back(neighbour_coord);
+                }
+            }
+        }
+    }
+    None
+}
+
+struct RoomGraph {
+    edges: Vec<Vec<usize>>,
+}
+
+impl RoomGraph {
+    fn new(grid: &Grid<SewerCell>, rooms: &Grid<Option<usize>>, num_rooms: usize) -> Self {
+        let (bridges, num_bridges) = classify_components(grid, |cell| cell == SewerCell::Bridge);
+        // each door and each bridge is a connector between the rooms adjacent to it
+        let mut connectors: Vec<HashSet<usize>> = vec![HashSet::new(); num_bridges];
+        for (coord, &cell) in grid.enumerate() {
+            let connector_index = match cell {
+                SewerCell::Bridge => bridges.get_checked(coord).unwrap(),
+                SewerCell::Door => {
+                    connectors.push(HashSet::new());
+                    connectors.len() - 1
+                }
+                SewerCell::Floor | SewerCell::Wall | SewerCell::Pool => continue,
+            };
+            for direction in CardinalDirection::all() {
+                if let Some(&Some(room)) = rooms.get(coord + direction.coord()) {
+                    connectors[connector_index].insert(room);
+                }
+            }
+        }
+        let mut edges = vec![Vec::new(); num_rooms];
+        for connector in connectors {
+            let connector = connector.into_iter().collect::<Vec<_>>();
+            for (i, &a) in connector.iter().enumerate() {
+                for &b in &connector[(i + 1)..] {
+                    edges[a].push(b);
+                    edges[b].push(a);
+                }
+            }
+        }
+        Self { edges }
+    }
+
+    fn count_routes(&self, from: usize, to: usize) -> usize {
+        let mut visited = vec![false; self.edges.len()];
+        let mut num_routes = 0;
+        let mut steps = 0;
+        self.count_routes_rec(from, to, &mut visited, &mut num_routes, &mut steps);
+        num_routes
+    }
+
+    fn count_routes_rec(
+        &self,
+        current: usize,
+        to: usize,
+        visited: &mut Vec<bool>,
+        num_routes: &mut usize,
+        steps: &mut usize,
+    ) {
+        *steps += 1;
+        if *num_routes >= MAX_ROUTES || *steps >= MAX_ROUTE_SEARCH_STEPS {
+            return;
+        }
+        if current == to {
+            *num_routes += 1;
+            return;
+        }
+        visited[current] = true;
+        for &next in self.edges[current].iter() {
+            if !visited[next] {
+                self.count_routes_rec(next, to, visited, num_routes, steps);
+            }
+        }
+        visited[current] = false;
+    }
+}
+
+impl SewerMetrics {
+    pub fn new(sewer: &Sewer) -> Self {
+        let grid = &sewer.map;
+        let count = |f: fn(SewerCell) -> bool| grid.iter().filter(|&&cell| f(cell)).count();
+        let walkable_area = count(SewerCell::is_walkable);
+        let num_doors = count(|cell| cell == SewerCell::Door);
+        let pool_area = count(|cell| cell == SewerCell::Pool);
+        let (_, num_bridges) = classify_components(grid, |cell| cell == SewerCell::Bridge);
+        let (rooms, num_rooms) = classify_components(grid, |cell| cell == SewerCell::Floor);
+        let num_routes = match (
+            rooms.get(sewer.start).cloned().flatten(),
+            rooms.get(sewer.goal).cloned().flatten(),
+        ) {
+            (Some(start_room), Some(goal_room)) => {
+                RoomGraph::new(grid, &rooms, num_rooms).count_routes(start_room, goal_room)
+            }
+            _ => 0,
+        };
+        let num_dead_ends = grid
+            .enumerate()
+            .filter(|&(coord, &cell)| {
+                cell.is_walkable()
+                    && CardinalDirection::all()
+                        .filter(|d| {
+                            grid.get(coord + d.coord())
+                                .map(|&cell| cell.is_walkable())
+                                .unwrap_or(false)
+                        })
+                        .count()
+                        == 1
+            })
+            .count();
+        Self {
+            walkable_area,
+            num_rooms,
+            num_doors,
+            num_bridges,
+            pool_area,
+            shortest_path_length: shortest_path_length(grid, sewer.start, sewer.goal),
+            num_routes,
+            num_dead_ends,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use grid_2d::Size;
+
+    fn sewer_from_strs(rows: &[&str], start: Coord, goal: Coord) -> Sewer {
+        let size = Size::new(rows[0].len() as u32, rows.len() as u32);
+        let map = Grid::new_fn(size, |coord| {
+            match rows[coord.y as usize].as_bytes()[coord.x as usize] {
+                b'.' => SewerCell::Floor,
+                b'#' => SewerCell::Wall,
+                b'~' => SewerCell::Pool,
+                b'=' => SewerCell::Bridge,
+                b'+' => SewerCell::Door,
+                other => panic!("unexpected cell: {}", other as char),
+            }
+        });
+        Sewer {
+            start,
+            goal,
+            map,
+            lights: Vec::new(),
+        }
+    }
+
+    fn metrics() -> SewerMetrics {
+        SewerMetrics {
+            walkable_area: 18,
+            num_rooms: 2,
+            num_doors: 1,
+            num_bridges: 1,
+            pool_area: 2,
+            shortest_path_length: Some(6),
+            num_routes: 2,
+            num_dead_ends: 0,
+        }
+    }
+
+    #[test]
+    fn metrics_of_fixed_map() {
+        let sewer = sewer_from_strs(
+            &[
+                "#########",
+                "#...+...#",
+                "#...#~~.#",
+                "#...=...#",
+                "#########",
+            ],
+            Coord::new(1, 1),
+            Coord::new(7, 1),
+        );
+        let metrics = SewerMetrics::new(&sewer);
+        assert_eq!(metrics.walkable_area, 18);
+        assert_eq!(metrics.num_rooms, 2);
+        assert_eq!(metrics.num_doors, 1);
+        assert_eq!(metrics.num_bridges, 1);
+        assert_eq!(metrics.pool_area, 2);
+        assert_eq!(metrics.shortest_path_length, Some(6));
+        assert_eq!(metrics.num_routes, 2);
+        assert_eq!(metrics.num_dead_ends, 0);
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let sewer = sewer_from_strs(
+            &["#######", "#..#..#", "#######"],
+            Coord::new(1, 1),
+            Coord::new(5, 1),
+        );
+        let metrics = SewerMetrics::new(&sewer);
+        assert_eq!(metrics.shortest_path_length, None);
+        assert_eq!(metrics.num_routes, 0);
+        assert_eq!(metrics.num_dead_ends, 4);
+    }
+
+    #[test]
+    fn constraints_bounds() {
+        let metrics = metrics();
+        assert!(SewerConstraints::default().is_satisfied_by(&metrics));
+        let satisfied = |constraints: SewerConstraints| constraints.is_satisfied_by(&metrics);
+        assert!(satisfied(SewerConstraints {
+            walkable_area: Bound::between(18, 18),
+            ..Default::default()
+        }));
+        assert!(!satisfied(SewerConstraints {
+            walkable_area: Bound::at_least(19),
+            ..Default::default()
+        }));
+        assert!(!satisfied(SewerConstraints {
+            num_rooms: Bound::at_most(1),
+            ..Default::default()
+        }));
+        assert!(satisfied(SewerConstraints {
+            num_routes: Bound::at_least(2),
+            num_dead_ends: Bound::at_most(0),
+            ..Default::default()
+        }));
+        assert!(!satisfied(SewerConstraints {
+            shortest_path_length: Bound::between(1, 5),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn constraints_without_path() {
+        let metrics = SewerMetrics {
+            shortest_path_length: None,
+            ..metrics()
+        };
+        assert!(SewerConstraints::default().is_satisfied_by(&metrics));
+        assert!(!SewerConstraints {
+            shortest_path_length: Bound::at_most(100),
+            ..Default::default()
+        }
+        .is_satisfied_by(&metrics));
+    }
+}
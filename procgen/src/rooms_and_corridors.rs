@@ -0,0 +1,188 @@
+use crate::{
+    bridge_pools_between, choose_start_and_goal, ensure_single_connected_area, LevelGenerator,
+    Sewer, SewerCell,
+};
+use direction::CardinalDirection;
+use grid_2d::{coord_2d::Axis, Coord, Grid, Size};
+use rand::{seq::SliceRandom, Rng};
+
+const NUM_ROOM_PLACEMENT_ATTEMPTS: usize = 200;
+const MAX_NUM_ROOMS: usize = 12;
+const MIN_NUM_ROOMS: usize = 3;
+const MIN_ROOM_WIDTH: u32 = 4;
+const MAX_ROOM_WIDTH: u32 = 10;
+const MIN_ROOM_HEIGHT: u32 = 3;
+const MAX_ROOM_HEIGHT: u32 = 7;
+/// Rooms at least this big in both dimensions may have a pool in the middle
+const MIN_POOL_ROOM_SIZE: u32 = 5;
+const POOL_ROOM_PROBABILITY: f64 = 0.4;
+/// Proportion of the rooms that get an extra corridor to another random room, creating loops
+const EXTRA_CORRIDOR_PROPORTION: f64 = 0.3;
+const DOOR_PROBABILITY: f64 = 0.5;
+
+/// Generates rectangular rooms joined by narrow corridors. Corridors which cross a pool become
+/// bridges, and the places where corridors enter rooms may become doors.
+#[derive(Clone, Copy)]
+pub struct RoomsAndCorridorsGenerator;
+
+#[derive(Clone, Copy)]
+struct Room {
+    top_left: Coord,
+    size: Size,
+}
+
+impl Room {
+    fn bottom_right(&self) -> Coord {
+        self.top_left + self.size.to_coord().unwrap() - Coord::new(1, 1)
+    }
+    fn centre(&self) -> Coord {
+        self.top_left + self.size.to_coord().unwrap() / 2
+    }
+    /// Rooms must be separated by at least two cells of wall so their walls don't touch
+    fn overlaps_with_margin(&self, other: &Room) -> bool {
+        let a_bottom_right = self.bottom_right();
+        let b_bottom_right = other.bottom_right();
+        self.top_left.x <= b_bottom_right.x + 2
+            && other.top_left.x <= a_bottom_right.x + 2
+            && self.top_left.y <= b_bottom_right.y + 2
+            && other.top_left.y <= a_bottom_right.y + 2
+    }
+    /// Coords of the wall surrounding the room, excluding corners, with the axis along which
+    /// the wall runs
+    fn wall_coords(&self) -> Vec<(Coord, Axis)> {
+        let bottom_right = self.bottom_right();
+        let mut coords = Vec::new();
+        for x in self.top_left.x..=bottom_right.x {
+            coords.push((Coord::new(x, self.top_left.y - 1), Axis::X));
+            coords.push((Coord::new(x, bottom_right.y + 1), Axis::X));
+        }
+        for y in self.top_left.y..=bottom_right.y {
+            coords.push((Coord::new(self.top_left.x - 1, y), Axis::Y));
+            coords.push((Coord::new(bottom_right.x + 1, y), Axis::Y));
+        }
+        coords
+    }
+    fn choose<R: Rng>(map_size: Size, rng: &mut R) -> Option<Self> {
+        let width = rng.gen_range(MIN_ROOM_WIDTH..=MAX_ROOM_WIDTH);
+        let height = rng.gen_range(MIN_ROOM_HEIGHT..=MAX_ROOM_HEIGHT);
+        // leave room for the surrounding wall
+        if width + 2 > map_size.width() || height + 2 > map_size.height() {
+            return None;
+        }
+        let x = rng.gen_range(1..=(map_size.width() - width - 1)) as i32;
+        let y = rng.gen_range(1..=(map_size.height() - height - 1)) as i32;
+        Some(Self {
+            top_left: Coord::new(x, y),
+            size: Size::new(width, height),
+        })
+    }
+}
+
+fn carve_corridor<R: Rng>(map: &mut Grid<SewerCell>, from: Coord, to: Coord, rng: &mut R) {
+    let corner = if rng.gen() {
+        Coord::new(to.x, from.y)
+    } else {
+        Coord::new(from.x, to.y)
+    };
+    for &(start, end) in &[(from, corner), (corner, to)] {
+        let mut coord = start;
+        loop {
+            let cell = map.get_checked_mut(coord);
+            *cell = match *cell {
+                SewerCell::Wall => SewerCell::Floor,
+                SewerCell::Pool => SewerCell::Bridge,
+                other => other,
+            };
+            if coord == end {
+                break;
+            }
+            coord = coord + Coord::new((end.x - coord.x).signum(), (end.y - coord.y).signum());
+        }
+    }
+}
+
+fn is_wall(map: &Grid<SewerCell>, coord: Coord) -> bool {
+    map.get(coord)
+        .map(|&cell| cell == SewerCell::Wall)
+        .unwrap_or(true)
+}
+
+fn add_doors<R: Rng>(map: &mut Grid<SewerCell>, rooms: &[Room], rng: &mut R) {
+    for room in rooms {
+        for (coord, wall_axis) in room.wall_coords() {
+            if map.get(coord).cloned() != Some(SewerCell::Floor) {
+                continue;
+            }
+            // only place doors where a corridor breaches the wall, with wall on either side
+            let (a, b) = match wall_axis {
+                Axis::X => (CardinalDirection::West, CardinalDirection::East),
+                Axis::Y => (CardinalDirection::North, CardinalDirection::South),
+            };
+            if is_wall(map, coord + a.coord())
+                && is_wall(map, coord + b.coord())
+                && rng.gen_bool(DOOR_PROBABILITY)
+            {
+                *map.get_checked_mut(coord) = SewerCell::Door;
+            }
+        }
+    }
+}
+
+impl LevelGenerator for RoomsAndCorridorsGenerator {
+    fn try_generate<R: Rng>(&self, size: Size, rng: &mut R) -> Option<Sewer> {
+        let mut rooms: Vec<Room> = Vec::new();
+        for _ in 0..NUM_ROOM_PLACEMENT_ATTEMPTS {
+            if rooms.len() >= MAX_NUM_ROOMS {
+                break;
+            }
+            if let Some(room) = Room::choose(size, rng) {
+                if !rooms.iter().any(|other| room.overlaps_with_margin(other)) {
+                    rooms.push(room);
+                }
+            }
+        }
+        if rooms.len() < MIN_NUM_ROOMS {
+            return None;
+        }
+        let mut map = Grid::new_clone(size, SewerCell::Wall);
+        for room in rooms.iter() {
+            let bottom_right = room.bottom_right();
+            let has_pool = room.size.width() >= MIN_POOL_ROOM_SIZE
+                && room.size.height() >= MIN_POOL_ROOM_SIZE
+                && rng.gen_bool(POOL_ROOM_PROBABILITY);
+            for y in room.top_left.y..=bottom_right.y {
+                for x in room.top_left.x..=bottom_right.x {
+                    let coord = Coord::new(x, y);
+                    let is_room_edge = x == room.top_left.x
+                        || y == room.top_left.y
+                        || x == bottom_right.x
+                        || y == bottom_right.y;
+                    *map.get_checked_mut(coord) = if has_pool && !is_room_edge {
+                        SewerCell::Pool
+                    } else {
+                        SewerCell::Floor
+                    };
+                }
+            }
+        }
+        // join the rooms left to right so corridors don't zig-zag across the whole map
+        rooms.sort_by_key(|room| room.centre().x);
+        for pair in rooms.windows(2) {
+            carve_corridor(&mut map, pair[0].centre(), pair[1].centre(), rng);
+        }
+        let num_extra_corridors = (rooms.len() as f64 * EXTRA_CORRIDOR_PROPORTION) as usize;
+        for _ in 0..num_extra_corridors {
+            let mut pair = rooms.choose_multiple(rng, 2);
+            let a = pair.next().unwrap().centre();
+            let b = pair.next().unwrap().centre();
+            carve_corridor(&mut map, a, b, rng);
+        }
+        add_doors(&mut map, &rooms, rng);
+        ensure_single_connected_area(&mut map)?;
+        let (start, goal) = choose_start_and_goal(&map, rng)?;
+        if !bridge_pools_between(&mut map, start, goal) {
+            return None;
+        }
+        Sewer::new_with_pool_lights(start, goal, map, rng)
+    }
+}
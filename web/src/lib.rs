@@ -26,6 +26,7 @@ pub fn run() -> Result<(), JsValue> {
         Some(AutoPlay),
         None,
         Box::new(EnvNull),
+        None,
     );
     context.run_app(app);
     Ok(())